@@ -0,0 +1,187 @@
+//! 重连后通过 REST 补齐断线期间的K线
+//!
+//! 补齐在单独的任务中进行, 连接继续读取 socket 并回复 ping; 正在补齐的流收到的实时K线先缓存,
+//! 该流补齐完成后再按顺序发送。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use cex_core::registry::StreamRegistry;
+use cex_core::source::ReconnectPolicy;
+use cex_core::{CexError, ChannelMsg, KlineEvent, KlineInterval, SimpleKLine};
+use chrono::Utc;
+use crossbeam::channel::Sender;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::rest::{RestClient, KLINES_LIMIT};
+
+/// 单页请求失败后的最大尝试次数, 仍失败则记为缺口
+pub const BACKFILL_ATTEMPTS: u32 = 3;
+
+/// 每条流最后一根已发送K线的 close_time_ms, 实时推送和补齐任务共享, 用于补齐缺口和去重
+pub(crate) type LastClose = Arc<Mutex<HashMap<usize, u64>>>;
+
+/// 补齐失败、尚未发送的K线区间 [start_ms, end_ms), 连接期间会定期重试
+///
+/// 重试成功后这些K线晚于后续的实时K线到达, 下游需要按 open_time 处理乱序
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gap {
+    pub symbol: String,
+    pub interval: KlineInterval,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+/// 一次连接上的补齐任务
+pub(crate) struct Backfill {
+    pub rest: RestClient,
+    pub policy: ReconnectPolicy,
+    pub registry: StreamRegistry,
+    pub last_close: LastClose,
+    pub gaps: Arc<Mutex<Vec<Gap>>>,
+    pub tx: Sender<ChannelMsg>,
+}
+
+impl Backfill {
+    /// 依次补齐 `streams` 中的流 (index, symbol, interval), 每条流完成后通过 `done_tx` 通知连接;
+    /// 之后重试记录的缺口, 直到全部补齐或连接断开
+    pub async fn run(self, streams: Vec<(usize, String, KlineInterval)>, done_tx: mpsc::UnboundedSender<usize>) {
+        let end_ms = Utc::now().timestamp_millis() as u64;
+        for (index, symbol, interval) in streams {
+            let last = self.last_close.lock().unwrap().get(&index).copied();
+            if let Some(last) = last {
+                let start_ms = self.skip_gaps(&symbol, interval, last + 1);
+                match self.fetch(index, &symbol, interval, start_ms, end_ms, false).await {
+                    Ok(count) if count > 0 => info!("补齐K线 {} {}: {} 根", symbol, interval, count),
+                    Ok(_) => {}
+                    Err((start_ms, e)) => self.record_gap(Gap { symbol, interval, start_ms, end_ms }, e),
+                }
+            }
+            let _ = done_tx.send(index);
+        }
+
+        let mut round = 0;
+        loop {
+            let gaps = self.gaps.lock().unwrap().clone();
+            if gaps.is_empty() {
+                break;
+            }
+            round += 1;
+            tokio::time::sleep(self.policy.delay(round)).await;
+            for gap in gaps {
+                let Some(index) = self.registry.get(&gap.symbol, gap.interval) else { continue };
+                let result = self.fetch(index, &gap.symbol, gap.interval, gap.start_ms, gap.end_ms, true).await;
+                let mut current = self.gaps.lock().unwrap();
+                let Some(pos) = current.iter().position(|g| *g == gap) else { continue };
+                match result {
+                    Ok(count) => {
+                        info!("补齐缺口 {} {} [{}, {}): {} 根", gap.symbol, gap.interval, gap.start_ms, gap.end_ms, count);
+                        current.remove(pos);
+                    }
+                    Err((start_ms, e)) => {
+                        warn!("补齐缺口失败 {} {} [{}, {}): {}", gap.symbol, gap.interval, start_ms, gap.end_ms, e);
+                        current[pos].start_ms = start_ms;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 已记为缺口的区间由缺口重试负责, 重连后从缺口之后开始补齐
+    fn skip_gaps(&self, symbol: &str, interval: KlineInterval, mut start_ms: u64) -> u64 {
+        for gap in self.gaps.lock().unwrap().iter() {
+            if gap.symbol == symbol && gap.interval == interval && gap.start_ms <= start_ms {
+                start_ms = start_ms.max(gap.end_ms);
+            }
+        }
+        start_ms
+    }
+
+    /// 分页补齐 [start_ms, end_ms) 内已收盘的K线, 返回发送的根数; 失败时返回尚未补齐的起点
+    ///
+    /// `late` 为 true 时是重试缺口, 不按 last_close 去重
+    async fn fetch(
+        &self,
+        index: usize,
+        symbol: &str,
+        interval: KlineInterval,
+        mut start_ms: u64,
+        end_ms: u64,
+        late: bool,
+    ) -> Result<usize, (u64, anyhow::Error)> {
+        let mut count = 0;
+        loop {
+            let klines = self.request(symbol, interval, start_ms, end_ms).await.map_err(|e| (start_ms, e))?;
+            let page_full = klines.len() >= KLINES_LIMIT as usize;
+            // 仍未收盘的K线等待实时推送
+            let closed = klines.into_iter().take_while(|k| k.close_time_ms < end_ms).collect::<Vec<_>>();
+            let reached_end = closed.len() < KLINES_LIMIT as usize;
+            if let Some(last) = closed.last() {
+                start_ms = last.close_time_ms + 1;
+            }
+            count += self.send(index, closed, late).await;
+            if !page_full || reached_end {
+                return Ok(count);
+            }
+        }
+    }
+
+    /// 请求一页K线, 失败时按重连策略等待后重试, 限频时至少等待 `Retry-After`
+    async fn request(&self, symbol: &str, interval: KlineInterval, start_ms: u64, end_ms: u64) -> Result<Vec<SimpleKLine>> {
+        let mut attempt = 0;
+        loop {
+            match self.rest.klines(symbol, interval, Some(start_ms), Some(end_ms), KLINES_LIMIT).await {
+                Ok(klines) => return Ok(klines),
+                Err(e) => {
+                    attempt += 1;
+                    if attempt >= BACKFILL_ATTEMPTS {
+                        return Err(e);
+                    }
+                    let mut delay = self.policy.delay(attempt);
+                    if let Some(retry_after) = self.rest.rate_limit().retry_after {
+                        delay = delay.max(retry_after);
+                    }
+                    warn!("补齐K线失败 {} {}, {:?} 后重试 ({}/{}): {}", symbol, interval, delay, attempt, BACKFILL_ATTEMPTS, e);
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// 阻塞发送一页K线, 等待下游消费而不是丢弃; 在阻塞线程中进行, 不占用运行时
+    async fn send(&self, index: usize, klines: Vec<SimpleKLine>, late: bool) -> usize {
+        let tx = self.tx.clone();
+        let last_close = self.last_close.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut count = 0;
+            for kline in klines {
+                if !late {
+                    let mut last_close = last_close.lock().unwrap();
+                    if last_close.get(&index).is_some_and(|last| kline.close_time_ms <= *last) {
+                        continue;
+                    }
+                    last_close.insert(index, kline.close_time_ms);
+                }
+                if tx.send(ChannelMsg::Kline(KlineEvent { index, kline, is_final: true })).is_err() {
+                    break;
+                }
+                count += 1;
+            }
+            count
+        })
+        .await
+        .unwrap_or(0)
+    }
+
+    /// 记录缺口并通知下游, 缺口之后的实时K线照常发送
+    fn record_gap(&self, gap: Gap, e: anyhow::Error) {
+        error!("补齐K线失败 {} {} [{}, {}): {}", gap.symbol, gap.interval, gap.start_ms, gap.end_ms, e);
+        let msg = format!("backfill {} {} [{}, {}): {}", gap.symbol, gap.interval, gap.start_ms, gap.end_ms, e);
+        self.gaps.lock().unwrap().push(gap);
+        if let Err(e) = self.tx.try_send(ChannelMsg::Error(CexError::NetworkError(msg))) {
+            error!("Failed to send error message: {}", e);
+        }
+    }
+}
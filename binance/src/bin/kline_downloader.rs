@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use binance::download::{download_klines, resume_from, Download};
+use binance::rest::{RestClient, BINANCE_REST_URL};
+use cex_core::writer::{create_writer, FileWriterConfig, WriterType};
+use cex_core::KlineInterval;
+use chrono::{NaiveDate, Utc};
+use clap::Parser;
+use tracing::info;
+
+/// 从 binance 分页下载历史K线, 按 FileWriter 的 kline_YYYYMMDD-HHMM.zst 轮转格式写入
+///
+/// 每行是一根K线的 json; 早期实时录制写入的 `[index, kline]` 行仍可由 FileReader 读取
+#[derive(Parser, Debug)]
+struct Args {
+    /// 交易对, 如 btcusdt
+    #[arg(long)]
+    symbol: String,
+    /// K线周期, 如 1m
+    #[arg(long)]
+    interval: KlineInterval,
+    /// 开始日期（UTC）, 如 2025-06-01
+    #[arg(long)]
+    start: NaiveDate,
+    /// 结束日期（UTC, 不含）, 默认下载到当前时间
+    #[arg(long)]
+    end: Option<NaiveDate>,
+    /// 输出目录, 已有数据时从最后一条记录继续下载
+    #[arg(long, default_value = "data")]
+    output_dir: PathBuf,
+    /// 文件轮转间隔（秒）, 需与实时录制保持一致
+    #[arg(long, default_value_t = 8 * 3600)]
+    rotation_interval: i64,
+    /// 分区目录模板, 如 {exchange}/{symbol}/{interval}, 需与实时录制保持一致
+    #[arg(long)]
+    partition: Option<String>,
+    #[arg(long, default_value = BINANCE_REST_URL)]
+    rest_url: String,
+    /// 每分钟权重上限（binance 为 6000）, 达到后等待到下一分钟
+    #[arg(long, default_value_t = 5000)]
+    max_weight: u32,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt().init();
+    let args = Args::parse();
+
+    let start_ms = args.start.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_millis() as u64;
+    let end_ms = args
+        .end
+        .map(|end| end.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_millis() as u64)
+        .unwrap_or(Utc::now().timestamp_millis() as u64);
+
+    fs::create_dir_all(&args.output_dir)?;
+    let start_ms = resume_from(&args.output_dir, args.partition.as_deref(), &args.symbol, args.interval, start_ms)?;
+
+    let writer = create_writer(WriterType::File(FileWriterConfig {
+        base_path: args.output_dir.clone(),
+        rotation_interval: args.rotation_interval,
+        partition: args.partition.clone(),
+        ..Default::default()
+    }))?;
+    let rest = RestClient::new(&args.rest_url);
+
+    let download = Download {
+        symbol: args.symbol,
+        interval: args.interval,
+        start_ms,
+        end_ms,
+        max_weight: args.max_weight,
+    };
+    let count = download_klines(&rest, &writer, &download).await?;
+    writer.close().await?;
+
+    info!("下载完成, 共 {} 根K线", count);
+    Ok(())
+}
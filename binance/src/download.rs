@@ -0,0 +1,109 @@
+//! 分页下载历史K线, 写入 FileWriter 的归档目录
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use cex_core::reader::{FileReader, KlineFilter};
+use cex_core::writer::Writer;
+use cex_core::KlineInterval;
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::rest::{RestClient, KLINES_LIMIT};
+
+/// 一次下载任务
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub symbol: String,
+    pub interval: KlineInterval,
+    /// 从该时间开始的K线, 已有数据时用 [`resume_from`] 计算
+    pub start_ms: u64,
+    /// 开盘时间早于该时间的K线
+    pub end_ms: u64,
+    /// 每分钟权重上限（binance 为 6000）, 达到后等待到下一分钟
+    pub max_weight: u32,
+}
+
+/// 下载 [start_ms, end_ms) 内已收盘的K线并写入 `writer`, 返回写入的根数
+///
+/// 被限频（429/418）时按 `Retry-After` 等待后重试; 已用权重达到上限时等待到下一分钟
+pub async fn download_klines(rest: &RestClient, writer: &Writer, download: &Download) -> Result<usize> {
+    let now_ms = Utc::now().timestamp_millis() as u64;
+    let end_ms = download.end_ms.min(now_ms);
+    let mut cursor = download.start_ms;
+    let mut count = 0usize;
+    while cursor < end_ms {
+        let klines = match rest.klines(&download.symbol, download.interval, Some(cursor), Some(end_ms - 1), KLINES_LIMIT).await {
+            Ok(klines) => klines,
+            Err(e) => match rest.rate_limit().retry_after {
+                Some(wait) => {
+                    warn!("触发限频, 等待 {:?}: {}", wait, e);
+                    tokio::time::sleep(wait).await;
+                    continue;
+                }
+                None => return Err(e),
+            },
+        };
+        let page_len = klines.len();
+
+        let mut reached_open_bar = false;
+        for kline in klines {
+            // 未收盘的K线不写入, 下次从这里继续
+            if kline.close_time_ms >= now_ms {
+                reached_open_bar = true;
+                break;
+            }
+            writer.write(&kline).await?;
+            cursor = kline.close_time_ms + 1;
+            count += 1;
+        }
+        writer.flush().await?;
+        info!("已下载 {} 根K线, 进度: {}", count, DateTime::<Utc>::from_timestamp_millis(cursor as i64).unwrap());
+
+        if page_len < KLINES_LIMIT as usize || reached_open_bar {
+            break;
+        }
+
+        let used_weight = rest.rate_limit().used_weight_1m;
+        if let Some(wait) = weight_wait(used_weight, download.max_weight, Utc::now().timestamp_millis() as u64) {
+            info!("当前分钟权重 {} 已达上限 {}, 等待 {:?}", used_weight, download.max_weight, wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+    Ok(count)
+}
+
+/// 已用权重达到上限时需要等待的时间: 到下一分钟再多等 1s, 未达上限返回 None
+pub fn weight_wait(used_weight: u32, max_weight: u32, now_ms: u64) -> Option<Duration> {
+    (used_weight >= max_weight).then(|| Duration::from_millis(60_000 - now_ms % 60_000 + 1_000))
+}
+
+/// 断点续传: 归档中已有该 (symbol, interval) 的数据且晚于 `start_ms` 时, 从最后一条之后开始
+pub fn resume_from(dir: &Path, partition: Option<&str>, symbol: &str, interval: KlineInterval, start_ms: u64) -> Result<u64> {
+    match last_close_time(dir, partition, symbol, interval)? {
+        Some(last_close) if last_close >= start_ms => {
+            info!("从已有数据继续下载: 最后一条收盘时间 {}", last_close);
+            Ok(last_close + 1)
+        }
+        _ => Ok(start_ms),
+    }
+}
+
+/// 在已有归档中查找该 (symbol, interval) 最后一条记录的收盘时间
+fn last_close_time(dir: &Path, partition: Option<&str>, symbol: &str, interval: KlineInterval) -> Result<Option<u64>> {
+    let reader = FileReader::new(dir).with_partition(partition.map(str::to_string));
+    let filter = KlineFilter {
+        symbols: vec![symbol.to_string()],
+        intervals: vec![interval],
+        ..Default::default()
+    };
+    // 从最新的文件开始找
+    for path in reader.files(&filter)?.iter().rev() {
+        let last = reader.read_file(path, &filter).map(|kline| kline.close_time_ms).max();
+        if last.is_some() {
+            return Ok(last);
+        }
+    }
+    Ok(None)
+}
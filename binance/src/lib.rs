@@ -1,15 +1,30 @@
-use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
-use cex_core::{ChannelMsg, Ping, SimpleKLine};
+mod backfill;
+pub mod download;
+pub mod rest;
+
+pub use backfill::{Gap, BACKFILL_ATTEMPTS};
+
+use backfill::{Backfill, LastClose};
+use rest::{RestClient, BINANCE_REST_URL};
+
+use cex_core::registry::StreamRegistry;
+use cex_core::source::{MarketDataSource, ReconnectPolicy, Reconnector, SubscribeOptions};
+use cex_core::{CexError, ChannelMsg, Decimal, KlineEvent, KlineInterval, Ping, SimpleKLine};
 
 use crossbeam::channel::Sender;
 
 use anyhow::Result;
+use async_trait::async_trait;
 use chrono::{TimeZone, Utc};
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message, WebSocketStream};
 use tracing::{debug, error, info, warn};
 
@@ -69,8 +84,9 @@ struct BNKline {
     end_time: i64,
     #[serde(rename = "s")]
     symbol: String,
+    /// binance 的周期名称与 `KlineInterval` 的字符串形式相同
     #[serde(rename = "i")]
-    interval: String,
+    interval: KlineInterval,
     #[serde(rename = "o")]
     open: String,
     #[serde(rename = "c")]
@@ -81,54 +97,490 @@ struct BNKline {
     low: String,
     #[serde(rename = "v")]
     volume: String,
+    #[serde(rename = "q", default)]
+    quote_volume: Option<String>,
+    #[serde(rename = "V", default)]
+    taker_buy_base_volume: Option<String>,
+    #[serde(rename = "Q", default)]
+    taker_buy_quote_volume: Option<String>,
+    #[serde(rename = "f", default)]
+    first_trade_id: Option<i64>,
+    #[serde(rename = "L", default)]
+    last_trade_id: Option<i64>,
     #[serde(rename = "n")]
     number_of_trades: i32,
     #[serde(rename = "x")]
     is_closed: bool,
 }
 
+/// binance 现货支持的K线周期
+pub const BINANCE_INTERVALS: &[KlineInterval] = KlineInterval::ALL;
+
+/// binance 组合流地址
+pub const BINANCE_WS_URL: &str = "wss://stream.binance.com:9443/stream";
+
+/// binance 连接配置
+#[derive(Debug, Clone)]
+pub struct BinanceConfig {
+    /// 组合流 websocket 地址, 测试时可指向本地服务
+    pub ws_url: String,
+    /// 断线重连策略
+    pub reconnect: ReconnectPolicy,
+    /// REST 地址, 重连后通过 `/api/v3/klines` 补齐断线期间的K线, None 表示不补齐
+    pub rest_url: Option<String>,
+}
+
+impl Default for BinanceConfig {
+    fn default() -> Self {
+        Self {
+            ws_url: BINANCE_WS_URL.to_string(),
+            rest_url: Some(BINANCE_REST_URL.to_string()),
+            reconnect: ReconnectPolicy::default(),
+        }
+    }
+}
+
+/// binance 行情数据源
+#[derive(Default)]
+pub struct BinanceSource {
+    config: BinanceConfig,
+    handles: Mutex<Vec<BinanceHandle>>,
+}
+
+impl BinanceSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: BinanceConfig) -> Self {
+        Self {
+            config,
+            handles: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for BinanceSource {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    fn supported_intervals(&self) -> &'static [KlineInterval] {
+        BINANCE_INTERVALS
+    }
+
+    async fn subscribe_with(
+        &self,
+        pair_list: Vec<(String, KlineInterval)>,
+        options: SubscribeOptions,
+        tx: Sender<ChannelMsg>,
+    ) -> Result<()> {
+        self.check_intervals(&pair_list)?;
+        let mut handles = self.handles.lock().unwrap();
+        // 同一个 channel 复用已有连接, 在线增加订阅
+        match handles.iter().find(|h| h.tx.same_channel(&tx)) {
+            Some(handle) => handle.subscribe_with(pair_list, options)?,
+            None => handles.push(subscribe_binance_with_options(self.config.clone(), pair_list, options, tx)),
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, pair_list: Vec<(String, KlineInterval)>) -> Result<()> {
+        for handle in self.handles.lock().unwrap().iter() {
+            handle.unsubscribe(pair_list.clone())?;
+        }
+        Ok(())
+    }
+}
+
+enum Control {
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+    List(oneshot::Sender<Vec<String>>),
+}
+
+/// binance 连接的控制句柄
+///
+/// 记录当前订阅的 (symbol, interval) 集合, 在线发送 `SUBSCRIBE`/`UNSUBSCRIBE`,
+/// 断线重连后按当前集合重新订阅。句柄全部释放后后台连接随之停止。
+#[derive(Clone)]
+pub struct BinanceHandle {
+    pair_list: Arc<Mutex<Vec<(String, KlineInterval)>>>,
+    registry: StreamRegistry,
+    gaps: Arc<Mutex<Vec<Gap>>>,
+    ctrl_tx: mpsc::UnboundedSender<Control>,
+    tx: Sender<ChannelMsg>,
+    task: Arc<AbortOnDrop>,
+}
+
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl BinanceHandle {
+    /// 在线增加订阅, 使用默认的订阅选项
+    pub fn subscribe(&self, pair_list: Vec<(String, KlineInterval)>) -> Result<()> {
+        self.subscribe_with(pair_list, SubscribeOptions::default())
+    }
+
+    /// 与 [`Self::subscribe`] 相同, 使用指定的订阅选项, 已订阅的只更新选项
+    pub fn subscribe_with(&self, pair_list: Vec<(String, KlineInterval)>, options: SubscribeOptions) -> Result<()> {
+        // 持有锁时发送, 重连时按同一把锁取订阅集合并丢弃排队的变更, 两者不会重复
+        let mut current = self.pair_list.lock().unwrap();
+        let mut added = Vec::new();
+        for pair in pair_list {
+            let index = self.registry.register(&pair.0, pair.1);
+            self.registry.set_partial(index, options.partial);
+            if !current.contains(&pair) {
+                current.push(pair.clone());
+                added.push(pair);
+            }
+        }
+        if !added.is_empty() {
+            info!("binance 增加订阅: {:?}", added);
+            self.send(Control::Subscribe(added.iter().map(stream_name).collect()))?;
+        }
+        Ok(())
+    }
+
+    /// 在线取消订阅
+    pub fn unsubscribe(&self, pair_list: Vec<(String, KlineInterval)>) -> Result<()> {
+        let mut current = self.pair_list.lock().unwrap();
+        let removed = current.iter().filter(|p| pair_list.contains(p)).cloned().collect::<Vec<_>>();
+        current.retain(|p| !pair_list.contains(p));
+        if !removed.is_empty() {
+            info!("binance 取消订阅: {:?}", removed);
+            self.send(Control::Unsubscribe(removed.iter().map(stream_name).collect()))?;
+        }
+        Ok(())
+    }
+
+    /// 本地记录的当前订阅
+    pub fn pair_list(&self) -> Vec<(String, KlineInterval)> {
+        self.pair_list.lock().unwrap().clone()
+    }
+
+    /// K线流编号表, 与 `ChannelMsg::Kline` 中的 index 对应
+    pub fn registry(&self) -> &StreamRegistry {
+        &self.registry
+    }
+
+    /// 补齐失败、仍在重试的K线区间
+    pub fn gaps(&self) -> Vec<Gap> {
+        self.gaps.lock().unwrap().clone()
+    }
+
+    /// 通过 `LIST_SUBSCRIPTIONS` 查询服务端实际的订阅, 返回 stream 名称
+    pub async fn list_subscriptions(&self) -> Result<Vec<String>> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.send(Control::List(reply_tx))?;
+        Ok(reply_rx.await?)
+    }
+
+    /// 停止后台连接
+    pub fn stop(&self) {
+        self.task.0.abort();
+    }
+
+    fn send(&self, ctrl: Control) -> Result<()> {
+        self.ctrl_tx.send(ctrl).map_err(|_| anyhow::anyhow!("binance 连接已停止"))
+    }
+}
+
 /// (code, interval), sender
-/// ("btcusdt", "1m")
-pub async fn subscribe_binance(pair_list: Vec<(String, String)>, tx: Sender<ChannelMsg>) {
-    info!("subscribe to binance: {:?}", pair_list);
-    // let pair_list = pair_list.iter().map(|(symbol, interval)| (symbol.to_string(), interval.to_string())).collect::<Vec<(String, String)>>();
-    loop { // 出错自动重连， binance 24h 会断开连接
-        if let Err(e) = connect_binance(pair_list.clone(), tx.clone()).await {
-            error!("Failed to connect to Binance: {}", e);
+/// ("btcusdt", KlineInterval::OneMinute)
+///
+/// 在后台建立连接并自动重连, 返回可在线增减订阅的控制句柄
+///
+/// 不兼容的变更: 原来是 `async fn`, `.await` 后一直运行直到连接结束; 现在是普通函数, 立即返回句柄,
+/// 需要在 tokio 运行时中调用, 且必须持有返回的句柄, 句柄全部释放后连接停止
+#[must_use = "句柄全部释放后连接停止"]
+pub fn subscribe_binance(pair_list: Vec<(String, KlineInterval)>, tx: Sender<ChannelMsg>) -> BinanceHandle {
+    subscribe_binance_with_config(BinanceConfig::default(), pair_list, tx)
+}
+
+/// 与 [`subscribe_binance`] 相同, 使用指定的连接配置
+#[must_use = "句柄全部释放后连接停止"]
+pub fn subscribe_binance_with_config(
+    config: BinanceConfig,
+    pair_list: Vec<(String, KlineInterval)>,
+    tx: Sender<ChannelMsg>,
+) -> BinanceHandle {
+    subscribe_binance_with_options(config, pair_list, SubscribeOptions::default(), tx)
+}
+
+/// 与 [`subscribe_binance_with_config`] 相同, 使用指定的订阅选项
+#[must_use = "句柄全部释放后连接停止"]
+pub fn subscribe_binance_with_options(
+    config: BinanceConfig,
+    pair_list: Vec<(String, KlineInterval)>,
+    options: SubscribeOptions,
+    tx: Sender<ChannelMsg>,
+) -> BinanceHandle {
+    info!("subscribe to binance: {:?} {:?}", pair_list, options);
+    let registry = StreamRegistry::new(&pair_list);
+    for index in 0..registry.len() {
+        registry.set_partial(index, options.partial);
+    }
+    let pair_list = Arc::new(Mutex::new(pair_list));
+    let (ctrl_tx, mut ctrl_rx) = mpsc::unbounded_channel();
+    let gaps = Arc::new(Mutex::new(Vec::new()));
+
+    let mut feed = Feed {
+        rest: config.rest_url.as_deref().map(RestClient::new),
+        config,
+        pair_list: pair_list.clone(),
+        registry: registry.clone(),
+        last_close: LastClose::default(),
+        gaps: gaps.clone(),
+        backfilling: HashMap::new(),
+        tx: tx.clone(),
+    };
+    let task = tokio::spawn(async move {
+        let mut reconnector = Reconnector::new("binance", feed.config.reconnect.clone(), feed.tx.clone());
+        loop { // 出错自动重连， binance 24h 会断开连接
+            let reason = match connect_binance(&mut feed, &mut ctrl_rx, &mut reconnector).await {
+                Ok(()) => "connection closed".to_string(),
+                Err(e) => {
+                    error!("Failed to connect to Binance: {}", e);
+                    e.to_string()
+                }
+            };
+            if !reconnector.disconnected(reason).await {
+                break;
+            }
         }
+    });
+
+    BinanceHandle {
+        pair_list,
+        registry,
+        gaps,
+        ctrl_tx,
+        tx,
+        task: Arc::new(AbortOnDrop(task)),
     }
 }
 
-async fn connect_binance(pair_list: Vec<(String, String)>, tx: Sender<ChannelMsg>) -> anyhow::Result<()> {
+/// 跨重连保留的订阅状态
+struct Feed {
+    config: BinanceConfig,
+    rest: Option<RestClient>,
+    pair_list: Arc<Mutex<Vec<(String, KlineInterval)>>>,
+    registry: StreamRegistry,
+    last_close: LastClose,
+    gaps: Arc<Mutex<Vec<Gap>>>,
+    /// 当前连接上正在补齐的流, 期间收到的实时K线暂存在这里
+    backfilling: HashMap<usize, Vec<SimpleKLine>>,
+    tx: Sender<ChannelMsg>,
+}
+
+impl Feed {
+    /// 发送一根已完结的K线, 已发送过的（close_time 不大于上一根）直接丢弃; 正在补齐的流先暂存
+    fn emit(&mut self, index: usize, kline: SimpleKLine) -> bool {
+        if let Some(pending) = self.backfilling.get_mut(&index) {
+            pending.push(kline);
+            return false;
+        }
+        {
+            let mut last_close = self.last_close.lock().unwrap();
+            if last_close.get(&index).is_some_and(|last| kline.close_time_ms <= *last) {
+                debug!("忽略重复K线: {} {} {}", kline.symbol, kline.interval, kline.open_time_h);
+                return false;
+            }
+            last_close.insert(index, kline.close_time_ms);
+        }
+        if let Err(e) = self.tx.try_send(ChannelMsg::Kline(KlineEvent { index, kline, is_final: true })) {
+            error!("Failed to handle kline data: {}", e);
+        }
+        true
+    }
+
+    /// 发送一根未收盘K线的更新, 不影响去重和补齐; 正在补齐的流不发送
+    fn emit_partial(&self, index: usize, kline: SimpleKLine) {
+        if self.backfilling.contains_key(&index)
+            || self.last_close.lock().unwrap().get(&index).is_some_and(|last| kline.close_time_ms <= *last)
+        {
+            return;
+        }
+        if let Err(e) = self.tx.try_send(ChannelMsg::Kline(KlineEvent { index, kline, is_final: false })) {
+            error!("Failed to handle kline data: {}", e);
+        }
+    }
+
+    /// 启动补齐任务: 断线期间错过的K线, 以及此前补齐失败的缺口
+    fn start_backfill(&mut self) -> Option<(AbortOnDrop, mpsc::UnboundedReceiver<usize>)> {
+        self.backfilling.clear();
+        let rest = self.rest.clone()?;
+        let streams = {
+            let last_close = self.last_close.lock().unwrap();
+            self.pair_list
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|(symbol, interval)| {
+                    let index = self.registry.get(symbol, *interval)?;
+                    last_close.contains_key(&index).then(|| (index, symbol.clone(), *interval))
+                })
+                .collect::<Vec<_>>()
+        };
+        if streams.is_empty() && self.gaps.lock().unwrap().is_empty() {
+            return None;
+        }
+        for (index, ..) in &streams {
+            self.backfilling.insert(*index, Vec::new());
+        }
+        let backfill = Backfill {
+            rest,
+            policy: self.config.reconnect.clone(),
+            registry: self.registry.clone(),
+            last_close: self.last_close.clone(),
+            gaps: self.gaps.clone(),
+            tx: self.tx.clone(),
+        };
+        let (done_tx, done_rx) = mpsc::unbounded_channel();
+        Some((AbortOnDrop(tokio::spawn(backfill.run(streams, done_tx))), done_rx))
+    }
+
+    /// 一条流补齐完成, 按顺序发送期间暂存的实时K线
+    fn backfill_done(&mut self, index: usize) {
+        for kline in self.backfilling.remove(&index).unwrap_or_default() {
+            self.emit(index, kline);
+        }
+    }
+}
+
+fn stream_name((symbol, interval): &(String, KlineInterval)) -> String {
+    format!("{}@kline_{}", symbol.to_lowercase(), interval)
+}
+
+async fn connect_binance(
+    feed: &mut Feed,
+    ctrl_rx: &mut mpsc::UnboundedReceiver<Control>,
+    reconnector: &mut Reconnector,
+) -> anyhow::Result<()> {
     // 用组合流 stream
-    let url = format!("wss://stream.binance.com:9443/stream");
-    let (mut ws_stream, _) = connect_async(url).await?;
+    let (ws_stream, _) = connect_async(&feed.config.ws_url).await?;
     info!("Connected to Binance");
 
-    let subs = json!({
-        "method": "SUBSCRIBE",
-        "params": pair_list.iter().map(|(symbol, interval)| format!("{}@kline_{}", symbol, interval)).collect::<Vec<String>>(),
-        "id": 1
-    });
+    let mut conn = Connection {
+        ws_stream,
+        next_id: 1,
+        pending_list: HashMap::new(),
+    };
+
+    // 按当前集合重新订阅, 断线期间排队的订阅变更已包含在集合中, 丢弃以免重复请求; 查询请求在订阅后处理
+    let mut queued = Vec::new();
+    let streams = {
+        let pair_list = feed.pair_list.lock().unwrap();
+        while let Ok(ctrl) = ctrl_rx.try_recv() {
+            if let Control::List(reply) = ctrl {
+                queued.push(Control::List(reply));
+            }
+        }
+        pair_list.iter().map(stream_name).collect::<Vec<_>>()
+    };
+    if !streams.is_empty() {
+        conn.request("SUBSCRIBE", streams).await?;
+        info!("Subscribed to Binance");
+    }
+    for ctrl in queued {
+        conn.handle_control(ctrl).await?;
+    }
+    reconnector.connected();
+
+    // 补齐与读取 socket 同时进行, 连接结束时补齐任务随之取消
+    let (_backfill, mut done_rx) = match feed.start_backfill() {
+        Some((task, done_rx)) => (Some(task), Some(done_rx)),
+        None => (None, None),
+    };
 
-    ws_stream.send(Message::Text(subs.to_string())).await?;
-    info!("Subscribed to Binance");
-    
-    handle_websocket_stream(ws_stream, tx).await?;
+    handle_websocket_stream(conn, feed, ctrl_rx, &mut done_rx).await?;
 
     Ok(())
 }
 
+struct Connection<S> {
+    ws_stream: WebSocketStream<S>,
+    next_id: u64,
+    /// 等待 LIST_SUBSCRIPTIONS 返回的请求
+    pending_list: HashMap<u64, oneshot::Sender<Vec<String>>>,
+}
+
+impl<S> Connection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn request(&mut self, method: &str, params: Vec<String>) -> Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let req = if params.is_empty() {
+            json!({ "method": method, "id": id })
+        } else {
+            json!({ "method": method, "params": params, "id": id })
+        };
+        self.ws_stream.send(Message::Text(req.to_string())).await?;
+        Ok(id)
+    }
+
+    async fn handle_control(&mut self, ctrl: Control) -> Result<()> {
+        match ctrl {
+            Control::Subscribe(streams) => {
+                self.request("SUBSCRIBE", streams).await?;
+            }
+            Control::Unsubscribe(streams) => {
+                self.request("UNSUBSCRIBE", streams).await?;
+            }
+            Control::List(reply) => {
+                let id = self.request("LIST_SUBSCRIPTIONS", Vec::new()).await?;
+                self.pending_list.insert(id, reply);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 请求的返回: {"result": null, "id": 1} / {"result": ["btcusdt@kline_1m"], "id": 3}
+/// 或错误: {"error": {"code": 2, "msg": "Invalid request"}, "id": 1}
+#[derive(Debug, Deserialize)]
+struct BNResponse {
+    id: u64,
+    #[serde(default)]
+    result: Option<Vec<String>>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
 async fn handle_websocket_stream<S>(
-    mut ws_stream: WebSocketStream<S>,
-    tx: Sender<ChannelMsg>,
+    mut conn: Connection<S>,
+    feed: &mut Feed,
+    ctrl_rx: &mut mpsc::UnboundedReceiver<Control>,
+    done_rx: &mut Option<mpsc::UnboundedReceiver<usize>>,
 ) -> Result<()>
 where
     S: AsyncRead + AsyncWrite + Unpin + Send,
 {
-    let mut m = BTreeMap::new();
-    let mut cnt = 0usize;
-    while let Some(message) = ws_stream.next().await {
+    loop {
+        let message = tokio::select! {
+            message = conn.ws_stream.next() => match message {
+                Some(message) => message,
+                None => break,
+            },
+            Some(ctrl) = ctrl_rx.recv() => {
+                conn.handle_control(ctrl).await?;
+                continue;
+            }
+            Some(index) = async { done_rx.as_mut()?.recv().await } => {
+                feed.backfill_done(index);
+                continue;
+            }
+        };
+
         match message {
             Ok(Message::Text(text)) => match serde_json::from_str::<BNKStreamFrame>(&text) {
                 Ok(frame) => {
@@ -143,31 +595,37 @@ where
                         kline_data.kline.low,
                         kline_data.kline.volume
                     );
-                    let symbol = kline_data.symbol.clone();
-                    let index = match m.get(&symbol) {
-                        Some(v) => *v,
-                        None => {
-                            cnt += 1;
-                            m.insert(symbol, cnt);
-                            cnt
-                        },
+                    let Some(index) = feed.registry.get(&kline_data.symbol, kline_data.kline.interval) else {
+                        warn!("未订阅的K线: {} {}", kline_data.symbol, kline_data.kline.interval);
+                        continue;
                     };
-                    
-                    // 只有当K线周期结束时才发送数据
-                    if kline_data.kline.is_closed {
-                        if let Err(e) = tx.try_send(ChannelMsg::Kline((index, kline_data.into()))) {
-                            error!("Failed to handle kline data: {}", e);
+
+                    // 默认只有当K线周期结束时才发送数据, 开启 partial 的流同时发送未收盘的更新
+                    let is_final = kline_data.kline.is_closed;
+                    if is_final || feed.registry.is_partial(index) {
+                        match SimpleKLine::try_from(kline_data) {
+                            Ok(kline) if is_final => {
+                                feed.emit(index, kline);
+                            }
+                            Ok(kline) => feed.emit_partial(index, kline),
+                            Err(e) => {
+                                error!("Failed to parse binance kline: {}", e);
+                                if let Err(e) = feed.tx.try_send(ChannelMsg::Error(e)) {
+                                    error!("Failed to send error message: {}", e);
+                                }
+                            }
                         }
                     }
                 }
-                Err(_) => {
-                    warn!("ignore msg: {}", text);
-                }
+                Err(_) => match serde_json::from_str::<BNResponse>(&text) {
+                    Ok(resp) => handle_response(&mut conn.pending_list, resp, &feed.tx),
+                    Err(_) => warn!("ignore msg: {}", text),
+                },
             },
             Ok(Message::Ping(ping)) => {
                 info!("收到Ping消息");
-                ws_stream.send(Message::Pong(ping)).await?;
-                if let Err(e) = tx.try_send(ChannelMsg::Ping(Ping::new("binance".to_string(), Utc::now().timestamp_millis()))) {
+                conn.ws_stream.send(Message::Pong(ping)).await?;
+                if let Err(e) = feed.tx.try_send(ChannelMsg::Ping(Ping::new("binance".to_string(), Utc::now().timestamp_millis()))) {
                     error!("Failed to send ping message: {}", e);
                 }
             }
@@ -184,26 +642,53 @@ where
     Ok(())
 }
 
-impl From<BNKlineData> for SimpleKLine {
-    fn from(kline_data: BNKlineData) -> Self {
+fn handle_response(pending_list: &mut HashMap<u64, oneshot::Sender<Vec<String>>>, resp: BNResponse, tx: &Sender<ChannelMsg>) {
+    if let Some(err) = resp.error {
+        error!("binance 请求 {} 失败: {}", resp.id, err);
+        if let Err(e) = tx.try_send(ChannelMsg::Error(CexError::ApiError(err.to_string()))) {
+            error!("Failed to send error message: {}", e);
+        }
+        pending_list.remove(&resp.id);
+        return;
+    }
+    match pending_list.remove(&resp.id) {
+        Some(reply) => {
+            let _ = reply.send(resp.result.unwrap_or_default());
+        }
+        None => debug!("binance 请求 {} 完成", resp.id),
+    }
+}
+
+impl TryFrom<BNKlineData> for SimpleKLine {
+    type Error = CexError;
+
+    fn try_from(kline_data: BNKlineData) -> Result<Self, CexError> {
+        let num = |v: &str| -> Result<Decimal, CexError> {
+            v.parse::<Decimal>().map_err(|e| CexError::ParseError(format!("{}: {}", v, e)))
+        };
         let open_time_dt = Utc.timestamp_opt(kline_data.kline.start_time / 1000, 0)
             .single()
             .map(|dt| dt.with_timezone(&chrono::FixedOffset::east_opt(8 * 3600).unwrap()))
             .unwrap_or_else(|| Utc::now().with_timezone(&chrono::FixedOffset::east_opt(8 * 3600).unwrap()));
-        SimpleKLine {
+        Ok(SimpleKLine {
             exchange: "binance".to_string(),
-            symbol: kline_data.symbol,
             open_time_ms: kline_data.kline.start_time as u64,
             close_time_ms: kline_data.kline.end_time as u64,
             open_time_h: open_time_dt.format("%Y%m%d-%H:%M").to_string(),
-            interval: kline_data.kline.interval.clone(),
-            open: kline_data.kline.open.parse().unwrap_or(0.0),
-            high: kline_data.kline.high.parse().unwrap_or(0.0),
-            low: kline_data.kline.low.parse().unwrap_or(0.0),
-            close: kline_data.kline.close.parse().unwrap_or(0.0),
-            volume: kline_data.kline.volume.parse().unwrap_or(0.0),
-            // quote_volume: 0.0, // Binance API 没有直接提供这个字段
+            interval: kline_data.kline.interval,
+            open: num(&kline_data.kline.open)?,
+            high: num(&kline_data.kline.high)?,
+            low: num(&kline_data.kline.low)?,
+            close: num(&kline_data.kline.close)?,
+            volume: num(&kline_data.kline.volume)?,
+            quote_volume: kline_data.kline.quote_volume.as_deref().map(num).transpose()?,
+            taker_buy_base_volume: kline_data.kline.taker_buy_base_volume.as_deref().map(num).transpose()?,
+            taker_buy_quote_volume: kline_data.kline.taker_buy_quote_volume.as_deref().map(num).transpose()?,
             trades_count: kline_data.kline.number_of_trades as u64,
-        }
+            first_trade_id: kline_data.kline.first_trade_id,
+            last_trade_id: kline_data.kline.last_trade_id,
+            event_time_ms: Some(kline_data.event_time as u64),
+            symbol: kline_data.symbol,
+        })
     }
-}
\ No newline at end of file
+}
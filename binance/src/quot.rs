@@ -144,7 +144,7 @@ impl KlineHandler {
     }
 
     async fn handle_kline(&mut self, kline_data: &BNKlineData) -> Result<()> {
-        let simple_kline = SimpleKLine::from(kline_data.clone());
+        let simple_kline = SimpleKLine::try_from(kline_data.clone())?;
 
         // 检查是否是新的一分钟
         if let Some(current_start_time) = self.current_kline_start_time {
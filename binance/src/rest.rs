@@ -0,0 +1,167 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use cex_core::{CexError, Decimal, KlineInterval, SimpleKLine};
+use chrono::{TimeZone, Utc};
+use serde::{de::IgnoredAny, Deserialize};
+
+/// binance 现货 REST 地址
+pub const BINANCE_REST_URL: &str = "https://api.binance.com";
+
+/// `/api/v3/klines` 单次最多返回的条数
+pub const KLINES_LIMIT: u16 = 1000;
+
+/*
+[
+    1499040000000,      // 开盘时间
+    "0.01634790",       // 开盘价
+    "0.80000000",       // 最高价
+    "0.01575800",       // 最低价
+    "0.01577100",       // 收盘价
+    "148976.11427815",  // 成交量
+    1499644799999,      // 收盘时间
+    "2434.19055334",    // 成交额
+    308,                // 成交笔数
+    "1756.87402397",    // 主动买入成交量
+    "28.46694368",      // 主动买入成交额
+    "0"                 // 忽略
+]
+*/
+#[derive(Debug, Deserialize, Clone)]
+struct BNRestKline(
+    u64,
+    String,
+    String,
+    String,
+    String,
+    String,
+    u64,
+    String,
+    u64,
+    String,
+    String,
+    IgnoredAny,
+);
+
+/// 最近一次请求返回的限频信息
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateLimit {
+    /// `X-MBX-USED-WEIGHT-1M`: 当前分钟已使用的权重
+    pub used_weight_1m: u32,
+    /// 429/418 时 `Retry-After` 要求等待的时间
+    pub retry_after: Option<Duration>,
+}
+
+/// binance REST 行情接口
+#[derive(Clone)]
+pub struct RestClient {
+    base_url: String,
+    client: reqwest::Client,
+    rate_limit: Arc<Mutex<RateLimit>>,
+}
+
+impl RestClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            rate_limit: Arc::new(Mutex::new(RateLimit::default())),
+        }
+    }
+
+    pub fn rate_limit(&self) -> RateLimit {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    /// 查询 [start_ms, end_ms] 内的K线, 按开盘时间升序, 最多 `limit` 条
+    pub async fn klines(
+        &self,
+        symbol: &str,
+        interval: KlineInterval,
+        start_ms: Option<u64>,
+        end_ms: Option<u64>,
+        limit: u16,
+    ) -> Result<Vec<SimpleKLine>> {
+        let symbol = symbol.to_uppercase();
+        let mut query = vec![
+            ("symbol", symbol.clone()),
+            ("interval", interval.as_str().to_string()),
+            ("limit", limit.to_string()),
+        ];
+        if let Some(start_ms) = start_ms {
+            query.push(("startTime", start_ms.to_string()));
+        }
+        if let Some(end_ms) = end_ms {
+            query.push(("endTime", end_ms.to_string()));
+        }
+
+        let resp = self.client
+            .get(format!("{}/api/v3/klines", self.base_url))
+            .query(&query)
+            .send()
+            .await
+            .context("Failed to request klines")?;
+        self.update_rate_limit(&resp);
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(CexError::ApiError(format!("{} {}", status, body)).into());
+        }
+
+        let rows = resp.json::<Vec<BNRestKline>>().await.context("Failed to parse klines")?;
+        rows.into_iter()
+            .map(|row| parse_rest_kline(&symbol, interval, row).map_err(Into::into))
+            .collect()
+    }
+
+    fn update_rate_limit(&self, resp: &reqwest::Response) {
+        let header = |name: &str| -> Option<u64> {
+            resp.headers().get(name)?.to_str().ok()?.parse().ok()
+        };
+        let mut rate_limit = self.rate_limit.lock().unwrap();
+        if let Some(weight) = header("x-mbx-used-weight-1m") {
+            rate_limit.used_weight_1m = weight as u32;
+        }
+        let status = resp.status().as_u16();
+        rate_limit.retry_after = if status == 429 || status == 418 {
+            Some(Duration::from_secs(header("retry-after").unwrap_or(60)))
+        } else {
+            None
+        };
+    }
+}
+
+fn parse_rest_kline(symbol: &str, interval: KlineInterval, row: BNRestKline) -> Result<SimpleKLine, CexError> {
+    let num = |v: &str| -> Result<Decimal, CexError> {
+        v.parse::<Decimal>().map_err(|e| CexError::ParseError(format!("{}: {}", v, e)))
+    };
+    let open_time_h = Utc.timestamp_millis_opt(row.0 as i64)
+        .single()
+        .ok_or_else(|| CexError::ParseError(format!("invalid ts: {}", row.0)))?
+        .with_timezone(&chrono::FixedOffset::east_opt(8 * 3600).unwrap())
+        .format("%Y%m%d-%H:%M")
+        .to_string();
+
+    Ok(SimpleKLine {
+        exchange: "binance".to_string(),
+        symbol: symbol.to_string(),
+        open_time_ms: row.0,
+        close_time_ms: row.6,
+        open_time_h,
+        interval,
+        open: num(&row.1)?,
+        high: num(&row.2)?,
+        low: num(&row.3)?,
+        close: num(&row.4)?,
+        volume: num(&row.5)?,
+        quote_volume: Some(num(&row.7)?),
+        taker_buy_base_volume: Some(num(&row.9)?),
+        taker_buy_quote_volume: Some(num(&row.10)?),
+        trades_count: row.8,
+        // REST 接口不返回成交ID和事件时间
+        first_trade_id: None,
+        last_trade_id: None,
+        event_time_ms: None,
+    })
+}
@@ -0,0 +1,242 @@
+use std::time::Duration;
+
+use binance::{subscribe_binance_with_config, BinanceConfig, BACKFILL_ATTEMPTS};
+use cex_core::source::ReconnectPolicy;
+use cex_core::{ChannelMsg, KlineEvent, KlineInterval};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::protocol::Message};
+
+const T0: u64 = 1748877600000;
+const MINUTE: u64 = 60_000;
+
+fn kline_frame(open_time: u64) -> String {
+    json!({
+        "stream": "btcusdt@kline_1m",
+        "data": {
+            "e": "kline", "E": open_time + MINUTE, "s": "BTCUSDT",
+            "k": {
+                "t": open_time, "T": open_time + MINUTE - 1, "s": "BTCUSDT", "i": "1m",
+                "o": "100.0", "c": "101.0", "h": "102.0", "l": "99.0", "v": "1.5", "n": 10, "x": true
+            }
+        }
+    }).to_string()
+}
+
+fn rest_row(open_time: u64) -> serde_json::Value {
+    json!([open_time, "100.0", "102.0", "99.0", "101.0", "1.5", open_time + MINUTE - 1, "151.5", 10, "0.7", "70.7", "0"])
+}
+
+/// 只处理一次请求的 mock HTTP 服务, 返回请求行
+async fn mock_rest(listener: TcpListener, body: String, request_tx: tokio::sync::oneshot::Sender<String>) {
+    let (request_tx2, mut requests) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(mock_rest_seq(listener, vec![(200, body)], request_tx2));
+    request_tx.send(requests.recv().await.unwrap()).unwrap();
+}
+
+/// 按顺序返回 `responses` 中的 (状态码, 内容) 的 mock HTTP 服务, 每个请求的请求行发送到 `request_tx`
+async fn mock_rest_seq(listener: TcpListener, responses: Vec<(u16, String)>, request_tx: tokio::sync::mpsc::UnboundedSender<String>) {
+    for (status, body) in responses {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        let request = String::from_utf8_lossy(&buf).lines().next().unwrap().to_string();
+        let resp = format!(
+            "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        stream.write_all(resp.as_bytes()).await.unwrap();
+        let _ = request_tx.send(request);
+    }
+}
+
+fn config(ws_url: String, rest_url: String) -> BinanceConfig {
+    BinanceConfig {
+        ws_url,
+        rest_url: Some(rest_url),
+        reconnect: ReconnectPolicy { initial_delay: Duration::from_millis(10), jitter: 0.0, ..Default::default() },
+    }
+}
+
+/// 第一次连接推送 T0 后断开, 第二次连接推送 `frames` 后保持连接
+async fn mock_ws(ws: TcpListener, frames: Vec<u64>) {
+    let (stream, _) = ws.accept().await.unwrap();
+    let mut conn = accept_async(stream).await.unwrap();
+    conn.next().await.unwrap().unwrap();
+    conn.send(Message::Text(kline_frame(T0))).await.unwrap();
+    conn.close(None).await.unwrap();
+    drop(conn);
+
+    let (stream, _) = ws.accept().await.unwrap();
+    let mut conn = accept_async(stream).await.unwrap();
+    conn.next().await.unwrap().unwrap();
+    for open_time in frames {
+        conn.send(Message::Text(kline_frame(open_time))).await.unwrap();
+    }
+    while conn.next().await.is_some() {}
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn backfills_missed_klines_after_reconnect() {
+    let rest = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rest_url = format!("http://{}", rest.local_addr().unwrap());
+    let body = json!([rest_row(T0 + MINUTE), rest_row(T0 + 2 * MINUTE), rest_row(T0 + 3 * MINUTE)]).to_string();
+    let (request_tx, request_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(mock_rest(rest, body, request_tx));
+
+    let ws = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let ws_url = format!("ws://{}", ws.local_addr().unwrap());
+    tokio::spawn(async move {
+        // 第一次连接推送 T0 后断开
+        let (stream, _) = ws.accept().await.unwrap();
+        let mut conn = accept_async(stream).await.unwrap();
+        conn.next().await.unwrap().unwrap();
+        conn.send(Message::Text(kline_frame(T0))).await.unwrap();
+        conn.close(None).await.unwrap();
+        drop(conn);
+
+        // 重连后先推送已经补齐的 T0+3m, 再推送新的 T0+4m
+        let (stream, _) = ws.accept().await.unwrap();
+        let mut conn = accept_async(stream).await.unwrap();
+        conn.next().await.unwrap().unwrap();
+        conn.send(Message::Text(kline_frame(T0 + 3 * MINUTE))).await.unwrap();
+        conn.send(Message::Text(kline_frame(T0 + 4 * MINUTE))).await.unwrap();
+        while conn.next().await.is_some() {}
+    });
+
+    let (tx, rx) = crossbeam::channel::bounded(2);
+    let config = BinanceConfig {
+        ws_url,
+        rest_url: Some(rest_url),
+        reconnect: ReconnectPolicy { initial_delay: Duration::from_millis(10), jitter: 0.0, ..Default::default() },
+    };
+    let _handle = subscribe_binance_with_config(config, vec![("btcusdt".to_string(), KlineInterval::OneMinute)], tx);
+
+    let mut open_times = Vec::new();
+    while open_times.len() < 5 {
+        if let ChannelMsg::Kline(KlineEvent { index, kline, .. }) = rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            assert_eq!(index, 0);
+            open_times.push(kline.open_time_ms);
+        }
+    }
+    assert_eq!(open_times, (0..5).map(|i| T0 + i * MINUTE).collect::<Vec<_>>());
+
+    let request = request_rx.await.unwrap();
+    assert!(request.starts_with("GET /api/v3/klines?"), "{}", request);
+    assert!(request.contains("symbol=BTCUSDT"), "{}", request);
+    assert!(request.contains("interval=1m"), "{}", request);
+    assert!(request.contains(&format!("startTime={}", T0 + MINUTE)), "{}", request);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn retries_failed_backfill_and_records_gap() {
+    let rest = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rest_url = format!("http://{}", rest.local_addr().unwrap());
+    let body = json!([rest_row(T0 + MINUTE), rest_row(T0 + 2 * MINUTE)]).to_string();
+    // 前 BACKFILL_ATTEMPTS 次都失败, 缺口在之后的重试中补齐
+    let mut responses = vec![(500, "{}".to_string()); BACKFILL_ATTEMPTS as usize];
+    responses.push((200, body));
+    let (request_tx, mut request_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(mock_rest_seq(rest, responses, request_tx));
+
+    let ws = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let ws_url = format!("ws://{}", ws.local_addr().unwrap());
+    tokio::spawn(mock_ws(ws, vec![T0 + 3 * MINUTE]));
+
+    let (tx, rx) = crossbeam::channel::bounded(16);
+    let handle = subscribe_binance_with_config(config(ws_url, rest_url), vec![("btcusdt".to_string(), KlineInterval::OneMinute)], tx);
+
+    let mut open_times = Vec::new();
+    let mut errors = Vec::new();
+    while open_times.len() < 4 {
+        match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            ChannelMsg::Kline(KlineEvent { kline, .. }) => open_times.push(kline.open_time_ms),
+            ChannelMsg::Error(e) => {
+                // 缺口在重试成功前记录在句柄中
+                if let Some(gap) = handle.gaps().first() {
+                    assert_eq!((gap.symbol.as_str(), gap.interval, gap.start_ms), ("btcusdt", KlineInterval::OneMinute, T0 + MINUTE));
+                }
+                errors.push(e);
+            }
+            _ => {}
+        }
+    }
+    // 缺口之后的实时K线先到, 补齐的K线后到
+    assert_eq!(open_times, [T0, T0 + 3 * MINUTE, T0 + MINUTE, T0 + 2 * MINUTE]);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].to_string().contains(&format!("backfill btcusdt 1m [{}", T0 + MINUTE)), "{}", errors[0]);
+    for _ in 0..100 {
+        if handle.gaps().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(handle.gaps().is_empty());
+
+    // 每次都从同一个起点请求
+    for _ in 0..=BACKFILL_ATTEMPTS {
+        let request = request_rx.recv().await.unwrap();
+        assert!(request.contains(&format!("startTime={}", T0 + MINUTE)), "{}", request);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn answers_ping_while_backfilling() {
+    let rest = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rest_url = format!("http://{}", rest.local_addr().unwrap());
+    let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+    tokio::spawn(async move {
+        // 收到 pong 之前不返回补齐结果
+        let _ = release_rx.await;
+        let body = json!([rest_row(T0 + MINUTE)]).to_string();
+        let (request_tx, _request_rx) = tokio::sync::mpsc::unbounded_channel();
+        mock_rest_seq(rest, vec![(200, body)], request_tx).await;
+    });
+
+    let ws = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let ws_url = format!("ws://{}", ws.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (stream, _) = ws.accept().await.unwrap();
+        let mut conn = accept_async(stream).await.unwrap();
+        conn.next().await.unwrap().unwrap();
+        conn.send(Message::Text(kline_frame(T0))).await.unwrap();
+        conn.close(None).await.unwrap();
+        drop(conn);
+
+        let (stream, _) = ws.accept().await.unwrap();
+        let mut conn = accept_async(stream).await.unwrap();
+        conn.next().await.unwrap().unwrap();
+        conn.send(Message::Text(kline_frame(T0 + 2 * MINUTE))).await.unwrap();
+        conn.send(Message::Ping(b"hi".to_vec())).await.unwrap();
+        match conn.next().await.unwrap().unwrap() {
+            Message::Pong(data) => assert_eq!(data, b"hi"),
+            message => panic!("unexpected {:?}", message),
+        }
+        release_tx.send(()).unwrap();
+        while conn.next().await.is_some() {}
+    });
+
+    let (tx, rx) = crossbeam::channel::bounded(16);
+    let _handle = subscribe_binance_with_config(config(ws_url, rest_url), vec![("btcusdt".to_string(), KlineInterval::OneMinute)], tx);
+
+    let mut open_times = Vec::new();
+    let mut pinged = false;
+    while open_times.len() < 3 {
+        match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            ChannelMsg::Kline(KlineEvent { kline, .. }) => open_times.push(kline.open_time_ms),
+            ChannelMsg::Ping(_) => pinged = true,
+            _ => {}
+        }
+    }
+    assert!(pinged);
+    // 补齐期间收到的实时K线在补齐之后发送
+    assert_eq!(open_times, [T0, T0 + MINUTE, T0 + 2 * MINUTE]);
+}
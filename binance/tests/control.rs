@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use binance::{subscribe_binance_with_config, BinanceConfig};
+use cex_core::source::ReconnectPolicy;
+use cex_core::{ChannelMsg, ConnectionStatus, KlineInterval};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{accept_async, tungstenite::protocol::Message};
+
+/// mock 服务: 把收到的请求转发给测试, 回复 LIST_SUBSCRIPTIONS 为当前订阅;
+/// 第 n 个连接收到前 close_after[n] 个请求后断开
+async fn mock_server(listener: TcpListener, close_after: Vec<usize>, requests: mpsc::UnboundedSender<(usize, Value)>) {
+    for conn_id in 0.. {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(stream).await.unwrap();
+        let mut subscribed = Vec::<String>::new();
+        let mut count = 0;
+        while let Some(Ok(message)) = ws.next().await {
+            let Message::Text(text) = message else { continue };
+            let req: Value = serde_json::from_str(&text).unwrap();
+            let params = req["params"].as_array().cloned().unwrap_or_default();
+            let params = params.iter().map(|p| p.as_str().unwrap().to_string());
+            let result = match req["method"].as_str().unwrap() {
+                "SUBSCRIBE" => {
+                    subscribed.extend(params);
+                    Value::Null
+                }
+                "UNSUBSCRIBE" => {
+                    let params = params.collect::<Vec<_>>();
+                    subscribed.retain(|s| !params.contains(s));
+                    Value::Null
+                }
+                "LIST_SUBSCRIPTIONS" => json!(subscribed),
+                method => panic!("unexpected method {}", method),
+            };
+            ws.send(Message::Text(json!({ "result": result, "id": req["id"] }).to_string())).await.unwrap();
+            requests.send((conn_id, req)).unwrap();
+            count += 1;
+            if close_after.get(conn_id) == Some(&count) {
+                break;
+            }
+        }
+    }
+}
+
+fn streams(req: &Value) -> Vec<&str> {
+    req["params"].as_array().unwrap().iter().map(|p| p.as_str().unwrap()).collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn subscribe_unsubscribe_and_list() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let (req_tx, mut req_rx) = mpsc::unbounded_channel();
+    tokio::spawn(mock_server(listener, Vec::new(), req_tx));
+
+    let (tx, _rx) = crossbeam::channel::bounded(16);
+    let config = BinanceConfig { ws_url: url, rest_url: None, ..Default::default() };
+    let handle = subscribe_binance_with_config(config, vec![("btcusdt".to_string(), KlineInterval::OneMinute)], tx);
+
+    let (_, req) = req_rx.recv().await.unwrap();
+    assert_eq!(req["method"], "SUBSCRIBE");
+    assert_eq!(streams(&req), ["btcusdt@kline_1m"]);
+
+    // 已订阅的不再重复请求
+    handle
+        .subscribe(vec![("BTCUSDT".to_string(), KlineInterval::FiveMinutes), ("btcusdt".to_string(), KlineInterval::OneMinute)])
+        .unwrap();
+    let (_, req) = req_rx.recv().await.unwrap();
+    assert_eq!(req["method"], "SUBSCRIBE");
+    assert_eq!(streams(&req), ["btcusdt@kline_5m"]);
+
+    handle.unsubscribe(vec![("btcusdt".to_string(), KlineInterval::OneMinute)]).unwrap();
+    let (_, req) = req_rx.recv().await.unwrap();
+    assert_eq!(req["method"], "UNSUBSCRIBE");
+    assert_eq!(streams(&req), ["btcusdt@kline_1m"]);
+    // 未订阅的不发送请求
+    handle.unsubscribe(vec![("ethusdt".to_string(), KlineInterval::OneMinute)]).unwrap();
+
+    assert_eq!(handle.list_subscriptions().await.unwrap(), ["btcusdt@kline_5m"]);
+    let (_, req) = req_rx.recv().await.unwrap();
+    assert_eq!(req["method"], "LIST_SUBSCRIPTIONS");
+    assert!(req.get("params").is_none());
+
+    assert_eq!(handle.pair_list(), [("BTCUSDT".to_string(), KlineInterval::FiveMinutes)]);
+    // 编号不随取消订阅回收
+    assert_eq!(handle.registry().get("btcusdt", KlineInterval::OneMinute), Some(0));
+    assert_eq!(handle.registry().get("btcusdt", KlineInterval::FiveMinutes), Some(1));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resubscribes_once_after_reconnect() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let (req_tx, mut req_rx) = mpsc::unbounded_channel();
+    // 第一个连接收到订阅后断开
+    tokio::spawn(mock_server(listener, vec![1], req_tx));
+
+    let (tx, rx) = crossbeam::channel::bounded(16);
+    let config = BinanceConfig {
+        ws_url: url,
+        rest_url: None,
+        reconnect: ReconnectPolicy { initial_delay: Duration::from_millis(300), jitter: 0.0, ..Default::default() },
+    };
+    let pair_list = vec![("btcusdt".to_string(), KlineInterval::OneMinute), ("ethusdt".to_string(), KlineInterval::OneMinute)];
+    let handle = subscribe_binance_with_config(config, pair_list, tx);
+
+    let (conn_id, req) = req_rx.recv().await.unwrap();
+    assert_eq!(conn_id, 0);
+    assert_eq!(streams(&req), ["btcusdt@kline_1m", "ethusdt@kline_1m"]);
+
+    // 断线期间修改订阅, 变更排队等待重连
+    loop {
+        let msg = tokio::task::block_in_place(|| rx.recv_timeout(Duration::from_secs(5)).unwrap());
+        if let ChannelMsg::Status(ConnectionStatus::Reconnecting { .. }) = msg {
+            break;
+        }
+    }
+    handle.subscribe(vec![("solusdt".to_string(), KlineInterval::OneHour)]).unwrap();
+    handle.unsubscribe(vec![("btcusdt".to_string(), KlineInterval::OneMinute)]).unwrap();
+    let list = tokio::spawn({
+        let handle = handle.clone();
+        async move { handle.list_subscriptions().await.unwrap() }
+    });
+
+    // 重连后只按当前集合订阅一次, 排队的查询在订阅之后处理
+    let (conn_id, req) = req_rx.recv().await.unwrap();
+    assert_eq!(conn_id, 1);
+    assert_eq!(req["method"], "SUBSCRIBE");
+    assert_eq!(streams(&req), ["ethusdt@kline_1m", "solusdt@kline_1h"]);
+    let (_, req) = req_rx.recv().await.unwrap();
+    assert_eq!(req["method"], "LIST_SUBSCRIPTIONS");
+    assert_eq!(list.await.unwrap(), ["ethusdt@kline_1m", "solusdt@kline_1h"]);
+
+    assert!(tokio::time::timeout(Duration::from_millis(200), req_rx.recv()).await.is_err());
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use binance::download::{download_klines, resume_from, weight_wait, Download};
+use binance::rest::{RestClient, KLINES_LIMIT};
+use cex_core::reader::{FileReader, KlineFilter};
+use cex_core::writer::{create_writer, FileWriterConfig, WriterType};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+const T0: u64 = 1748877600000;
+const MINUTE: u64 = 60_000;
+
+fn rest_row(open_time: u64) -> serde_json::Value {
+    json!([open_time, "100.0", "102.0", "99.0", "101.0", "1.5", open_time + MINUTE - 1, "151.5", 10, "0.7", "70.7", "0"])
+}
+
+fn page(open_times: impl Iterator<Item = u64>) -> String {
+    json!(open_times.map(rest_row).collect::<Vec<_>>()).to_string()
+}
+
+/// (状态码, 响应头, 内容)
+type Response = (u16, Vec<(&'static str, String)>, String);
+
+/// 按顺序返回 `responses` 的 mock HTTP 服务, 每个请求的请求行发送到 `request_tx`
+async fn mock_rest(listener: TcpListener, responses: Vec<Response>, request_tx: mpsc::UnboundedSender<String>) {
+    for (status, headers, body) in responses {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        let request = String::from_utf8_lossy(&buf).lines().next().unwrap().to_string();
+        let headers = headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect::<String>();
+        let resp = format!(
+            "HTTP/1.1 {} X\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        );
+        stream.write_all(resp.as_bytes()).await.unwrap();
+        let _ = request_tx.send(request);
+    }
+}
+
+async fn serve(responses: Vec<Response>) -> (RestClient, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rest = RestClient::new(&format!("http://{}", listener.local_addr().unwrap()));
+    let (request_tx, request_rx) = mpsc::unbounded_channel();
+    tokio::spawn(mock_rest(listener, responses, request_tx));
+    (rest, request_rx)
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("binance-download-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn download(start_ms: u64, end_ms: u64) -> Download {
+    Download {
+        symbol: "btcusdt".to_string(),
+        interval: KlineInterval::OneMinute,
+        start_ms,
+        end_ms,
+        max_weight: 5000,
+    }
+}
+
+fn read_open_times(dir: &PathBuf) -> Vec<u64> {
+    let reader = FileReader::new(dir);
+    let filter = KlineFilter::default();
+    reader.read(&filter).unwrap().map(|kline| kline.open_time_ms).collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn records_rate_limit_headers() {
+    let (rest, _requests) = serve(vec![
+        (200, vec![("X-MBX-USED-WEIGHT-1M", "123".to_string())], page([T0].into_iter())),
+        (429, vec![("X-MBX-USED-WEIGHT-1M", "6001".to_string()), ("Retry-After", "7".to_string())], "{}".to_string()),
+        (418, vec![], "{}".to_string()),
+    ])
+    .await;
+
+    rest.klines("btcusdt", KlineInterval::OneMinute, Some(T0), None, KLINES_LIMIT).await.unwrap();
+    let rate_limit = rest.rate_limit();
+    assert_eq!(rate_limit.used_weight_1m, 123);
+    assert_eq!(rate_limit.retry_after, None);
+
+    assert!(rest.klines("btcusdt", KlineInterval::OneMinute, Some(T0), None, KLINES_LIMIT).await.is_err());
+    let rate_limit = rest.rate_limit();
+    assert_eq!(rate_limit.used_weight_1m, 6001);
+    assert_eq!(rate_limit.retry_after, Some(Duration::from_secs(7)));
+
+    // 没有 Retry-After 时等待 60s, 权重保留上一次的值
+    assert!(rest.klines("btcusdt", KlineInterval::OneMinute, Some(T0), None, KLINES_LIMIT).await.is_err());
+    let rate_limit = rest.rate_limit();
+    assert_eq!(rate_limit.used_weight_1m, 6001);
+    assert_eq!(rate_limit.retry_after, Some(Duration::from_secs(60)));
+}
+
+#[test]
+fn waits_for_next_minute_at_weight_limit() {
+    assert_eq!(weight_wait(4999, 5000, T0 + 30_000), None);
+    assert_eq!(weight_wait(5000, 5000, T0 + 30_000), Some(Duration::from_secs(31)));
+    assert_eq!(weight_wait(6000, 5000, T0 + 59_500), Some(Duration::from_millis(1_500)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn retries_after_rate_limited() {
+    let (rest, mut requests) = serve(vec![
+        (429, vec![("Retry-After", "1".to_string())], "{}".to_string()),
+        (200, vec![], page((0..3).map(|i| T0 + i * MINUTE))),
+    ])
+    .await;
+    let dir = temp_dir("retry");
+    let writer = create_writer(WriterType::File(FileWriterConfig { base_path: dir.clone(), ..Default::default() })).unwrap();
+
+    let started = Instant::now();
+    let count = download_klines(&rest, &writer, &download(T0, T0 + 3 * MINUTE)).await.unwrap();
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(count, 3);
+    writer.flush().await.unwrap();
+    assert_eq!(read_open_times(&dir), [T0, T0 + MINUTE, T0 + 2 * MINUTE]);
+
+    // 重试时使用同一个起点
+    for _ in 0..2 {
+        let request = requests.recv().await.unwrap();
+        assert!(request.contains(&format!("startTime={}", T0)), "{}", request);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resumes_after_existing_klines() {
+    let dir = temp_dir("resume");
+    assert_eq!(resume_from(&dir, None, "btcusdt", KlineInterval::OneMinute, T0).unwrap(), T0);
+
+    // 第一次下载前两根
+    let (rest, _requests) = serve(vec![(200, vec![], page((0..2).map(|i| T0 + i * MINUTE)))]).await;
+    let writer = create_writer(WriterType::File(FileWriterConfig { base_path: dir.clone(), ..Default::default() })).unwrap();
+    assert_eq!(download_klines(&rest, &writer, &download(T0, T0 + 2 * MINUTE)).await.unwrap(), 2);
+    writer.flush().await.unwrap();
+
+    // 再次下载时从最后一根之后开始, 其它交易对和周期不影响
+    let start_ms = resume_from(&dir, None, "BTCUSDT", KlineInterval::OneMinute, T0).unwrap();
+    assert_eq!(start_ms, T0 + 2 * MINUTE);
+    assert_eq!(resume_from(&dir, None, "ethusdt", KlineInterval::OneMinute, T0).unwrap(), T0);
+    assert_eq!(resume_from(&dir, None, "btcusdt", KlineInterval::FiveMinutes, T0).unwrap(), T0);
+    // 指定的开始时间晚于已有数据时不回退
+    assert_eq!(resume_from(&dir, None, "btcusdt", KlineInterval::OneMinute, T0 + 10 * MINUTE).unwrap(), T0 + 10 * MINUTE);
+
+    let (rest, mut requests) = serve(vec![(200, vec![], page((2..4).map(|i| T0 + i * MINUTE)))]).await;
+    assert_eq!(download_klines(&rest, &writer, &download(start_ms, T0 + 4 * MINUTE)).await.unwrap(), 2);
+    writer.flush().await.unwrap();
+    let request = requests.recv().await.unwrap();
+    assert!(request.contains(&format!("startTime={}", T0 + 2 * MINUTE)), "{}", request);
+
+    let mut open_times = read_open_times(&dir);
+    open_times.sort();
+    assert_eq!(open_times, (0..4).map(|i| T0 + i * MINUTE).collect::<Vec<_>>());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn resumes_from_legacy_records() {
+    // 早期实时录制按 [index, kline] 写入每一行
+    let dir = temp_dir("legacy");
+    let price = Decimal::new(100, 0);
+    let kline = SimpleKLine::new("binance", "BTCUSDT", T0, T0 + MINUTE - 1, KlineInterval::OneMinute, price, price, price, price, price, 1);
+    let line = serde_json::to_string(&(3usize, &kline)).unwrap() + "\n";
+    let data = zstd::stream::encode_all(line.as_bytes(), 0).unwrap();
+    fs::write(dir.join("kline_20250602-0800.zst"), data).unwrap();
+
+    assert_eq!(resume_from(&dir, None, "btcusdt", KlineInterval::OneMinute, T0).unwrap(), kline.close_time_ms + 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use binance::{subscribe_binance_with_options, BinanceConfig};
+use cex_core::source::SubscribeOptions;
+use cex_core::{ChannelMsg, ConnectionStatus, KlineEvent, KlineInterval};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::protocol::Message};
+
+const T0: u64 = 1748877600000;
+
+fn kline_frame(close: &str, is_closed: bool) -> String {
+    json!({
+        "stream": "btcusdt@kline_1m",
+        "data": {
+            "e": "kline", "E": T0 + 30_000, "s": "BTCUSDT",
+            "k": {
+                "t": T0, "T": T0 + 59_999, "s": "BTCUSDT", "i": "1m",
+                "o": "100.0", "c": close, "h": "102.0", "l": "99.0", "v": "1.5", "n": 10, "x": is_closed
+            }
+        }
+    }).to_string()
+}
+
+/// 推送两次未收盘更新和一次收盘, 返回收到的 (close, is_final)
+async fn run(options: SubscribeOptions) -> Vec<(String, bool)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(stream).await.unwrap();
+        let Some(Ok(Message::Text(_))) = ws.next().await else { panic!("expect subscribe") };
+        for (close, is_closed) in [("100.5", false), ("101.0", false), ("101.5", true)] {
+            ws.send(Message::Text(kline_frame(close, is_closed))).await.unwrap();
+        }
+        // 保持连接直到测试结束
+        while ws.next().await.is_some() {}
+    });
+
+    let (tx, rx) = crossbeam::channel::bounded(16);
+    let config = BinanceConfig { ws_url: url, rest_url: None, ..Default::default() };
+    let _handle = subscribe_binance_with_options(config, vec![("btcusdt".to_string(), KlineInterval::OneMinute)], options, tx);
+
+    let mut received = Vec::new();
+    while let Ok(msg) = rx.recv_timeout(Duration::from_millis(500)) {
+        match msg {
+            ChannelMsg::Kline(KlineEvent { index, kline, is_final }) => {
+                assert_eq!(index, 0);
+                received.push((kline.close.to_string(), is_final));
+            }
+            ChannelMsg::Status(ConnectionStatus::Connected { .. }) => {}
+            _ => panic!("unexpected message"),
+        }
+    }
+    received
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn partial_updates_are_opt_in() {
+    let closed_only = run(SubscribeOptions::default()).await;
+    assert_eq!(closed_only, vec![("101.5".to_string(), true)]);
+
+    let partial = run(SubscribeOptions { partial: true }).await;
+    assert_eq!(
+        partial,
+        vec![("100.5".to_string(), false), ("101.0".to_string(), false), ("101.5".to_string(), true)]
+    );
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use binance::{subscribe_binance_with_config, BinanceConfig};
+use cex_core::source::ReconnectPolicy;
+use cex_core::{ChannelMsg, ConnectionStatus, KlineEvent, KlineInterval};
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::protocol::Message};
+
+fn kline_frame(open_time: u64) -> String {
+    format!(
+        r#"{{"stream":"btcusdt@kline_1m","data":{{"e":"kline","E":{},"s":"BTCUSDT","k":{{"t":{},"T":{},"s":"BTCUSDT","i":"1m","f":4978109970,"L":4978110557,"o":"104349.06000000","c":"104380.96000000","h":"104380.96000000","l":"104349.06000000","v":"10.32405000","n":588,"x":true,"q":"1077392.54360710","V":"10.27943000","Q":"1072735.25781810","B":"0"}}}}}}"#,
+        open_time + 60_023,
+        open_time,
+        open_time + 59_999
+    )
+}
+
+fn fast_policy(max_attempts: Option<u32>) -> ReconnectPolicy {
+    ReconnectPolicy {
+        initial_delay: Duration::from_millis(20),
+        max_delay: Duration::from_millis(100),
+        multiplier: 2.0,
+        jitter: 0.0,
+        max_attempts,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reconnects_after_server_drops_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    // 每个连接只推送一根新K线然后断开
+    tokio::spawn(async move {
+        for open_time in (1748877600000u64..).step_by(60_000) {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(stream).await.unwrap();
+            let Some(Ok(Message::Text(sub))) = ws.next().await else { panic!("expect subscribe") };
+            assert!(sub.contains("btcusdt@kline_1m"));
+            ws.send(Message::Text(kline_frame(open_time))).await.unwrap();
+            ws.close(None).await.unwrap();
+        }
+    });
+
+    let (tx, rx) = crossbeam::channel::bounded(16);
+    let config = BinanceConfig { ws_url: url, reconnect: fast_policy(None), rest_url: None };
+    let _handle = subscribe_binance_with_config(config, vec![("btcusdt".to_string(), KlineInterval::OneMinute)], tx);
+
+    let recv = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    for _ in 0..2 {
+        assert!(matches!(recv(), ChannelMsg::Status(ConnectionStatus::Connected { .. })));
+        let ChannelMsg::Kline(KlineEvent { index, kline, is_final: true }) = recv() else { panic!("expect kline") };
+        assert_eq!(index, 0);
+        assert_eq!(kline.symbol, "BTCUSDT");
+        assert_eq!(kline.close.to_string(), "104380.96000000");
+        assert_eq!(kline.quote_volume.unwrap().to_string(), "1077392.54360710");
+        assert_eq!(kline.taker_buy_base_volume.unwrap().to_string(), "10.27943000");
+        assert_eq!(kline.taker_buy_quote_volume.unwrap().to_string(), "1072735.25781810");
+        assert_eq!((kline.first_trade_id, kline.last_trade_id), (Some(4978109970), Some(4978110557)));
+        assert_eq!(kline.event_time_ms, Some(kline.open_time_ms + 60_023));
+        assert!(matches!(recv(), ChannelMsg::Status(ConnectionStatus::Disconnected { .. })));
+        // 连接成功后重连计数被重置
+        let ChannelMsg::Status(ConnectionStatus::Reconnecting { attempt, delay_ms, .. }) = recv() else { panic!("expect reconnecting") };
+        assert_eq!(attempt, 1);
+        assert_eq!(delay_ms, 20);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn gives_up_after_max_attempts() {
+    // 绑定后立即释放端口, 之后的连接都会被拒绝
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let (tx, rx) = crossbeam::channel::bounded(16);
+    let config = BinanceConfig { ws_url: url, reconnect: fast_policy(Some(2)), rest_url: None };
+    let _handle = subscribe_binance_with_config(config, vec![("btcusdt".to_string(), KlineInterval::OneMinute)], tx);
+
+    let recv = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    for expected in [1, 2] {
+        assert!(matches!(recv(), ChannelMsg::Status(ConnectionStatus::Disconnected { .. })));
+        let ChannelMsg::Status(ConnectionStatus::Reconnecting { attempt, delay_ms, .. }) = recv() else { panic!("expect reconnecting") };
+        assert_eq!(attempt, expected);
+        assert_eq!(delay_ms, 20 * 2u64.pow(expected - 1));
+    }
+    assert!(matches!(recv(), ChannelMsg::Status(ConnectionStatus::Disconnected { .. })));
+    assert!(matches!(recv(), ChannelMsg::Status(ConnectionStatus::GaveUp { attempts: 2, .. })));
+}
+
+#[test]
+fn backoff_is_capped_and_jittered() {
+    let policy = ReconnectPolicy { jitter: 0.5, ..fast_policy(None) };
+    for attempt in 1..20 {
+        let base = (20.0 * 2f64.powi(attempt as i32 - 1)).min(100.0);
+        let delay = policy.delay(attempt).as_secs_f64() * 1000.0;
+        assert!(delay >= base * 0.5 - 1e-6 && delay <= 100.0 + 1e-6, "attempt {} delay {}", attempt, delay);
+    }
+}
+
+#[test]
+fn backoff_ignores_invalid_factors() {
+    // 非法的倍数和抖动不会 panic, 等待时间仍在 0 ~ max_delay 之间
+    for (multiplier, jitter) in [(-2.0, 0.0), (f64::NAN, 0.0), (f64::INFINITY, 0.0), (2.0, -1.0), (2.0, f64::NAN), (2.0, 5.0)] {
+        let policy = ReconnectPolicy { multiplier, jitter, ..fast_policy(None) };
+        for attempt in 1..10 {
+            assert!(policy.delay(attempt) <= Duration::from_millis(100), "{} {} {}", multiplier, jitter, attempt);
+        }
+    }
+    let policy = ReconnectPolicy { multiplier: -2.0, jitter: f64::NAN, ..fast_policy(None) };
+    assert_eq!(policy.delay(3), Duration::from_millis(20));
+    let policy = ReconnectPolicy { initial_delay: Duration::ZERO, multiplier: f64::INFINITY, ..fast_policy(None) };
+    assert_eq!(policy.delay(3), Duration::from_millis(100));
+}
@@ -0,0 +1,194 @@
+//! 用已收盘的小周期K线合成大周期K线
+//!
+//! 大周期K线的开盘时间按交易所的规则对齐: 从 1970-01-01 起按周期长度切分, 1w 从周一开始, 1M 按自然月。
+//! 默认按 UTC 对齐（与 binance 一致）, 可通过 [`KlineAggregator::with_utc_offset`] 按其它时区的日期对齐,
+//! 如 UTC+8 的日线。
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, FixedOffset, Months, TimeZone};
+use tracing::warn;
+
+use crate::{Decimal, KlineInterval, SimpleKLine};
+
+const DAY_MS: i64 = 24 * 3600 * 1000;
+/// 1970-01-01 是周四, 周线从 1970-01-05（周一）开始切分
+const WEEK_ORIGIN_MS: i64 = 4 * DAY_MS;
+
+// 最近的一根大周期K线, 输出后保留到下一个周期开始, 用于忽略其它输入周期的同一段数据
+struct Bucket {
+    kline: SimpleKLine,
+    /// 已合并的数据覆盖到的时间（最后一根小周期K线的收盘时间）
+    covered_to_ms: u64,
+    /// 已合并的小周期K线的总时长
+    covered_ms: u64,
+    done: bool,
+}
+
+/// K线合成器
+///
+/// 按 (exchange, symbol, 目标周期) 合成, 输入须为按时间顺序的已收盘K线。同一交易对有多个输入周期时（如 1m 和 15m）,
+/// 开盘时间不晚于已合并数据的K线被忽略, 每段时间只计入一次, 每根大周期K线只输出一次; 重复或更早的K线同样被忽略。
+/// 大周期的最后一根小周期K线到达时立即输出; 中间缺失的K线不影响对齐, 缺失最后一根时在下一个周期的K线到达时输出,
+/// 周期内完全没有数据时不输出。
+pub struct KlineAggregator {
+    targets: Vec<KlineInterval>,
+    utc_offset_ms: i64,
+    buckets: HashMap<(String, String, KlineInterval), Bucket>,
+}
+
+impl KlineAggregator {
+    /// 合成 targets 中的周期, 不能由输入周期整合成的周期会被跳过
+    pub fn new(targets: &[KlineInterval]) -> Self {
+        let mut targets = targets.to_vec();
+        targets.sort();
+        targets.dedup();
+        Self {
+            targets,
+            utc_offset_ms: 0,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// 按 UTC+hours 的日期对齐, 影响 1d 等不能整除该偏移的周期
+    pub fn with_utc_offset(mut self, hours: i32) -> Self {
+        self.utc_offset_ms = hours as i64 * 3600 * 1000;
+        self
+    }
+
+    pub fn targets(&self) -> &[KlineInterval] {
+        &self.targets
+    }
+
+    /// 输入周期能否合成 target: target 的每个周期边界都是输入周期的边界
+    pub fn can_aggregate(&self, base: KlineInterval, target: KlineInterval) -> bool {
+        let base_ms = base.duration_ms() as i64;
+        match target {
+            KlineInterval::OneMonth => DAY_MS % base_ms == 0 && self.utc_offset_ms % base_ms == 0,
+            _ => {
+                let origin = if target == KlineInterval::OneWeek { WEEK_ORIGIN_MS } else { 0 };
+                let target_ms = target.duration_ms() as i64;
+                target_ms > base_ms
+                    && target_ms % base_ms == 0
+                    && (origin - self.utc_offset_ms).rem_euclid(base_ms) == 0
+            }
+        }
+    }
+
+    /// 包含 time_ms 的 target 周期, 返回 (开盘时间, 收盘时间), 收盘时间为周期结束前 1ms
+    pub fn period(&self, target: KlineInterval, time_ms: u64) -> Option<(u64, u64)> {
+        if target == KlineInterval::OneMonth {
+            let tz = FixedOffset::east_opt((self.utc_offset_ms / 1000) as i32)?;
+            let local = DateTime::from_timestamp_millis(time_ms as i64)?.with_timezone(&tz);
+            let start = tz.with_ymd_and_hms(local.year(), local.month(), 1, 0, 0, 0).single()?;
+            let end = start.checked_add_months(Months::new(1))?;
+            return Some((start.timestamp_millis() as u64, end.timestamp_millis() as u64 - 1));
+        }
+        let origin = if target == KlineInterval::OneWeek { WEEK_ORIGIN_MS } else { 0 } - self.utc_offset_ms;
+        let target_ms = target.duration_ms() as i64;
+        let start = (time_ms as i64 - origin).div_euclid(target_ms) * target_ms + origin;
+        Some((start as u64, (start + target_ms - 1) as u64))
+    }
+
+    /// 输入一根已收盘K线, 返回因此完成的大周期K线（按周期从短到长）
+    pub fn push(&mut self, kline: &SimpleKLine) -> Vec<SimpleKLine> {
+        let mut output = Vec::new();
+        for target in self.targets.clone() {
+            if !self.can_aggregate(kline.interval, target) {
+                continue;
+            }
+            let Some((start, end)) = self.period(target, kline.open_time_ms) else { continue };
+            let key = (kline.exchange.clone(), kline.symbol.clone(), target);
+
+            let bucket = match self.buckets.get_mut(&key) {
+                // 重复、更早或已由其它输入周期合并的K线
+                Some(bucket) if kline.open_time_ms <= bucket.covered_to_ms => continue,
+                Some(bucket) if bucket.kline.open_time_ms == start => {
+                    merge(bucket, kline);
+                    bucket
+                }
+                Some(bucket) => {
+                    // 上一个周期缺少最后的K线, 先输出
+                    if !bucket.done {
+                        output.push(finish(bucket));
+                    }
+                    *bucket = open_bucket(kline, target, start, end);
+                    bucket
+                }
+                None => self.buckets.entry(key).or_insert(open_bucket(kline, target, start, end)),
+            };
+
+            if kline.close_time_ms >= end {
+                output.push(finish(bucket));
+                bucket.done = true;
+            }
+        }
+        output
+    }
+
+    /// 依次输入 klines, 只返回合成的大周期K线, 用于回放
+    pub fn resample<I>(mut self, klines: I) -> impl Iterator<Item = SimpleKLine>
+    where
+        I: IntoIterator<Item = SimpleKLine>,
+    {
+        klines.into_iter().flat_map(move |kline| self.push(&kline))
+    }
+}
+
+fn open_bucket(kline: &SimpleKLine, target: KlineInterval, start: u64, end: u64) -> Bucket {
+    let mut bar = SimpleKLine::new(
+        &kline.exchange,
+        &kline.symbol,
+        start,
+        end,
+        target,
+        kline.open,
+        kline.high,
+        kline.low,
+        kline.close,
+        kline.volume,
+        kline.trades_count,
+    );
+    bar.quote_volume = kline.quote_volume;
+    bar.taker_buy_base_volume = kline.taker_buy_base_volume;
+    bar.taker_buy_quote_volume = kline.taker_buy_quote_volume;
+    bar.first_trade_id = kline.first_trade_id;
+    bar.last_trade_id = kline.last_trade_id;
+    bar.event_time_ms = kline.event_time_ms;
+    Bucket {
+        kline: bar,
+        covered_to_ms: kline.close_time_ms,
+        covered_ms: kline.interval.duration_ms(),
+        done: false,
+    }
+}
+
+fn merge(bucket: &mut Bucket, kline: &SimpleKLine) {
+    let sum = |a: Option<Decimal>, b: Option<Decimal>| Some(a? + b?);
+    let bar = &mut bucket.kline;
+    bar.high = bar.high.max(kline.high);
+    bar.low = bar.low.min(kline.low);
+    bar.close = kline.close;
+    bar.volume += kline.volume;
+    bar.trades_count += kline.trades_count;
+    bar.quote_volume = sum(bar.quote_volume, kline.quote_volume);
+    bar.taker_buy_base_volume = sum(bar.taker_buy_base_volume, kline.taker_buy_base_volume);
+    bar.taker_buy_quote_volume = sum(bar.taker_buy_quote_volume, kline.taker_buy_quote_volume);
+    bar.first_trade_id = bar.first_trade_id.or(kline.first_trade_id);
+    bar.last_trade_id = kline.last_trade_id.or(bar.last_trade_id);
+    bar.event_time_ms = kline.event_time_ms.or(bar.event_time_ms);
+    bucket.covered_to_ms = kline.close_time_ms;
+    bucket.covered_ms += kline.interval.duration_ms();
+}
+
+fn finish(bucket: &Bucket) -> SimpleKLine {
+    let bar = &bucket.kline;
+    let expected_ms = bar.close_time_ms + 1 - bar.open_time_ms;
+    if bucket.covered_ms < expected_ms {
+        warn!(
+            "合成K线缺少数据 {} {} {} {}: {}/{} 分钟",
+            bar.exchange, bar.symbol, bar.interval, bar.open_time_h, bucket.covered_ms / 60_000, expected_ms / 60_000
+        );
+    }
+    bar.clone()
+}
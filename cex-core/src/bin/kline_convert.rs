@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use cex_core::reader::{FileReader, KlineFilter};
+use cex_core::writer::{create_writer, FileWriterConfig, WriterType};
+use clap::Parser;
+use tracing::info;
+
+/// 把 json lines 的 kline_*.zst 归档转换为二进制列式格式 kline_*.bin
+#[derive(Parser, Debug)]
+struct Args {
+    /// 源归档目录
+    #[arg(long)]
+    input_dir: PathBuf,
+    /// 输出目录
+    #[arg(long)]
+    output_dir: PathBuf,
+    /// 源归档的分区目录模板, 输出使用相同的分区
+    #[arg(long)]
+    partition: Option<String>,
+    /// 文件轮转间隔（秒）
+    #[arg(long, default_value_t = 8 * 3600)]
+    rotation_interval: i64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt().init();
+    let args = Args::parse();
+
+    let reader = FileReader::new(&args.input_dir).with_partition(args.partition.clone());
+    let writer = create_writer(WriterType::BinaryFile(FileWriterConfig {
+        base_path: args.output_dir,
+        rotation_interval: args.rotation_interval,
+        partition: args.partition,
+        ..Default::default()
+    }))?;
+
+    let mut count = 0usize;
+    for kline in reader.read(&KlineFilter::default())? {
+        writer.write(&kline).await?;
+        count += 1;
+        if count.is_multiple_of(100_000) {
+            info!("已转换 {} 条", count);
+        }
+    }
+    writer.close().await?;
+    info!("转换完成, 共 {} 条", count);
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use cex_core::export::export_parquet;
+use cex_core::reader::{FileReader, KlineFilter};
+use cex_core::KlineInterval;
+use chrono::{FixedOffset, NaiveDate, TimeZone};
+use clap::Parser;
+use tracing::info;
+
+/// 把K线归档导出为按 exchange/symbol/interval/date 分区的 parquet 文件
+#[derive(Parser, Debug)]
+struct Args {
+    /// 归档目录
+    #[arg(long, default_value = "data")]
+    input_dir: PathBuf,
+    /// 归档的分区目录模板, 如 {exchange}/{symbol}/{interval}
+    #[arg(long)]
+    partition: Option<String>,
+    /// 输出目录
+    #[arg(long)]
+    output_dir: PathBuf,
+    /// 只导出指定交易所
+    #[arg(long)]
+    exchange: Option<String>,
+    /// 只导出指定交易对, 可重复
+    #[arg(long)]
+    symbol: Vec<String>,
+    /// 只导出指定周期, 可重复
+    #[arg(long)]
+    interval: Vec<KlineInterval>,
+    /// 开始日期（UTC+8, 与分区日期一致）
+    #[arg(long)]
+    start: Option<NaiveDate>,
+    /// 结束日期（UTC+8, 不含）
+    #[arg(long)]
+    end: Option<NaiveDate>,
+}
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt().init();
+    let args = Args::parse();
+
+    let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+    let date_ms = |date: NaiveDate| {
+        tz.from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap()).unwrap().timestamp_millis() as u64
+    };
+    let filter = KlineFilter {
+        exchange: args.exchange,
+        symbols: args.symbol,
+        intervals: args.interval,
+        start_ms: args.start.map(date_ms),
+        end_ms: args.end.map(date_ms),
+    };
+
+    let reader = FileReader::new(&args.input_dir).with_partition(args.partition);
+    let files = export_parquet(&reader, &filter, &args.output_dir)?;
+    info!("导出完成, 共 {} 个文件", files.len());
+    Ok(())
+}
@@ -0,0 +1,233 @@
+//! 二进制列式K线格式
+//!
+//! 文件以 8 字节头开始: `CEXK` + 版本号(u16) + 保留(u16), 之后是若干 zstd 帧, 每帧解压后是一个自包含的块:
+//!
+//! ```text
+//! u32 行数 n
+//! u16 字典大小 m, 每项为 (exchange, symbol, interval), 字符串为 u16 长度 + utf8
+//! n × u16 字典编号
+//! n × u64 open_time_ms, n × u64 close_time_ms
+//! n × 16 字节 open, high, low, close, volume（Decimal::serialize）
+//! n × u64 trades_count
+//! n × u8 可选字段标记, 第 0~5 位依次表示 quote_volume, taker_buy_base_volume, taker_buy_quote_volume,
+//!     first_trade_id, last_trade_id, event_time_ms 是否存在
+//! n × 16 字节 quote_volume, taker_buy_base_volume, taker_buy_quote_volume（不存在时为 0）
+//! n × i64 first_trade_id, n × i64 last_trade_id, n × u64 event_time_ms（不存在时为 0）
+//! ```
+//!
+//! 所有数值均为小端序, open_time_h 读取时按 UTC+8 重新生成
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use anyhow::{bail, ensure, Context, Result};
+use chrono::{DateTime, FixedOffset};
+
+use crate::{Decimal, KlineInterval, SimpleKLine};
+
+pub const MAGIC: &[u8; 4] = b"CEXK";
+pub const VERSION: u16 = 1;
+pub const HEADER_LEN: usize = 8;
+
+/// 每行编码后的字节数, 用于估算缓冲大小
+pub const ROW_LEN: usize = 2 + 8 * 2 + 16 * 5 + 8 + 1 + 16 * 3 + 8 * 3;
+
+pub fn write_header<W: Write>(writer: &mut W) -> std::io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&0u16.to_le_bytes())
+}
+
+/// 读取并校验文件头
+pub fn read_header<R: Read>(reader: &mut R) -> Result<()> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header).context("Failed to read header")?;
+    ensure!(&header[..4] == MAGIC, "invalid magic: {:?}", &header[..4]);
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    ensure!(version == VERSION, "unsupported version: {}", version);
+    Ok(())
+}
+
+/// 把一组K线编码为一个块（未压缩）
+pub fn encode_block(klines: &[SimpleKLine]) -> Result<Vec<u8>> {
+    let mut dict: Vec<(&str, &str, &str)> = Vec::new();
+    let mut dict_index: HashMap<(&str, &str, &str), u16> = HashMap::new();
+    let mut ids = Vec::with_capacity(klines.len());
+    for kline in klines {
+        let key = (kline.exchange.as_str(), kline.symbol.as_str(), kline.interval.as_str());
+        let id = match dict_index.get(&key) {
+            Some(id) => *id,
+            None => {
+                let id = u16::try_from(dict.len()).context("too many series in one block")?;
+                dict.push(key);
+                dict_index.insert(key, id);
+                id
+            }
+        };
+        ids.push(id);
+    }
+
+    let mut buf = Vec::with_capacity(8 + klines.len() * ROW_LEN);
+    buf.extend_from_slice(&(klines.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    for (exchange, symbol, interval) in &dict {
+        for s in [exchange, symbol, interval] {
+            let len = u16::try_from(s.len()).context("string too long")?;
+            buf.extend_from_slice(&len.to_le_bytes());
+            buf.extend_from_slice(s.as_bytes());
+        }
+    }
+    for id in ids {
+        buf.extend_from_slice(&id.to_le_bytes());
+    }
+    for column in [|k: &SimpleKLine| k.open_time_ms, |k: &SimpleKLine| k.close_time_ms] {
+        for kline in klines {
+            buf.extend_from_slice(&column(kline).to_le_bytes());
+        }
+    }
+    for column in [
+        |k: &SimpleKLine| k.open,
+        |k: &SimpleKLine| k.high,
+        |k: &SimpleKLine| k.low,
+        |k: &SimpleKLine| k.close,
+        |k: &SimpleKLine| k.volume,
+    ] {
+        for kline in klines {
+            buf.extend_from_slice(&column(kline).serialize());
+        }
+    }
+    for kline in klines {
+        buf.extend_from_slice(&kline.trades_count.to_le_bytes());
+    }
+
+    for kline in klines {
+        let present = [
+            kline.quote_volume.is_some(),
+            kline.taker_buy_base_volume.is_some(),
+            kline.taker_buy_quote_volume.is_some(),
+            kline.first_trade_id.is_some(),
+            kline.last_trade_id.is_some(),
+            kline.event_time_ms.is_some(),
+        ];
+        buf.push(present.iter().enumerate().fold(0u8, |flags, (bit, p)| flags | (*p as u8) << bit));
+    }
+    for column in [
+        |k: &SimpleKLine| k.quote_volume,
+        |k: &SimpleKLine| k.taker_buy_base_volume,
+        |k: &SimpleKLine| k.taker_buy_quote_volume,
+    ] {
+        for kline in klines {
+            buf.extend_from_slice(&column(kline).unwrap_or_default().serialize());
+        }
+    }
+    for column in [|k: &SimpleKLine| k.first_trade_id, |k: &SimpleKLine| k.last_trade_id] {
+        for kline in klines {
+            buf.extend_from_slice(&column(kline).unwrap_or_default().to_le_bytes());
+        }
+    }
+    for kline in klines {
+        buf.extend_from_slice(&kline.event_time_ms.unwrap_or_default().to_le_bytes());
+    }
+    Ok(buf)
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let Some(bytes) = self.data.get(self.pos..self.pos + len) else {
+            bail!("block truncated at {}", self.pos);
+        };
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn decimal(&mut self) -> Result<Decimal> {
+        Ok(Decimal::deserialize(self.take(16)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec())?)
+    }
+}
+
+/// 解码一个块（已解压）
+pub fn decode_block(data: &[u8]) -> Result<Vec<SimpleKLine>> {
+    let mut cursor = Cursor { data, pos: 0 };
+    let n = cursor.u32()? as usize;
+    let dict = (0..cursor.u16()?)
+        .map(|_| Ok((cursor.string()?, cursor.string()?, cursor.string()?.parse::<KlineInterval>()?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let ids = (0..n).map(|_| cursor.u16()).collect::<Result<Vec<_>>>()?;
+    let mut u64_column = || (0..n).map(|_| cursor.u64()).collect::<Result<Vec<_>>>();
+    let open_time = u64_column()?;
+    let close_time = u64_column()?;
+    let mut decimal_column = || (0..n).map(|_| cursor.decimal()).collect::<Result<Vec<_>>>();
+    let (open, high, low, close, volume) =
+        (decimal_column()?, decimal_column()?, decimal_column()?, decimal_column()?, decimal_column()?);
+    let trades_count = (0..n).map(|_| cursor.u64()).collect::<Result<Vec<_>>>()?;
+
+    let flags = (0..n).map(|_| cursor.u8()).collect::<Result<Vec<_>>>()?;
+    let mut decimal_column = || (0..n).map(|_| cursor.decimal()).collect::<Result<Vec<_>>>();
+    let (quote_volume, taker_buy_base_volume, taker_buy_quote_volume) = (decimal_column()?, decimal_column()?, decimal_column()?);
+    let mut i64_column = || (0..n).map(|_| cursor.i64()).collect::<Result<Vec<_>>>();
+    let (first_trade_id, last_trade_id) = (i64_column()?, i64_column()?);
+    let event_time = (0..n).map(|_| cursor.u64()).collect::<Result<Vec<_>>>()?;
+
+    let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+    (0..n)
+        .map(|i| {
+            let (exchange, symbol, interval) = dict.get(ids[i] as usize).context("invalid dict id")?;
+            let open_time_h = DateTime::from_timestamp_millis(open_time[i] as i64)
+                .context("invalid open time")?
+                .with_timezone(&tz)
+                .format("%Y%m%d-%H:%M")
+                .to_string();
+            Ok(SimpleKLine {
+                exchange: exchange.clone(),
+                symbol: symbol.clone(),
+                open_time_ms: open_time[i],
+                close_time_ms: close_time[i],
+                open_time_h,
+                interval: *interval,
+                open: open[i],
+                high: high[i],
+                low: low[i],
+                close: close[i],
+                volume: volume[i],
+                quote_volume: (flags[i] & 1 != 0).then_some(quote_volume[i]),
+                taker_buy_base_volume: (flags[i] & 1 << 1 != 0).then_some(taker_buy_base_volume[i]),
+                taker_buy_quote_volume: (flags[i] & 1 << 2 != 0).then_some(taker_buy_quote_volume[i]),
+                trades_count: trades_count[i],
+                first_trade_id: (flags[i] & 1 << 3 != 0).then_some(first_trade_id[i]),
+                last_trade_id: (flags[i] & 1 << 4 != 0).then_some(last_trade_id[i]),
+                event_time_ms: (flags[i] & 1 << 5 != 0).then_some(event_time[i]),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone};
+use parquet::basic::Compression;
+use parquet::data_type::{DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use rust_decimal::prelude::ToPrimitive;
+use tracing::info;
+
+use crate::reader::{period_start_ms, FileReader, KlineFilter};
+use crate::{Decimal, KlineInterval, SimpleKLine};
+
+/// parquet 文件的列, exchange, symbol, interval 和 date 在分区目录中, 不重复写入文件; 价格和交易量导出为 DOUBLE 供分析使用
+const KLINE_SCHEMA: &str = "
+message kline {
+    REQUIRED INT64 open_time (TIMESTAMP(MILLIS,true));
+    REQUIRED INT64 close_time (TIMESTAMP(MILLIS,true));
+    REQUIRED DOUBLE open;
+    REQUIRED DOUBLE high;
+    REQUIRED DOUBLE low;
+    REQUIRED DOUBLE close;
+    REQUIRED DOUBLE volume;
+    REQUIRED INT64 trades_count;
+    OPTIONAL DOUBLE quote_volume;
+    OPTIONAL DOUBLE taker_buy_base_volume;
+    OPTIONAL DOUBLE taker_buy_quote_volume;
+    OPTIONAL INT64 first_trade_id;
+    OPTIONAL INT64 last_trade_id;
+    OPTIONAL INT64 event_time (TIMESTAMP(MILLIS,true));
+}
+";
+
+/// 导出文件名
+pub const PARQUET_FILE_NAME: &str = "kline.parquet";
+
+const DAY_MS: u64 = 24 * 3600 * 1000;
+
+/// 把归档中满足条件的K线导出为 parquet
+///
+/// 按 hive 风格分区写入 `exchange=<exchange>/symbol=<symbol>/interval=<interval>/date=<YYYY-MM-DD>/kline.parquet`,
+/// 日期按开盘时间的 UTC+8 计算（与归档文件名一致）, 每个文件内按 open_time 排序并去重, 返回写入的文件列表（按路径排序）
+///
+/// 按日期逐天读取归档, 内存中只保留一天的数据
+pub fn export_parquet(reader: &FileReader, filter: &KlineFilter, output_dir: &Path) -> Result<Vec<PathBuf>> {
+    let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+    let date_of = |ms: u64| -> Result<NaiveDate> {
+        Ok(DateTime::from_timestamp_millis(ms as i64).context("invalid open time")?.with_timezone(&tz).date_naive())
+    };
+
+    // 归档文件所在的日期; 周期开始时写入的文件可能包含前一天收盘的K线
+    let mut days = BTreeSet::new();
+    for path in reader.files(filter)? {
+        if let Some(start) = period_start_ms(&path) {
+            days.insert(date_of(start)?);
+            days.insert(date_of(start.saturating_sub(1))?);
+        }
+    }
+
+    let mut files = Vec::new();
+    for date in days {
+        let day_start = tz.from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap()).unwrap().timestamp_millis() as u64;
+        let day_filter = KlineFilter {
+            start_ms: Some(filter.start_ms.map_or(day_start, |start| start.max(day_start))),
+            end_ms: Some(filter.end_ms.map_or(day_start + DAY_MS, |end| end.min(day_start + DAY_MS))),
+            ..filter.clone()
+        };
+        if day_filter.start_ms >= day_filter.end_ms {
+            continue;
+        }
+
+        let mut partitions: BTreeMap<(String, String, KlineInterval), Vec<SimpleKLine>> = BTreeMap::new();
+        for kline in reader.read(&day_filter)? {
+            partitions.entry((kline.exchange.clone(), kline.symbol.clone(), kline.interval)).or_default().push(kline);
+        }
+        for ((exchange, symbol, interval), mut klines) in partitions {
+            klines.sort_by_key(|k| k.open_time_ms);
+            klines.dedup_by_key(|k| k.open_time_ms);
+
+            let dir = output_dir
+                .join(format!("exchange={}", escape_path_value(&exchange)))
+                .join(format!("symbol={}", escape_path_value(&symbol)))
+                .join(format!("interval={}", interval))
+                .join(format!("date={}", date.format("%Y-%m-%d")));
+            fs::create_dir_all(&dir).context("Failed to create directory")?;
+            let path = dir.join(PARQUET_FILE_NAME);
+            write_parquet(&path, &klines)?;
+            info!("导出 {} 条到 {:?}", klines.len(), path);
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// 分区目录中的取值, 字母数字和 `-_.` 以外的字符按 hive 的方式转义为 %XX, 避免交易对中的 `/` 等字符生成额外的目录
+fn escape_path_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.".contains(&byte) {
+            escaped.push(byte as char);
+        } else {
+            escaped.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    escaped
+}
+
+fn write_parquet(path: &Path, klines: &[SimpleKLine]) -> Result<()> {
+    let schema = Arc::new(parse_message_type(KLINE_SCHEMA)?);
+    let props = Arc::new(WriterProperties::builder().set_compression(Compression::SNAPPY).build());
+    let file = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let mut writer = SerializedFileWriter::new(file, schema, props)?;
+
+    let longs = |f: fn(&SimpleKLine) -> i64| klines.iter().map(f).collect::<Vec<_>>();
+    let doubles = |f: fn(&SimpleKLine) -> f64| klines.iter().map(f).collect::<Vec<_>>();
+    // 可选列只写入非空值, 另外返回每行的 definition level
+    let levels = |present: &dyn Fn(&SimpleKLine) -> bool| klines.iter().map(|k| present(k) as i16).collect::<Vec<_>>();
+    let optional_doubles = |f: fn(&SimpleKLine) -> Option<Decimal>| {
+        let values = klines.iter().filter_map(f).map(|v| v.to_f64().unwrap_or_default()).collect::<Vec<_>>();
+        (values, levels(&|k| f(k).is_some()))
+    };
+    let optional_longs = |f: fn(&SimpleKLine) -> Option<i64>| {
+        (klines.iter().filter_map(f).collect::<Vec<_>>(), levels(&|k| f(k).is_some()))
+    };
+
+    let mut row_group = writer.next_row_group()?;
+    let mut column = 0;
+    while let Some(mut col) = row_group.next_column()? {
+        match column {
+            0 => col.typed::<Int64Type>().write_batch(&longs(|k| k.open_time_ms as i64), None, None)?,
+            1 => col.typed::<Int64Type>().write_batch(&longs(|k| k.close_time_ms as i64), None, None)?,
+            2 => col.typed::<DoubleType>().write_batch(&doubles(SimpleKLine::open_f64), None, None)?,
+            3 => col.typed::<DoubleType>().write_batch(&doubles(SimpleKLine::high_f64), None, None)?,
+            4 => col.typed::<DoubleType>().write_batch(&doubles(SimpleKLine::low_f64), None, None)?,
+            5 => col.typed::<DoubleType>().write_batch(&doubles(SimpleKLine::close_f64), None, None)?,
+            6 => col.typed::<DoubleType>().write_batch(&doubles(SimpleKLine::volume_f64), None, None)?,
+            7 => col.typed::<Int64Type>().write_batch(&longs(|k| k.trades_count as i64), None, None)?,
+            8..=10 => {
+                let (values, levels) = optional_doubles(match column {
+                    8 => |k| k.quote_volume,
+                    9 => |k| k.taker_buy_base_volume,
+                    _ => |k| k.taker_buy_quote_volume,
+                });
+                col.typed::<DoubleType>().write_batch(&values, Some(&levels), None)?
+            }
+            11..=13 => {
+                let (values, levels) = optional_longs(match column {
+                    11 => |k| k.first_trade_id,
+                    12 => |k| k.last_trade_id,
+                    _ => |k| k.event_time_ms.map(|t| t as i64),
+                });
+                col.typed::<Int64Type>().write_batch(&values, Some(&levels), None)?
+            }
+            _ => unreachable!("schema has 14 columns"),
+        };
+        col.close()?;
+        column += 1;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
@@ -1,8 +1,18 @@
+use rust_decimal::prelude::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+pub use rust_decimal::Decimal;
+
 pub mod writer;
+pub mod reader;
+pub mod binary;
+pub mod export;
+pub mod ring;
 pub mod structure;
+pub mod source;
+pub mod registry;
+pub mod aggregate;
 
 #[derive(Debug, Error)]
 pub enum CexError {
@@ -15,7 +25,7 @@ pub enum CexError {
 }
 
 /// 简单K线数据结构
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SimpleKLine {
     /// 交易所
     pub exchange: String,
@@ -27,22 +37,38 @@ pub struct SimpleKLine {
     pub close_time_ms: u64,
     /// 开盘时间戳（易读）: 20250601-20:01
     pub open_time_h: String,
-    /// 时间间隔
-    pub interval: String,
-    /// 开盘价
-    pub open: f64,
+    /// K线周期
+    pub interval: KlineInterval,
+    /// 开盘价, 价格和交易量保留交易所返回的精度, json 中序列化为字符串
+    pub open: Decimal,
     /// 最高价
-    pub high: f64,
+    pub high: Decimal,
     /// 最低价
-    pub low: f64,
+    pub low: Decimal,
     /// 收盘价
-    pub close: f64,
+    pub close: Decimal,
     /// 交易量
-    pub volume: f64,
-    /// 交易额
-    // pub quote_volume: f64,
+    pub volume: Decimal,
+    /// 交易额, 以下 Option 字段在交易所不提供时为 None, 不写入 json
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote_volume: Option<Decimal>,
+    /// 主动买入成交量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taker_buy_base_volume: Option<Decimal>,
+    /// 主动买入成交额
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taker_buy_quote_volume: Option<Decimal>,
     /// 交易笔数
     pub trades_count: u64,
+    /// 第一笔成交ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_trade_id: Option<i64>,
+    /// 最后一笔成交ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_trade_id: Option<i64>,
+    /// 交易所推送的事件时间（毫秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_time_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,27 +88,52 @@ impl Ping {
     }
 }
 
+/// 行情连接状态变化
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ConnectionStatus {
+    /// 已连接并完成订阅
+    Connected { source: String },
+    /// 连接断开
+    Disconnected { source: String, reason: String },
+    /// 第 attempt 次重连, 等待 delay_ms 后发起
+    Reconnecting { source: String, attempt: u32, delay_ms: u64 },
+    /// 达到最大重连次数, 放弃重连
+    GaveUp { source: String, attempts: u32 },
+}
+
+/// 一次K线推送
+#[derive(Debug, Clone)]
+pub struct KlineEvent {
+    /// 该K线流在 `registry::StreamRegistry` 中的编号, 即订阅列表中的位置（从 0 开始）
+    pub index: usize,
+    pub kline: SimpleKLine,
+    /// K线已收盘; 订阅时开启 partial 才会收到 false 的未收盘更新
+    pub is_final: bool,
+}
+
+#[allow(clippy::large_enum_variant)]
 pub enum ChannelMsg {
     Ping(Ping),
-    Kline((usize, SimpleKLine)),
+    Kline(KlineEvent),
     Error(CexError),
+    Status(ConnectionStatus),
 }
 
 
 impl SimpleKLine {
     /// 创建新的K线数据
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         exchange: &str,
         symbol: &str,
         open_time: u64,
         close_time: u64,
         interval: KlineInterval,
-        open: f64,
-        high: f64,
-        low: f64,
-        close: f64,
-        volume: f64,
-        // quote_volume: f64,
+        open: Decimal,
+        high: Decimal,
+        low: Decimal,
+        close: Decimal,
+        volume: Decimal,
         trades_count: u64,
     ) -> Self {
         // 将时间戳转换为UTC+8时区的易读格式
@@ -98,41 +149,171 @@ impl SimpleKLine {
             open_time_ms: open_time,
             close_time_ms: close_time,
             open_time_h,
-            interval: interval.as_str().to_string(),
+            interval,
             open,
             high,
             low,
             close,
             volume,
-            // quote_volume,
             trades_count,
             exchange: exchange.to_string(),
             symbol: symbol.to_string(),
+            ..Default::default()
         }
     }
+    /// 用于指标计算的 f64 开盘价, 其它 *_f64 同理
+    pub fn open_f64(&self) -> f64 {
+        self.open.to_f64().unwrap_or_default()
+    }
+
+    pub fn high_f64(&self) -> f64 {
+        self.high.to_f64().unwrap_or_default()
+    }
+
+    pub fn low_f64(&self) -> f64 {
+        self.low.to_f64().unwrap_or_default()
+    }
+
+    pub fn close_f64(&self) -> f64 {
+        self.close.to_f64().unwrap_or_default()
+    }
+
+    pub fn volume_f64(&self) -> f64 {
+        self.volume.to_f64().unwrap_or_default()
+    }
 } 
 
-#[derive(Debug, Clone)]
+/// K线周期, 字符串形式与币安一致（`1m`, `1h`, `1M` 等, 区分大小写）
+///
+/// 各交易所的周期名称由交易所 crate 各自映射
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum KlineInterval {
+    OneSecond,
+    #[default]
     OneMinute,
+    ThreeMinutes,
     FiveMinutes,
     FifteenMinutes,
     ThirtyMinutes,
     OneHour,
+    TwoHours,
     FourHours,
+    SixHours,
+    EightHours,
+    TwelveHours,
     OneDay,
+    ThreeDays,
+    OneWeek,
+    OneMonth,
 }
 
 impl KlineInterval {
+    /// 全部周期, 从短到长
+    pub const ALL: &'static [KlineInterval] = &[
+        KlineInterval::OneSecond,
+        KlineInterval::OneMinute,
+        KlineInterval::ThreeMinutes,
+        KlineInterval::FiveMinutes,
+        KlineInterval::FifteenMinutes,
+        KlineInterval::ThirtyMinutes,
+        KlineInterval::OneHour,
+        KlineInterval::TwoHours,
+        KlineInterval::FourHours,
+        KlineInterval::SixHours,
+        KlineInterval::EightHours,
+        KlineInterval::TwelveHours,
+        KlineInterval::OneDay,
+        KlineInterval::ThreeDays,
+        KlineInterval::OneWeek,
+        KlineInterval::OneMonth,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
+            KlineInterval::OneSecond => "1s",
             KlineInterval::OneMinute => "1m",
+            KlineInterval::ThreeMinutes => "3m",
             KlineInterval::FiveMinutes => "5m",
             KlineInterval::FifteenMinutes => "15m",
             KlineInterval::ThirtyMinutes => "30m",
             KlineInterval::OneHour => "1h",
+            KlineInterval::TwoHours => "2h",
             KlineInterval::FourHours => "4h",
+            KlineInterval::SixHours => "6h",
+            KlineInterval::EightHours => "8h",
+            KlineInterval::TwelveHours => "12h",
             KlineInterval::OneDay => "1d",
+            KlineInterval::ThreeDays => "3d",
+            KlineInterval::OneWeek => "1w",
+            KlineInterval::OneMonth => "1M",
+        }
+    }
+
+    /// 周期长度（毫秒）, 1M 按 30 天计算, 实际收盘时间用 [`KlineInterval::close_time_ms`]
+    pub fn duration_ms(&self) -> u64 {
+        const MINUTE: u64 = 60_000;
+        const DAY: u64 = 24 * 60 * MINUTE;
+        match self {
+            KlineInterval::OneSecond => 1000,
+            KlineInterval::OneMinute => MINUTE,
+            KlineInterval::ThreeMinutes => 3 * MINUTE,
+            KlineInterval::FiveMinutes => 5 * MINUTE,
+            KlineInterval::FifteenMinutes => 15 * MINUTE,
+            KlineInterval::ThirtyMinutes => 30 * MINUTE,
+            KlineInterval::OneHour => 60 * MINUTE,
+            KlineInterval::TwoHours => 2 * 60 * MINUTE,
+            KlineInterval::FourHours => 4 * 60 * MINUTE,
+            KlineInterval::SixHours => 6 * 60 * MINUTE,
+            KlineInterval::EightHours => 8 * 60 * MINUTE,
+            KlineInterval::TwelveHours => 12 * 60 * MINUTE,
+            KlineInterval::OneDay => DAY,
+            KlineInterval::ThreeDays => 3 * DAY,
+            KlineInterval::OneWeek => 7 * DAY,
+            KlineInterval::OneMonth => 30 * DAY,
         }
     }
-}
\ No newline at end of file
+
+    /// 按开盘时间计算收盘时间（最后一毫秒）, 1M 按自然月计算, 开盘时间无效时返回 None
+    pub fn close_time_ms(&self, open_time_ms: u64) -> Option<u64> {
+        let end_ms = match self {
+            KlineInterval::OneMonth => {
+                let open = chrono::DateTime::from_timestamp_millis(i64::try_from(open_time_ms).ok()?)?;
+                u64::try_from(open.checked_add_months(chrono::Months::new(1))?.timestamp_millis()).ok()?
+            }
+            // 交易所返回的开盘时间不可信, 溢出时返回 None
+            _ => open_time_ms.checked_add(self.duration_ms())?,
+        };
+        end_ms.checked_sub(1)
+    }
+}
+
+impl std::fmt::Display for KlineInterval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for KlineInterval {
+    type Err = CexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KlineInterval::ALL
+            .iter()
+            .find(|i| i.as_str() == s)
+            .copied()
+            .ok_or_else(|| CexError::ParseError(format!("unknown kline interval: {}", s)))
+    }
+}
+
+impl Serialize for KlineInterval {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for KlineInterval {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = std::borrow::Cow::<str>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
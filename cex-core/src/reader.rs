@@ -0,0 +1,298 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::{FixedOffset, NaiveDateTime, TimeZone};
+use serde::de::DeserializeOwned;
+use shared_memory::{Shmem, ShmemConf};
+use tracing::warn;
+use zstd::stream::read::Decoder;
+
+use crate::binary;
+use crate::ring::{RingRead, RingReader};
+use crate::writer::FileFormat;
+use crate::{KlineInterval, SimpleKLine};
+
+/// K线过滤条件, 为空的条件不过滤
+#[derive(Debug, Clone, Default)]
+pub struct KlineFilter {
+    pub exchange: Option<String>,
+    /// 交易对, 不区分大小写
+    pub symbols: Vec<String>,
+    pub intervals: Vec<KlineInterval>,
+    /// 按开盘时间过滤 [start_ms, end_ms)
+    pub start_ms: Option<u64>,
+    pub end_ms: Option<u64>,
+}
+
+impl KlineFilter {
+    pub fn matches(&self, kline: &SimpleKLine) -> bool {
+        self.exchange.as_ref().is_none_or(|e| *e == kline.exchange)
+            && (self.symbols.is_empty() || self.symbols.iter().any(|s| s.eq_ignore_ascii_case(&kline.symbol)))
+            && (self.intervals.is_empty() || self.intervals.contains(&kline.interval))
+            && self.start_ms.is_none_or(|start| kline.open_time_ms >= start)
+            && self.end_ms.is_none_or(|end| kline.open_time_ms < end)
+    }
+}
+
+// 文件读取器, 读取 FileWriter 写入的 kline_YYYYMMDD-HHMM.zst / .bin 文件（包括分区子目录）
+pub struct FileReader {
+    base_path: PathBuf,
+    partition: Option<String>,
+}
+
+impl FileReader {
+    pub fn new(base_path: impl Into<PathBuf>) -> Self {
+        Self {
+            base_path: base_path.into(),
+            partition: None,
+        }
+    }
+
+    /// 使用与 FileWriterConfig 相同的分区模板, 按过滤条件跳过不相关的分区目录
+    pub fn with_partition(mut self, partition: Option<String>) -> Self {
+        self.partition = partition;
+        self
+    }
+
+    /// 按文件名中的轮转时间（UTC+8）和分区目录列出可能包含满足条件数据的文件, 按路径排序
+    ///
+    /// 早期的 FileWriter 按写入时间轮转, K线在收盘后写入, 可能落在开盘时间之后的文件中,
+    /// 因此结束时间按过滤条件中最长的K线周期放宽, 并多读取其后的一个文件
+    pub fn files(&self, filter: &KlineFilter) -> Result<Vec<PathBuf>> {
+        let slack = filter.intervals.iter().map(|i| i.duration_ms()).max().unwrap_or(0);
+        let end_ms = filter.end_ms.map(|end| end.saturating_add(slack));
+        let mut dirs = vec![self.base_path.clone()];
+        let mut selected = Vec::new();
+        while let Some(dir) = dirs.pop() {
+            let mut files = Vec::new();
+            for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read dir {:?}", dir))? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if let Some(start) = period_start_ms(&path) {
+                    files.push((start, path));
+                }
+            }
+            if files.is_empty() || !self.partition_matches(&dir, filter) {
+                continue;
+            }
+            files.sort();
+
+            // 每个文件覆盖到同目录下一个文件开始为止, 最后一个文件不限; 结束时间之后的第一个文件也读取
+            for (i, (_, path)) in files.iter().enumerate() {
+                let next_start = files.get(i + 1).map(|(start, _)| *start);
+                let after_start = match (filter.start_ms, next_start) {
+                    (Some(start), Some(next)) => next > start,
+                    _ => true,
+                };
+                let before_end = match (end_ms, i.checked_sub(1)) {
+                    (Some(end), Some(prev)) => files[prev].0 < end,
+                    _ => true,
+                };
+                if after_start && before_end {
+                    selected.push(path.clone());
+                }
+            }
+        }
+        selected.sort();
+        Ok(selected)
+    }
+
+    /// 分区目录是否可能包含满足过滤条件的数据
+    fn partition_matches(&self, dir: &Path, filter: &KlineFilter) -> bool {
+        let Some(template) = &self.partition else { return true };
+        let Ok(relative) = dir.strip_prefix(&self.base_path) else { return true };
+        let parts = relative.iter().filter_map(|p| p.to_str()).collect::<Vec<_>>();
+        let names = template.split('/').filter(|p| !p.is_empty()).collect::<Vec<_>>();
+        if parts.len() != names.len() {
+            return false;
+        }
+        parts.iter().zip(names).all(|(part, name)| match name {
+            "{exchange}" => filter.exchange.as_ref().is_none_or(|e| e == part),
+            "{symbol}" => filter.symbols.is_empty() || filter.symbols.iter().any(|s| s.eq_ignore_ascii_case(part)),
+            "{interval}" => filter.intervals.is_empty() || filter.intervals.iter().any(|i| i.as_str() == *part),
+            name if name.starts_with('{') => true,
+            name => name == *part,
+        })
+    }
+
+    /// 按文件顺序读取满足条件的K线（同一文件内为写入顺序）
+    pub fn read(&self, filter: &KlineFilter) -> Result<KlineIter> {
+        let files = self.files(filter)?;
+        Ok(KlineIter::new(files, filter.clone()))
+    }
+
+    /// 只读取指定文件
+    pub fn read_file(&self, path: &Path, filter: &KlineFilter) -> KlineIter {
+        KlineIter::new(vec![path.to_path_buf()], filter.clone())
+    }
+}
+
+/// 从文件名 kline_YYYYMMDD-HHMM.zst / .bin 解析轮转周期的开始时间
+pub(crate) fn period_start_ms(path: &Path) -> Option<u64> {
+    FileFormat::from_path(path)?;
+    let name = path.file_stem()?.to_str()?;
+    let time = name.strip_prefix("kline_")?;
+    let naive = NaiveDateTime::parse_from_str(time, "%Y%m%d-%H%M").ok()?;
+    let start = FixedOffset::east_opt(8 * 3600).unwrap().from_local_datetime(&naive).single()?;
+    Some(start.timestamp_millis() as u64)
+}
+
+type ZstdLines = std::io::Lines<BufReader<Decoder<'static, BufReader<File>>>>;
+
+// 当前正在读取的文件
+enum Source {
+    JsonLines(ZstdLines),
+    /// 二进制文件整体读入, 按帧解码
+    Binary { data: Vec<u8>, pos: usize, rows: std::vec::IntoIter<SimpleKLine> },
+}
+
+impl Source {
+    fn open(path: &Path) -> Result<Self> {
+        match FileFormat::from_path(path) {
+            Some(FileFormat::Binary) => {
+                let data = fs::read(path)?;
+                binary::read_header(&mut data.as_slice())?;
+                Ok(Source::Binary { data, pos: binary::HEADER_LEN, rows: Vec::new().into_iter() })
+            }
+            _ => Ok(Source::JsonLines(BufReader::new(Decoder::new(File::open(path)?)?).lines())),
+        }
+    }
+
+    /// 读取下一条记录, 文件结束返回 None, 出错时该文件剩余部分无法读取
+    fn next_kline(&mut self, path: &Path) -> Option<Result<SimpleKLine>> {
+        match self {
+            Source::JsonLines(lines) => loop {
+                let line = match lines.next()? {
+                    Ok(line) => line,
+                    Err(e) => return Some(Err(e.into())),
+                };
+                // 兼容实时录制早期写入的 [index, kline] 格式
+                let kline = serde_json::from_str::<SimpleKLine>(&line)
+                    .or_else(|_| serde_json::from_str::<(usize, SimpleKLine)>(&line).map(|(_, k)| k));
+                match kline {
+                    Ok(kline) => return Some(Ok(kline)),
+                    Err(e) => warn!("跳过无法解析的记录 {:?}: {}", path, e),
+                }
+            },
+            Source::Binary { data, pos, rows } => loop {
+                if let Some(kline) = rows.next() {
+                    return Some(Ok(kline));
+                }
+                if *pos >= data.len() {
+                    return None;
+                }
+                let block = zstd::zstd_safe::find_frame_compressed_size(&data[*pos..])
+                    .map_err(|code| anyhow::anyhow!("incomplete frame: {}", zstd::zstd_safe::get_error_name(code)))
+                    .and_then(|size| {
+                        let frame = &data[*pos..*pos + size];
+                        *pos += size;
+                        Ok(zstd::stream::decode_all(frame)?)
+                    })
+                    .and_then(|block| binary::decode_block(&block));
+                match block {
+                    Ok(block) => *rows = block.into_iter(),
+                    Err(e) => {
+                        // 之后的数据无法定位, 放弃该文件剩余部分
+                        *pos = data.len();
+                        return Some(Err(e));
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// K线迭代器
+///
+/// json lines 文件中可能有多个 zstd 帧（每次 flush 写出一帧）, 崩溃留下的不完整帧在解码出错时跳过该文件剩余部分;
+/// 二进制文件按块解码, 规则相同
+pub struct KlineIter {
+    files: std::vec::IntoIter<PathBuf>,
+    current: Option<(PathBuf, Source)>,
+    filter: KlineFilter,
+}
+
+impl KlineIter {
+    fn new(files: Vec<PathBuf>, filter: KlineFilter) -> Self {
+        Self {
+            files: files.into_iter(),
+            current: None,
+            filter,
+        }
+    }
+
+    fn open_next(&mut self) -> bool {
+        for path in self.files.by_ref() {
+            match Source::open(&path) {
+                Ok(source) => {
+                    self.current = Some((path, source));
+                    return true;
+                }
+                Err(e) => warn!("打开 {:?} 失败: {}", path, e),
+            }
+        }
+        false
+    }
+}
+
+impl Iterator for KlineIter {
+    type Item = SimpleKLine;
+
+    fn next(&mut self) -> Option<SimpleKLine> {
+        loop {
+            if self.current.is_none() && !self.open_next() {
+                return None;
+            }
+            let (path, source) = self.current.as_mut()?;
+            match source.next_kline(path) {
+                Some(Ok(kline)) if self.filter.matches(&kline) => return Some(kline),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    warn!("读取 {:?} 中断: {}", path, e);
+                    self.current = None;
+                }
+                None => self.current = None,
+            }
+        }
+    }
+}
+
+/// 共享内存读取器, 连接 ShmemWriter 创建的环形缓冲（见 [`crate::ring`]）
+pub struct ShmemReader {
+    // ring 指向 shmem 的内存, 需要与 shmem 一起释放
+    ring: RingReader,
+    _shmem: Shmem,
+}
+
+unsafe impl Send for ShmemReader {}
+
+impl ShmemReader {
+    /// 按 shmem_name 连接, 从当前最新位置开始读取
+    pub fn open(shmem_name: &str) -> Result<Self> {
+        let shmem = ShmemConf::new()
+            .os_id(shmem_name)
+            .open()
+            .with_context(|| format!("Failed to open shared memory {}", shmem_name))?;
+        let ring = unsafe { RingReader::attach(shmem.as_ptr(), shmem.len())? };
+        Ok(Self { ring, _shmem: shmem })
+    }
+
+    /// 读取下一条原始记录, 没有新数据时返回 None
+    pub fn try_read(&mut self) -> Option<RingRead> {
+        self.ring.try_read()
+    }
+
+    /// 读取下一条记录并反序列化, 没有新数据时返回 None
+    ///
+    /// 读取端落后被覆盖时返回错误, 之后从最新位置继续读取
+    pub fn try_next<T: DeserializeOwned>(&mut self) -> Result<Option<T>> {
+        match self.ring.try_read() {
+            None => Ok(None),
+            Some(RingRead::Record(data)) => Ok(Some(serde_json::from_slice(&data)?)),
+            Some(RingRead::Overrun { lost_bytes }) => bail!("shared memory overrun, lost {} bytes", lost_bytes),
+        }
+    }
+}
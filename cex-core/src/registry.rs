@@ -0,0 +1,94 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use crate::KlineInterval;
+
+/// 一条K线流: (symbol, interval)
+///
+/// symbol 统一转为小写, 因此 `BTCUSDT` 与 `btcusdt` 是同一条流
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StreamKey {
+    pub symbol: String,
+    pub interval: KlineInterval,
+}
+
+impl StreamKey {
+    pub fn new(symbol: &str, interval: KlineInterval) -> Self {
+        Self {
+            symbol: symbol.to_lowercase(),
+            interval,
+        }
+    }
+}
+
+#[derive(Default)]
+struct RegistryInner {
+    keys: Vec<StreamKey>,
+    index: HashMap<StreamKey, usize>,
+    /// 推送未收盘K线的流
+    partial: HashSet<usize>,
+}
+
+/// K线流编号表, `ChannelMsg::Kline` 中的 index 即为这里的编号
+///
+/// 编号从 0 开始按注册顺序分配: 用订阅列表创建时, 第 i 个 (symbol, interval) 的编号就是 i。
+/// 编号一经分配不再改变, 取消订阅也不回收, 断线重连后同一条流总是得到同一个编号。
+/// 克隆后共享同一张表。
+#[derive(Clone, Default)]
+pub struct StreamRegistry(Arc<RwLock<RegistryInner>>);
+
+impl StreamRegistry {
+    pub fn new(pair_list: &[(String, KlineInterval)]) -> Self {
+        let registry = Self::default();
+        for (symbol, interval) in pair_list {
+            registry.register(symbol, *interval);
+        }
+        registry
+    }
+
+    /// 注册一条流并返回编号, 已注册的返回原编号
+    pub fn register(&self, symbol: &str, interval: KlineInterval) -> usize {
+        let key = StreamKey::new(symbol, interval);
+        if let Some(index) = self.0.read().unwrap().index.get(&key) {
+            return *index;
+        }
+        let mut inner = self.0.write().unwrap();
+        if let Some(index) = inner.index.get(&key) {
+            return *index;
+        }
+        let index = inner.keys.len();
+        inner.keys.push(key.clone());
+        inner.index.insert(key, index);
+        index
+    }
+
+    pub fn get(&self, symbol: &str, interval: KlineInterval) -> Option<usize> {
+        self.0.read().unwrap().index.get(&StreamKey::new(symbol, interval)).copied()
+    }
+
+    /// 设置该流是否推送未收盘K线
+    pub fn set_partial(&self, index: usize, partial: bool) {
+        let mut inner = self.0.write().unwrap();
+        if partial {
+            inner.partial.insert(index);
+        } else {
+            inner.partial.remove(&index);
+        }
+    }
+
+    pub fn is_partial(&self, index: usize) -> bool {
+        self.0.read().unwrap().partial.contains(&index)
+    }
+
+    pub fn key(&self, index: usize) -> Option<StreamKey> {
+        self.0.read().unwrap().keys.get(index).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.read().unwrap().keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,216 @@
+//! 共享内存 SPSC 环形缓冲协议
+//!
+//! 内存布局: [`RingHeader`]（256 字节）之后是数据区, 数据区大小（capacity）为 8 的倍数。
+//! 每条记录为 u32 长度 + 数据, 按 8 字节对齐; 数据区末尾放不下时写入长度为 `u32::MAX` 的填充标记, 从数据区开头继续。
+//!
+//! 写入端不等待读取端, 读取端落后超过 capacity 时数据会被覆盖。写入前先发布 claim_seq, 写完再发布 write_seq,
+//! 读取端复制数据后检查 claim_seq, 发现读过的区域可能已被覆盖时丢弃该记录并报告溢出（seqlock 方式）。
+
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+
+use anyhow::{ensure, Result};
+
+pub const RING_MAGIC: u32 = u32::from_le_bytes(*b"CEXR");
+pub const RING_VERSION: u32 = 1;
+pub const RING_HEADER_LEN: usize = std::mem::size_of::<RingHeader>();
+
+const PADDING: u32 = u32::MAX;
+
+/// 环形缓冲头部, 写入端和读取端各自更新的计数器放在不同的缓存行
+#[repr(C)]
+pub struct RingHeader {
+    magic: AtomicU32,
+    version: u32,
+    capacity: u64,
+    _pad0: [u8; 48],
+    /// 写入端正在写入的记录结束位置
+    claim_seq: AtomicU64,
+    _pad1: [u8; 56],
+    /// 已写完的位置（累计字节数, 单调递增）
+    write_seq: AtomicU64,
+    _pad2: [u8; 56],
+    /// 读取端已读到的位置
+    read_seq: AtomicU64,
+    /// 读取端检测到的溢出次数
+    overruns: AtomicU64,
+    _pad3: [u8; 48],
+}
+
+const _: () = assert!(RING_HEADER_LEN == 256);
+
+fn record_len(payload_len: usize) -> u64 {
+    (4 + payload_len as u64 + 7) & !7
+}
+
+/// 写入端
+pub struct RingWriter {
+    header: *const RingHeader,
+    data: *mut u8,
+    capacity: u64,
+    seq: u64,
+}
+
+impl RingWriter {
+    /// 在 `len` 字节的内存上初始化环形缓冲
+    ///
+    /// # Safety
+    /// `ptr` 指向至少 `len` 字节、按 8 字节对齐且在 RingWriter 生命周期内有效的可写内存
+    pub unsafe fn init(ptr: *mut u8, len: usize) -> Result<Self> {
+        ensure!(len > RING_HEADER_LEN + 64, "shared memory too small: {}", len);
+        let capacity = ((len - RING_HEADER_LEN) & !7) as u64;
+
+        std::ptr::write_bytes(ptr, 0, RING_HEADER_LEN);
+        let header = ptr as *mut RingHeader;
+        (*header).version = RING_VERSION;
+        (*header).capacity = capacity;
+        // magic 最后写入, 读取端看到 magic 时其它字段已初始化
+        (*header).magic.store(RING_MAGIC, Ordering::Release);
+
+        Ok(Self {
+            header,
+            data: ptr.add(RING_HEADER_LEN),
+            capacity,
+            seq: 0,
+        })
+    }
+
+    fn header(&self) -> &RingHeader {
+        unsafe { &*self.header }
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// 读取端落后的字节数
+    pub fn reader_lag(&self) -> u64 {
+        self.seq.saturating_sub(self.header().read_seq.load(Ordering::Acquire))
+    }
+
+    /// 读取端检测到的溢出次数
+    pub fn overruns(&self) -> u64 {
+        self.header().overruns.load(Ordering::Relaxed)
+    }
+
+    /// 写入一条记录, 不等待读取端
+    pub fn push(&mut self, payload: &[u8]) -> Result<()> {
+        let len = record_len(payload.len());
+        ensure!(len <= self.capacity / 2, "record too large: {} bytes", payload.len());
+
+        let offset = self.seq % self.capacity;
+        let padding = if len > self.capacity - offset { self.capacity - offset } else { 0 };
+        let end = self.seq + padding + len;
+
+        let header = self.header();
+        header.claim_seq.store(end, Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        unsafe {
+            if padding > 0 {
+                std::ptr::write_volatile(self.data.add(offset as usize) as *mut u32, PADDING);
+            }
+            let at = self.data.add(((self.seq + padding) % self.capacity) as usize);
+            std::ptr::write_volatile(at as *mut u32, payload.len() as u32);
+            std::ptr::copy_nonoverlapping(payload.as_ptr(), at.add(4), payload.len());
+        }
+
+        header.write_seq.store(end, Ordering::Release);
+        self.seq = end;
+        Ok(())
+    }
+}
+
+/// 读取结果
+#[derive(Debug)]
+pub enum RingRead {
+    Record(Vec<u8>),
+    /// 读取端落后被覆盖, 已跳到最新位置
+    Overrun { lost_bytes: u64 },
+}
+
+/// 读取端
+pub struct RingReader {
+    header: *const RingHeader,
+    data: *const u8,
+    capacity: u64,
+    pos: u64,
+}
+
+impl RingReader {
+    /// 连接已初始化的环形缓冲, 从当前写入位置开始读取
+    ///
+    /// # Safety
+    /// `ptr` 指向至少 `len` 字节、按 8 字节对齐且在 RingReader 生命周期内有效的内存
+    pub unsafe fn attach(ptr: *const u8, len: usize) -> Result<Self> {
+        ensure!(len >= RING_HEADER_LEN, "shared memory too small: {}", len);
+        let header = ptr as *const RingHeader;
+        let magic = (*header).magic.load(Ordering::Acquire);
+        ensure!(magic == RING_MAGIC, "invalid ring magic: {:#x}", magic);
+        ensure!((*header).version == RING_VERSION, "unsupported ring version: {}", (*header).version);
+        let capacity = (*header).capacity;
+        ensure!(capacity as usize <= len - RING_HEADER_LEN, "invalid ring capacity: {}", capacity);
+
+        let pos = (*header).write_seq.load(Ordering::Acquire);
+        Ok(Self {
+            header,
+            data: ptr.add(RING_HEADER_LEN),
+            capacity,
+            pos,
+        })
+    }
+
+    fn header(&self) -> &RingHeader {
+        unsafe { &*self.header }
+    }
+
+    fn overrun(&mut self) -> RingRead {
+        let latest = self.header().write_seq.load(Ordering::Acquire);
+        self.header().overruns.fetch_add(1, Ordering::Relaxed);
+        let lost_bytes = latest.saturating_sub(self.pos);
+        self.pos = latest;
+        self.header().read_seq.store(self.pos, Ordering::Release);
+        RingRead::Overrun { lost_bytes }
+    }
+
+    /// 读取下一条记录, 没有新数据时返回 None
+    pub fn try_read(&mut self) -> Option<RingRead> {
+        let start = self.pos;
+        let written = self.header().write_seq.load(Ordering::Acquire);
+        if start == written {
+            return None;
+        }
+        if written - start > self.capacity {
+            return Some(self.overrun());
+        }
+
+        let mut pos = start;
+        let mut offset = pos % self.capacity;
+        let mut len = unsafe { std::ptr::read_volatile(self.data.add(offset as usize) as *const u32) };
+        if len == PADDING {
+            pos += self.capacity - offset;
+            offset = 0;
+            len = unsafe { std::ptr::read_volatile(self.data as *const u32) };
+        }
+
+        let payload = if record_len(len as usize) <= self.capacity - offset && pos < written {
+            let mut payload = vec![0u8; len as usize];
+            unsafe {
+                std::ptr::copy_nonoverlapping(self.data.add(offset as usize + 4), payload.as_mut_ptr(), len as usize);
+            }
+            Some(payload)
+        } else {
+            None
+        };
+
+        // 复制完成后确认这段区域没有被写入端覆盖
+        fence(Ordering::Acquire);
+        let claimed = self.header().claim_seq.load(Ordering::Relaxed);
+        let Some(payload) = payload.filter(|_| claimed <= start + self.capacity) else {
+            return Some(self.overrun());
+        };
+
+        self.pos = pos + record_len(payload.len());
+        self.header().read_seq.store(self.pos, Ordering::Release);
+        Some(RingRead::Record(payload))
+    }
+}
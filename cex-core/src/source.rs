@@ -0,0 +1,221 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use crossbeam::channel::Sender;
+use tokio::task::JoinHandle;
+use rand::Rng;
+use tracing::{error, info, warn};
+
+use crate::{ChannelMsg, ConnectionStatus, KlineInterval};
+
+/// 订阅选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SubscribeOptions {
+    /// 同时推送未收盘K线的更新（`KlineEvent::is_final` 为 false）
+    pub partial: bool,
+}
+
+/// 行情数据源，每个交易所 crate 各自实现，策略运行端只依赖该 trait
+#[async_trait]
+pub trait MarketDataSource: Send + Sync {
+    /// 交易所名称，与 `SimpleKLine::exchange` 一致
+    fn name(&self) -> &'static str;
+
+    /// 该交易所支持的K线周期
+    fn supported_intervals(&self) -> &'static [KlineInterval];
+
+    /// 订阅 (symbol, interval) 列表，K线通过 `tx` 推送，在后台运行并自动重连
+    async fn subscribe(&self, pair_list: Vec<(String, KlineInterval)>, tx: Sender<ChannelMsg>) -> Result<()> {
+        self.subscribe_with(pair_list, SubscribeOptions::default(), tx).await
+    }
+
+    /// 与 `subscribe` 相同，使用指定的订阅选项
+    async fn subscribe_with(
+        &self,
+        pair_list: Vec<(String, KlineInterval)>,
+        options: SubscribeOptions,
+        tx: Sender<ChannelMsg>,
+    ) -> Result<()>;
+
+    /// 取消订阅 (symbol, interval) 列表
+    async fn unsubscribe(&self, pair_list: Vec<(String, KlineInterval)>) -> Result<()>;
+
+    /// 检查订阅列表中的周期是否都被支持
+    fn check_intervals(&self, pair_list: &[(String, KlineInterval)]) -> Result<()> {
+        let supported = self.supported_intervals();
+        for (symbol, interval) in pair_list {
+            if !supported.contains(interval) {
+                anyhow::bail!("{} 不支持的K线周期: {} {}", self.name(), symbol, interval);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub type SubscribeFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+pub type SubscribeFn = fn(Vec<(String, KlineInterval)>, SubscribeOptions, Sender<ChannelMsg>) -> SubscribeFuture;
+
+struct SubscriptionTask {
+    pair_list: Vec<(String, KlineInterval)>,
+    options: SubscribeOptions,
+    tx: Sender<ChannelMsg>,
+    handle: JoinHandle<()>,
+}
+
+/// 每次订阅对应一个后台任务，取消订阅时用剩余的列表重启任务
+///
+/// 供只能在建立连接时发送订阅请求的数据源使用
+pub struct TaskSubscriptions {
+    run: SubscribeFn,
+    tasks: Mutex<Vec<SubscriptionTask>>,
+}
+
+impl TaskSubscriptions {
+    pub fn new(run: SubscribeFn) -> Self {
+        Self {
+            run,
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn spawn(&self, pair_list: Vec<(String, KlineInterval)>, options: SubscribeOptions, tx: Sender<ChannelMsg>) {
+        let handle = tokio::spawn((self.run)(pair_list.clone(), options, tx.clone()));
+        self.tasks.lock().unwrap().push(SubscriptionTask { pair_list, options, tx, handle });
+    }
+
+    pub fn remove(&self, pair_list: &[(String, KlineInterval)]) {
+        let mut tasks = self.tasks.lock().unwrap();
+        for task in std::mem::take(&mut *tasks) {
+            if !task.pair_list.iter().any(|p| pair_list.contains(p)) {
+                tasks.push(task);
+                continue;
+            }
+            task.handle.abort();
+            let remaining = task.pair_list.into_iter().filter(|p| !pair_list.contains(p)).collect::<Vec<_>>();
+            info!("取消订阅后剩余: {:?}", remaining);
+            if !remaining.is_empty() {
+                let handle = tokio::spawn((self.run)(remaining.clone(), task.options, task.tx.clone()));
+                tasks.push(SubscriptionTask { pair_list: remaining, options: task.options, tx: task.tx, handle });
+            }
+        }
+    }
+}
+
+// 数据源被释放时停止所有订阅任务
+impl Drop for TaskSubscriptions {
+    fn drop(&mut self) {
+        for task in self.tasks.get_mut().unwrap().drain(..) {
+            task.handle.abort();
+        }
+    }
+}
+
+/// 断线重连策略: 指数退避 + 随机抖动
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// 第一次重连前的等待时间
+    pub initial_delay: Duration,
+    /// 等待时间上限
+    pub max_delay: Duration,
+    /// 每次失败后等待时间的倍数, 不小于 1
+    pub multiplier: f64,
+    /// 抖动比例 (0~1), 实际等待时间在 delay * (1 ± jitter) 之间
+    pub jitter: f64,
+    /// 连续重连失败的最大次数, None 表示一直重连
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 第 attempt 次（从 1 开始）重连前的等待时间
+    ///
+    /// 取值无效时按有效范围处理: multiplier 小于 1 或为 NaN 时按 1, jitter 限制在 0~1, NaN 按 0
+    pub fn delay(&self, attempt: u32) -> Duration {
+        // f64::max 忽略 NaN
+        let multiplier = self.multiplier.max(1.0);
+        let exp = multiplier.powi(attempt.saturating_sub(1).min(64) as i32);
+        let base = self.initial_delay.as_secs_f64() * exp;
+        // 0 * inf 为 NaN, f64::min 同样忽略 NaN
+        let base = base.min(self.max_delay.as_secs_f64());
+        let jitter = if self.jitter.is_nan() { 0.0 } else { self.jitter.clamp(0.0, 1.0) };
+        let factor = if jitter > 0.0 {
+            rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter)
+        } else {
+            1.0
+        };
+        Duration::from_secs_f64((base * factor).min(self.max_delay.as_secs_f64()))
+    }
+}
+
+/// 按 [`ReconnectPolicy`] 控制重连节奏, 并把连接状态以 `ChannelMsg::Status` 推送给下游
+pub struct Reconnector {
+    source: String,
+    policy: ReconnectPolicy,
+    tx: Sender<ChannelMsg>,
+    attempt: u32,
+}
+
+impl Reconnector {
+    pub fn new(source: &str, policy: ReconnectPolicy, tx: Sender<ChannelMsg>) -> Self {
+        Self {
+            source: source.to_string(),
+            policy,
+            tx,
+            attempt: 0,
+        }
+    }
+
+    /// 连接成功, 重置重连计数
+    pub fn connected(&mut self) {
+        self.attempt = 0;
+        info!("{} 连接成功", self.source);
+        self.send(ConnectionStatus::Connected { source: self.source.clone() });
+    }
+
+    /// 连接断开, 等待退避时间后返回 true 表示继续重连, 返回 false 表示放弃
+    pub async fn disconnected(&mut self, reason: String) -> bool {
+        warn!("{} 连接断开: {}", self.source, reason);
+        self.send(ConnectionStatus::Disconnected { source: self.source.clone(), reason });
+
+        self.attempt += 1;
+        if let Some(max_attempts) = self.policy.max_attempts {
+            if self.attempt > max_attempts {
+                error!("{} 重连 {} 次失败, 放弃重连", self.source, max_attempts);
+                self.send(ConnectionStatus::GaveUp { source: self.source.clone(), attempts: max_attempts });
+                return false;
+            }
+        }
+
+        let delay = self.policy.delay(self.attempt);
+        info!("{} 第 {} 次重连, 等待 {:?}", self.source, self.attempt, delay);
+        self.send(ConnectionStatus::Reconnecting {
+            source: self.source.clone(),
+            attempt: self.attempt,
+            delay_ms: delay.as_millis() as u64,
+        });
+        tokio::time::sleep(delay).await;
+        true
+    }
+
+    fn send(&self, status: ConnectionStatus) {
+        if let Err(e) = self.tx.try_send(ChannelMsg::Status(status)) {
+            error!("Failed to send status message: {}", e);
+        }
+    }
+}
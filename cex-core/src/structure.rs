@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use tracing::error;
+
+use crate::SimpleKLine;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Signal {
@@ -114,15 +117,64 @@ impl Trade {
     pub fn calculate(&mut self) {
         if self.enter_position.as_ref().is_some() && self.exit_position.as_ref().is_some() {
             match self.direction {
-                Direction::Long => {
+                Direction::Long | Direction::LongClose => {
                     self.roi = Some((self.exit_position.as_ref().unwrap().price - self.enter_position.as_ref().unwrap().price) / self.enter_position.as_ref().unwrap().price * 100.0 - self.fee * 100.0);
                 }
-                Direction::Short => {
+                Direction::Short | Direction::ShortClose => {
                     self.roi = Some((self.enter_position.as_ref().unwrap().price - self.exit_position.as_ref().unwrap().price) / self.enter_position.as_ref().unwrap().price * 100.0 - self.fee * 100.0);
                 }
                 _ => {}
             } 
         }
     }
-}
 
+    /// 按运行端的开平仓规则处理策略信号, 产生开仓或平仓时返回当时的交易快照
+    ///
+    /// 已有持仓时忽略入场信号, 无持仓时忽略出场信号; 平仓后返回完整交易并重置自身
+    pub fn on_signal(&mut self, signal: Signal, kline: &SimpleKLine) -> Option<Trade> {
+        match signal {
+            Signal::Enter { direction, price } => {
+                if self.enter_position.is_some() {
+                    error!("入场时已有持仓，该策略不支持重复入场");
+                    return None;
+                }
+                self.exchange = kline.exchange.clone();
+                self.symbol = kline.symbol.clone();
+                self.direction = direction;
+                self.enter_position = Some(Position {
+                    price,
+                    entry_bar_index: 0,
+                    size: 1.0,
+                });
+                self.enter_time = kline.close_time_ms as i64;
+                Some(self.clone())
+            }
+            Signal::Exit { reason, price } => {
+                if self.enter_position.is_none() {
+                    error!("暂未入场，不处理该信号");
+                    return None;
+                }
+                // 更新交易方向
+                match self.direction {
+                    Direction::Long => {
+                        self.direction = Direction::LongClose;
+                    },
+                    Direction::Short => {
+                        self.direction = Direction::ShortClose;
+                    },
+                    _ => {},
+                }
+                self.exit_position = Some(Position {
+                    price,
+                    entry_bar_index: 0,
+                    size: 1.0,
+                });
+                self.exit_reason = reason;
+                self.exit_time = kline.close_time_ms as i64;
+                self.calculate();
+                // 出场后重置交易信息
+                Some(std::mem::take(self))
+            }
+        }
+    }
+}
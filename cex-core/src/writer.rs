@@ -1,143 +1,454 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::fs::{self, File, OpenOptions};
-use std::io::Write;
-use zstd::Encoder;
+use std::io::{Read, Seek, SeekFrom, Write};
 use chrono::{DateTime, Utc, TimeZone};
 use shared_memory::{ShmemConf, Shmem};
-use anyhow::{Result, Context};
-use serde::Serialize;
+use anyhow::{bail, ensure, Result, Context};
+use serde::{Deserialize, Serialize};
 use tracing::{info, error, warn};
 use std::sync::Arc;
-use tokio::sync::Mutex as TokioMutex;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crossbeam::channel::{bounded, select, tick, unbounded, Receiver, Sender, TrySendError};
+use crate::binary;
+use crate::ring::RingWriter;
+use crate::SimpleKLine;
+use tokio::sync::oneshot;
+
+/// 带事件时间的记录, 文件写入器按事件时间选择轮转文件
+pub trait Record: Serialize + Send + Sync {
+    /// 事件时间（毫秒）
+    fn event_time_ms(&self) -> u64;
+
+    /// 分区字段的值, 用于替换分区模板中的 {name}
+    fn partition_value(&self, _name: &str) -> Option<String> {
+        None
+    }
+
+    /// 二进制格式只能写入K线
+    fn as_kline(&self) -> Option<&SimpleKLine> {
+        None
+    }
+}
+
+impl Record for SimpleKLine {
+    fn event_time_ms(&self) -> u64 {
+        self.open_time_ms
+    }
+
+    fn partition_value(&self, name: &str) -> Option<String> {
+        match name {
+            "exchange" => Some(self.exchange.clone()),
+            "symbol" => Some(self.symbol.clone()),
+            "interval" => Some(self.interval.to_string()),
+            _ => None,
+        }
+    }
+
+    fn as_kline(&self) -> Option<&SimpleKLine> {
+        Some(self)
+    }
+}
+
+// 对象安全的 Record, 记录装箱后交给后台线程写入
+trait QueuedRecord: Send {
+    fn event_time_ms(&self) -> u64;
+    fn partition_value(&self, name: &str) -> Option<String>;
+    fn as_kline(&self) -> Option<&SimpleKLine>;
+    fn to_json(&self) -> serde_json::Result<Vec<u8>>;
+}
+
+impl<T: Record + 'static> QueuedRecord for T {
+    fn event_time_ms(&self) -> u64 {
+        Record::event_time_ms(self)
+    }
+
+    fn partition_value(&self, name: &str) -> Option<String> {
+        Record::partition_value(self, name)
+    }
+
+    fn as_kline(&self) -> Option<&SimpleKLine> {
+        Record::as_kline(self)
+    }
+
+    fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(self)
+    }
+}
+
+/// 文件写入格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileFormat {
+    /// zstd 压缩的 json lines, 文件名 kline_*.zst
+    ///
+    /// 每行一根K线; 早期的实时录制写入的是 `[index, kline]`, 读取时两种行都能识别
+    #[default]
+    JsonLines,
+    /// 二进制列式格式（见 [`crate::binary`]）, 文件名 kline_*.bin
+    Binary,
+}
+
+impl FileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::JsonLines => "zst",
+            FileFormat::Binary => "bin",
+        }
+    }
+
+    /// 根据文件扩展名判断格式
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "zst" => Some(FileFormat::JsonLines),
+            "bin" => Some(FileFormat::Binary),
+            _ => None,
+        }
+    }
+
+    fn header_len(&self) -> usize {
+        match self {
+            FileFormat::JsonLines => 0,
+            FileFormat::Binary => binary::HEADER_LEN,
+        }
+    }
+}
 
 // 文件写入器的配置
 #[derive(Clone)]
 pub struct FileWriterConfig {
     pub base_path: PathBuf,
     pub rotation_interval: i64,  // 文件轮转间隔（秒）
+    /// 分区目录模板, 如 "{exchange}/{symbol}/{interval}", None 时全部写入 base_path
+    pub partition: Option<String>,
+    /// 分区超过该时间（秒）没有写入就关闭文件
+    pub idle_timeout: i64,
+    /// 缓冲超过该大小（字节）时写出一个压缩帧
+    pub flush_bytes: usize,
+    /// 距上次写出超过该时间（秒）时写出一个压缩帧
+    pub flush_interval: i64,
+}
+
+impl Default for FileWriterConfig {
+    fn default() -> Self {
+        Self {
+            base_path: PathBuf::from("data"),
+            rotation_interval: 8 * 3600,
+            partition: None,
+            idle_timeout: 600,
+            flush_bytes: 64 * 1024,
+            flush_interval: 10,
+        }
+    }
+}
+
+impl FileWriterConfig {
+    /// 检查配置取值, 轮转间隔必须为正数
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.rotation_interval > 0, "rotation_interval must be positive: {}", self.rotation_interval);
+        Ok(())
+    }
+}
+
+/// 替换分区模板中的 {name}, 记录没有该字段时报错
+fn partition_dir(template: &str, data: &dyn QueuedRecord) -> Result<PathBuf> {
+    let mut dir = PathBuf::new();
+    for part in template.split('/').filter(|p| !p.is_empty()) {
+        match part.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+            Some(name) => {
+                let value = data
+                    .partition_value(name)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown partition field: {}", name))?;
+                dir.push(value);
+            }
+            None => dir.push(part),
+        }
+    }
+    Ok(dir)
+}
+
+/// 截断文件末尾不完整的 zstd 帧（写入过程中崩溃留下的）, 返回截掉的字节数
+///
+/// 二进制格式的文件头不完整时截断为空文件
+pub fn recover_file(path: &Path) -> Result<u64> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).context("Failed to open file for recovery"),
+    };
+    let len = file.metadata()?.len();
+
+    let header_len = FileFormat::from_path(path).unwrap_or_default().header_len() as u64;
+    let valid = if len < header_len { 0 } else { scan_frames(&mut file, header_len).context("Failed to read file for recovery")? };
+
+    let torn = len - valid;
+    if torn > 0 {
+        warn!("截断 {:?} 末尾不完整的帧: {} 字节", path, torn);
+        OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_len(valid))
+            .context("Failed to truncate torn frame")?;
+    }
+    Ok(torn)
+}
+
+/// 恢复时每次读取的字节数
+const SCAN_CHUNK: u64 = 64 * 1024;
+
+/// 从 offset 开始逐帧扫描, 返回最后一个完整帧的结束位置; 内存中只保留当前的帧
+fn scan_frames(file: &mut File, offset: u64) -> std::io::Result<u64> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut valid = offset;
+    let mut buf = Vec::new();
+    let mut eof = false;
+    loop {
+        match zstd::zstd_safe::find_frame_compressed_size(&buf) {
+            Ok(size) if size > 0 => {
+                valid += size as u64;
+                buf.drain(..size);
+            }
+            _ if eof => return Ok(valid),
+            // 帧不完整, 继续读取
+            _ => eof = file.take(SCAN_CHUNK).read_to_end(&mut buf)? == 0,
+        }
+    }
 }
 
-// 文件写入器
+// 某个分区当前打开的文件, 数据先缓冲在内存中, 按策略整帧写出
+struct PartitionFile {
+    path: PathBuf,
+    file: File,
+    format: FileFormat,
+    // json lines 格式的缓冲
+    buffer: Vec<u8>,
+    // 二进制格式的缓冲
+    rows: Vec<SimpleKLine>,
+    last_write_time: DateTime<Utc>,
+    last_flush_time: DateTime<Utc>,
+}
+
+impl PartitionFile {
+    fn open(path: PathBuf, format: FileFormat) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create directory")?;
+        }
+        recover_file(&path)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)  // 使用追加模式
+            .open(&path)
+            .context("Failed to create/open file")?;
+        if format == FileFormat::Binary {
+            if file.metadata()?.len() == 0 {
+                binary::write_header(&mut file).context("Failed to write header")?;
+            } else {
+                binary::read_header(&mut File::open(&path)?).with_context(|| format!("Cannot append to {:?}", path))?;
+            }
+        }
+
+        Ok(Self {
+            path,
+            file,
+            format,
+            buffer: Vec::new(),
+            rows: Vec::new(),
+            last_write_time: Utc::now(),
+            last_flush_time: Utc::now(),
+        })
+    }
+
+    fn push(&mut self, data: &dyn QueuedRecord) -> Result<()> {
+        match self.format {
+            FileFormat::JsonLines => {
+                let json = data.to_json().context("Failed to serialize data")?;
+                self.buffer.extend_from_slice(&json);
+                self.buffer.push(b'\n');
+            }
+            FileFormat::Binary => {
+                let kline = data.as_kline().context("Binary format only supports klines")?;
+                self.rows.push(kline.clone());
+            }
+        }
+        self.last_write_time = Utc::now();
+        Ok(())
+    }
+
+    /// 缓冲的大致字节数
+    fn buffered_len(&self) -> usize {
+        self.buffer.len() + self.rows.len() * binary::ROW_LEN
+    }
+
+    /// 把缓冲压缩成一个完整的帧, 一次写入并落盘
+    fn write_frame(&mut self) -> Result<()> {
+        if self.buffered_len() == 0 {
+            return Ok(());
+        }
+        let payload = match self.format {
+            FileFormat::JsonLines => std::mem::take(&mut self.buffer),
+            FileFormat::Binary => binary::encode_block(&std::mem::take(&mut self.rows))?,
+        };
+        let frame = zstd::bulk::compress(&payload, 3).context("Failed to compress frame")?;
+        self.file.write_all(&frame).context("Failed to write frame")?;
+        self.file.sync_data().context("Failed to sync file")?;
+        self.last_flush_time = Utc::now();
+        Ok(())
+    }
+
+    fn close(mut self) -> Result<()> {
+        self.write_frame()
+    }
+}
+
+/// 每个分区最多同时打开的周期数
+///
+/// 周期边界附近不同周期的K线交替到达（如上一周期的 4h K线和新周期的 1m K线）, 两个文件都保持打开,
+/// 不会反复关闭再打开; 更早周期的迟到数据打开对应文件追加后立即关闭
+const OPEN_PERIODS: usize = 2;
+
+// 文件写入器, 每个 (分区, 周期) 一个打开的文件
 pub struct FileWriter {
     config: FileWriterConfig,
-    current_file: Option<(PathBuf, Encoder<'static, File>)>,
-    current_period_start: DateTime<Utc>,
-    last_flush_time: DateTime<Utc>,
+    format: FileFormat,
+    files: HashMap<(PathBuf, i64), PartitionFile>,
 }
 
 impl FileWriter {
     pub fn new(config: FileWriterConfig) -> Self {
+        Self::with_format(config, FileFormat::JsonLines)
+    }
+
+    pub fn with_format(config: FileWriterConfig, format: FileFormat) -> Self {
         Self {
             config,
-            current_file: None,
-            current_period_start: Utc::now(),
-            last_flush_time: Utc::now(),
+            format,
+            files: HashMap::new(),
         }
     }
 
-    fn get_file_path(&self, timestamp: DateTime<Utc>) -> PathBuf {
-        let period_start = timestamp.timestamp() / self.config.rotation_interval * self.config.rotation_interval;
+    fn period_of(&self, event_time_ms: u64) -> i64 {
+        (event_time_ms / 1000) as i64 / self.config.rotation_interval
+    }
+
+    fn get_file_path(&self, partition: &Path, period: i64) -> PathBuf {
+        let period_start = period * self.config.rotation_interval;
         let period_start_dt = Utc.timestamp_opt(period_start, 0)
             .single()
-            .unwrap_or_else(|| Utc::now())
+            .unwrap_or_else(Utc::now)
             .with_timezone(&chrono::FixedOffset::east_opt(8 * 3600).unwrap());
             
         let filename = format!(
-            "kline_{}.zst",
-            period_start_dt.format("%Y%m%d-%H%M")
+            "kline_{}.{}",
+            period_start_dt.format("%Y%m%d-%H%M"),
+            self.format.extension()
         );
-        self.config.base_path.join(filename)
+        self.config.base_path.join(partition).join(filename)
     }
 
-    fn should_rotate_file(&self, timestamp: DateTime<Utc>) -> bool {
-        if self.current_file.is_none() {
-            return true;
-        }
-        
-        let current_period = self.current_period_start.timestamp() / self.config.rotation_interval;
-        let new_period = timestamp.timestamp() / self.config.rotation_interval;
-        current_period != new_period
+    /// 打开分区指定周期的文件, 已关闭周期的迟到数据会重新以追加模式打开对应文件
+    fn rotate_file(&mut self, partition: &Path, period: i64) -> Result<()> {
+        let file_path = self.get_file_path(partition, period);
+        info!("Rotated to new file: {:?}", file_path);
+        self.files.insert((partition.to_path_buf(), period), PartitionFile::open(file_path, self.format)?);
+        Ok(())
     }
 
-    async fn rotate_file(&mut self, timestamp: DateTime<Utc>) -> Result<()> {
-        if let Some((_, encoder)) = self.current_file.take() {
-            // 先完成压缩
-            let mut finished_encoder = encoder.finish().context("Failed to finish previous file")?;
-            // 再刷新文件
-            finished_encoder.flush().context("Failed to flush previous file")?;
+    /// 分区只保留最新的 [`OPEN_PERIODS`] 个周期的文件, 关闭更早的
+    fn close_old_periods(&mut self, partition: &Path) -> Result<()> {
+        let mut periods = self.files.keys().filter(|(p, _)| p == partition).map(|(_, period)| *period).collect::<Vec<_>>();
+        if periods.len() <= OPEN_PERIODS {
+            return Ok(());
         }
-
-        let file_path = self.get_file_path(timestamp);
-        if let Some(parent) = file_path.parent() {
-            fs::create_dir_all(parent).context("Failed to create directory")?;
+        periods.sort_unstable();
+        for period in &periods[..periods.len() - OPEN_PERIODS] {
+            if let Some(file) = self.files.remove(&(partition.to_path_buf(), *period)) {
+                file.close().context("Failed to close previous file")?;
+            }
         }
-
-        let file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .append(true)  // 使用追加模式
-            .open(&file_path)
-            .context("Failed to create/open file")?;
-            
-        let encoder = zstd::Encoder::new(file, 3).context("Failed to create zstd encoder")?;
-        self.current_file = Some((file_path.clone(), encoder));
-        self.current_period_start = timestamp;
-        self.last_flush_time = Utc::now();
-        
-        info!("Rotated to new file: {:?}", file_path);
         Ok(())
     }
 
-    async fn write<T: Serialize + Send + Sync>(&mut self, data: &T) -> Result<()> {
-        let timestamp = Utc::now();
-        
-        if self.should_rotate_file(timestamp) {
-            self.rotate_file(timestamp).await?;
+    /// 关闭超过 idle_timeout 没有写入的分区
+    fn close_idle_files(&mut self) {
+        let now = Utc::now();
+        let idle = self
+            .files
+            .iter()
+            .filter(|(_, file)| (now - file.last_write_time).num_seconds() >= self.config.idle_timeout)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in idle {
+            if let Some(file) = self.files.remove(&key) {
+                info!("关闭空闲文件: {:?}", file.path);
+                if let Err(e) = file.close() {
+                    error!("Failed to close idle file: {}", e);
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, data: &dyn QueuedRecord) -> Result<()> {
+        let partition = match &self.config.partition {
+            Some(template) => partition_dir(template, data)?,
+            None => PathBuf::new(),
+        };
+        // 按记录自己的事件时间选择周期
+        let period = self.period_of(data.event_time_ms());
+        let key = (partition, period);
+        if !self.files.contains_key(&key) {
+            self.rotate_file(&key.0, period)?;
         }
 
-        if let Some((_path, encoder)) = &mut self.current_file {
-            let json = serde_json::to_string(data).context("Failed to serialize data")?;
-            writeln!(encoder, "{}", json).context("Failed to write to file")?;
+        if let Some(file) = self.files.get_mut(&key) {
+            file.push(data)?;
+            if file.buffered_len() >= self.config.flush_bytes
+                || (file.last_write_time - file.last_flush_time).num_seconds() >= self.config.flush_interval
+            {
+                file.write_frame()?;
+            }
         } else {
             error!("没有可用的文件句柄");
             return Err(anyhow::anyhow!("No file handle available"));
         }
 
+        self.close_old_periods(&key.0)?;
+        self.close_idle_files();
         Ok(())
     }
 
-    async fn flush(&mut self) -> Result<()> {
-        if let Some((path, encoder)) = self.current_file.take() {
-            // 先完成压缩
-            let mut finished_encoder = encoder.finish().context("Failed to finish encoder")?;
-            // 再刷新文件
-            finished_encoder.flush().context("Failed to flush file")?;
-            
-            // 创建新的编码器
-            let file = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .append(true)
-                .open(&path)
-                .context("Failed to reopen file")?;
-            
-            let new_encoder = zstd::Encoder::new(file, 3).context("Failed to create new encoder")?;
-            self.current_file = Some((path, new_encoder));
-            self.last_flush_time = Utc::now();
-        } else {
+    /// 后台线程定时调用, 没有新记录时也能关闭空闲的文件, 按 flush_interval 写出缓冲
+    fn tick(&mut self) {
+        self.close_idle_files();
+        let now = Utc::now();
+        for file in self.files.values_mut() {
+            if file.buffered_len() > 0 && (now - file.last_flush_time).num_seconds() >= self.config.flush_interval {
+                if let Err(e) = file.write_frame() {
+                    error!("Failed to flush {:?}: {:#}", file.path, e);
+                }
+            }
+        }
+    }
+
+    /// 立即写出所有分区的缓冲
+    fn flush(&mut self) -> Result<()> {
+        self.close_idle_files();
+        if self.files.is_empty() {
             warn!("没有文件需要flush");
         }
+        for file in self.files.values_mut() {
+            file.write_frame()?;
+        }
         Ok(())
     }
 }
 
 impl Drop for FileWriter {
     fn drop(&mut self) {
-        if let Some((_, encoder)) = self.current_file.take() {
-            match encoder.finish().and_then(|mut finished_encoder| finished_encoder.flush()) {
-                Ok(_) => (),
-                Err(e) => error!("Failed to properly close file: {}", e),
+        for (_, file) in self.files.drain() {
+            if let Err(e) = file.close() {
+                error!("Failed to properly close file: {}", e);
             }
         }
     }
@@ -151,11 +462,12 @@ pub struct ShmemWriterConfig {
     pub shmem_name: String,
 }
 
-// 共享内存写入器
+// 共享内存写入器, 以 SPSC 环形缓冲（见 [`crate::ring`]）发布 json 记录
 pub struct ShmemWriter {
     config: ShmemWriterConfig,
-    shmem: Arc<Shmem>,
-    write_pos: Arc<AtomicUsize>,
+    // ring 指向 shmem 的内存, 需要与 shmem 一起释放
+    ring: RingWriter,
+    _shmem: Shmem,
 }
 
 // 实现Send和Sync trait
@@ -169,88 +481,399 @@ impl ShmemWriter {
             .os_id(&config.shmem_name)
             .create()
             .context("Failed to create shared memory")?;
+        let ring = unsafe { RingWriter::init(shmem.as_ptr(), shmem.len())? };
+        info!("共享内存 {} 已创建, 容量 {} 字节", config.shmem_name, ring.capacity());
 
         Ok(Self {
             config,
-            shmem: Arc::new(shmem),
-            write_pos: Arc::new(AtomicUsize::new(0)),
+            ring,
+            _shmem: shmem,
         })
     }
 
-    async fn write<T: Serialize + Send + Sync>(&self, data: &T) -> Result<()> {
-        let json = serde_json::to_string(data).context("Failed to serialize data")?;
-        let bytes = json.as_bytes();
-        
-        // 使用原子操作更新写入位置
-        let mut current_pos = self.write_pos.load(Ordering::Relaxed);
-        if current_pos + bytes.len() + 1 > self.config.shmem_size {
-            current_pos = 0;
-            self.write_pos.store(0, Ordering::Relaxed);
-        }
-
-        // 创建一个临时缓冲区
-        let mut buffer = Vec::with_capacity(bytes.len() + 1);
-        buffer.extend_from_slice(bytes);
-        buffer.push(b'\n');
-
-        // 一次性写入所有数据
-        unsafe {
-            std::ptr::copy_nonoverlapping(
-                buffer.as_ptr(),
-                self.shmem.as_ptr().add(current_pos),
-                buffer.len()
-            );
-        }
-        
-        self.write_pos.store(current_pos + buffer.len(), Ordering::Relaxed);
-        Ok(())
+    fn write(&mut self, data: &dyn QueuedRecord) -> Result<()> {
+        let json = data.to_json().context("Failed to serialize data")?;
+        self.ring.push(&json)
     }
 
-    async fn flush(&self) -> Result<()> {
+    fn flush(&self) -> Result<()> {
         // 共享内存不需要flush操作
+        let lag = self.ring.reader_lag();
+        if lag > self.ring.capacity() {
+            warn!("共享内存 {} 的读取端落后 {} 字节, 溢出 {} 次", self.config.shmem_name, lag, self.ring.overruns());
+        }
         Ok(())
     }
 }
 
+/// 多路写入时各写入器的错误, 未列出的写入器已写入成功
+#[derive(Debug)]
+pub struct SinkErrors(pub Vec<(String, anyhow::Error)>);
+
+impl std::fmt::Display for SinkErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let errors = self.0.iter().map(|(sink, e)| format!("{}: {:#}", sink, e)).collect::<Vec<_>>();
+        write!(f, "写入失败 [{}]", errors.join("; "))
+    }
+}
+
+impl std::error::Error for SinkErrors {}
+
+// 多路写入中的一个写入器, 创建失败时为 None, 由后台线程定时重试
+struct Sink {
+    name: String,
+    writer_type: WriterType,
+    inner: Option<WriterInner>,
+}
+
+impl Sink {
+    fn retry(&mut self) {
+        if self.inner.is_some() {
+            return;
+        }
+        if let Ok(inner) = create_inner(self.writer_type.clone()) {
+            info!("写入器 {} 重试创建成功", self.name);
+            self.inner = Some(inner);
+        }
+    }
+}
+
 // 内部写入器枚举
 enum WriterInner {
     File(FileWriter),
     Shmem(ShmemWriter),
+    /// 每个写入器的错误互不影响
+    Multi(Vec<Sink>),
 }
 
 impl WriterInner {
-    async fn write<T: Serialize + Send + Sync>(&mut self, data: &T) -> Result<()> {
+    fn write(&mut self, data: &dyn QueuedRecord) -> Result<()> {
         match self {
-            WriterInner::File(w) => w.write(data).await,
-            WriterInner::Shmem(w) => w.write(data).await,
+            WriterInner::File(w) => w.write(data),
+            WriterInner::Shmem(w) => w.write(data),
+            WriterInner::Multi(sinks) => {
+                let mut errors = Vec::new();
+                for sink in sinks.iter_mut() {
+                    let result = match &mut sink.inner {
+                        Some(inner) => inner.write(data),
+                        None => Err(anyhow::anyhow!("Writer not created")),
+                    };
+                    if let Err(e) = result {
+                        errors.push((sink.name.clone(), e));
+                    }
+                }
+                if errors.is_empty() { Ok(()) } else { Err(SinkErrors(errors).into()) }
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        match self {
+            WriterInner::File(w) => w.tick(),
+            WriterInner::Shmem(_) => {}
+            WriterInner::Multi(sinks) => {
+                for sink in sinks.iter_mut() {
+                    sink.retry();
+                    if let Some(inner) = &mut sink.inner {
+                        inner.tick();
+                    }
+                }
+            }
         }
     }
 
-    async fn flush(&mut self) -> Result<()> {
+    fn flush(&mut self) -> Result<()> {
         match self {
-            WriterInner::File(w) => w.flush().await,
-            WriterInner::Shmem(w) => w.flush().await,
+            WriterInner::File(w) => w.flush(),
+            WriterInner::Shmem(w) => w.flush(),
+            WriterInner::Multi(sinks) => {
+                let mut errors = Vec::new();
+                for sink in sinks.iter_mut() {
+                    let result = match &mut sink.inner {
+                        Some(inner) => inner.flush(),
+                        None => Err(anyhow::anyhow!("Writer not created")),
+                    };
+                    if let Err(e) = result {
+                        errors.push((sink.name.clone(), e));
+                    }
+                }
+                if errors.is_empty() { Ok(()) } else { Err(SinkErrors(errors).into()) }
+            }
         }
     }
 }
 
-// 公开的写入器结构体
+/// 写入队列满时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// 等待队列有空位
+    #[default]
+    Block,
+    /// 丢弃队列中最早的记录
+    DropOldest,
+    /// 返回错误, 丢弃当前记录
+    Error,
+}
+
+/// 写入队列的配置
+#[derive(Debug, Clone)]
+pub struct QueueConfig {
+    /// 队列容量（记录数）
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl QueueConfig {
+    /// 检查配置取值, 容量为 0 时 DropOldest 会一直空转
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.capacity > 0, "queue capacity must be positive");
+        Ok(())
+    }
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 4096,
+            overflow: OverflowPolicy::Block,
+        }
+    }
+}
+
+/// 写入器的运行指标
+#[derive(Debug, Clone, Default)]
+pub struct WriterMetrics {
+    /// 队列中等待写入的记录数
+    pub queue_depth: usize,
+    pub written: u64,
+    /// 队列满时丢弃的记录数
+    pub dropped: u64,
+    /// 后台写入失败的记录数, 多路写入时任一写入器失败即计入
+    pub write_errors: u64,
+    /// (写入器名称, 写入失败的记录数), 多路写入时每个写入器一项
+    pub sink_errors: Vec<(String, u64)>,
+    /// 从入队到写入完成的平均延迟（微秒）
+    pub avg_latency_us: u64,
+    pub max_latency_us: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    written: AtomicU64,
+    dropped: AtomicU64,
+    write_errors: AtomicU64,
+    sink_errors: Vec<(String, AtomicU64)>,
+    total_latency_us: AtomicU64,
+    max_latency_us: AtomicU64,
+}
+
+impl Counters {
+    fn new(sinks: Vec<String>) -> Self {
+        Self {
+            sink_errors: sinks.into_iter().map(|name| (name, AtomicU64::new(0))).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// 按 [`SinkErrors`] 计入失败的写入器, 单个写入器时计入唯一的一项
+    fn record_error(&self, e: &anyhow::Error) {
+        self.write_errors.fetch_add(1, Ordering::Relaxed);
+        let failed = match e.downcast_ref::<SinkErrors>() {
+            Some(errors) => errors.0.iter().map(|(name, _)| name.as_str()).collect(),
+            None => self.sink_errors.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(),
+        };
+        for (name, count) in &self.sink_errors {
+            if failed.contains(&name.as_str()) {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+type QueuedItem = (Box<dyn QueuedRecord>, Instant);
+
+/// 后台线程检查空闲文件、定时写出和重试创建写入器的间隔
+const TICK: Duration = Duration::from_secs(1);
+
+// 后台写入线程, 所有文件和共享内存 IO 都在该线程中完成
+struct Worker {
+    records: Sender<QueuedItem>,
+    // 丢弃最早的记录时从发送端取出
+    pending: Receiver<QueuedItem>,
+    // flush 请求, 关闭后写入线程写完队列中的记录后退出
+    flushes: Option<Sender<oneshot::Sender<Result<()>>>>,
+    thread: Option<JoinHandle<()>>,
+    overflow: OverflowPolicy,
+    counters: Arc<Counters>,
+}
+
+impl Worker {
+    fn spawn(mut inner: WriterInner, sinks: Vec<String>, queue: QueueConfig) -> Result<Self> {
+        let (records, pending) = bounded::<QueuedItem>(queue.capacity);
+        let (flushes, flush_requests) = unbounded::<oneshot::Sender<Result<()>>>();
+        let counters = Arc::new(Counters::new(sinks));
+
+        let receiver = pending.clone();
+        let ticker = tick(TICK);
+        let thread_counters = counters.clone();
+        let write = move |inner: &mut WriterInner, (data, queued_at): QueuedItem| {
+            match inner.write(data.as_ref()) {
+                Ok(()) => {
+                    thread_counters.written.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    error!("后台写入失败: {:#}", e);
+                    thread_counters.record_error(&e);
+                }
+            }
+            let latency = queued_at.elapsed().as_micros() as u64;
+            thread_counters.total_latency_us.fetch_add(latency, Ordering::Relaxed);
+            thread_counters.max_latency_us.fetch_max(latency, Ordering::Relaxed);
+        };
+
+        let thread = std::thread::Builder::new()
+            .name("cex-writer".to_string())
+            .spawn(move || loop {
+                select! {
+                    recv(receiver) -> item => {
+                        if let Ok(item) = item {
+                            write(&mut inner, item);
+                        }
+                    }
+                    recv(flush_requests) -> request => {
+                        // flush 之前入队的记录先全部写入
+                        while let Ok(item) = receiver.try_recv() {
+                            write(&mut inner, item);
+                        }
+                        match request {
+                            Ok(reply) => {
+                                let _ = reply.send(inner.flush());
+                            }
+                            // Writer 已释放, inner 在线程结束时关闭所有文件
+                            Err(_) => break,
+                        }
+                    }
+                    recv(ticker) -> _ => inner.tick(),
+                }
+            })
+            .context("Failed to spawn writer thread")?;
+
+        Ok(Self {
+            records,
+            pending,
+            flushes: Some(flushes),
+            thread: Some(thread),
+            overflow: queue.overflow,
+            counters,
+        })
+    }
+
+    async fn push(&self, mut item: QueuedItem) -> Result<()> {
+        loop {
+            item = match self.records.try_send(item) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(item)) => item,
+                Err(TrySendError::Disconnected(_)) => bail!("Writer thread stopped"),
+            };
+            match self.overflow {
+                OverflowPolicy::Block => {
+                    // 在阻塞线程池中等待空位, 不占用异步工作线程
+                    let records = self.records.clone();
+                    return tokio::task::spawn_blocking(move || records.send(item))
+                        .await
+                        .context("Writer queue send failed")?
+                        .map_err(|_| anyhow::anyhow!("Writer thread stopped"));
+                }
+                OverflowPolicy::DropOldest => {
+                    if self.pending.try_recv().is_ok() {
+                        self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                OverflowPolicy::Error => {
+                    self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                    bail!("Writer queue full ({} records)", self.records.len());
+                }
+            }
+        }
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let (reply, result) = oneshot::channel();
+        self.flushes
+            .as_ref()
+            .context("Writer thread stopped")?
+            .send(reply)
+            .map_err(|_| anyhow::anyhow!("Writer thread stopped"))?;
+        result.await.context("Writer thread stopped")?
+    }
+
+    fn metrics(&self) -> WriterMetrics {
+        let written = self.counters.written.load(Ordering::Relaxed);
+        let write_errors = self.counters.write_errors.load(Ordering::Relaxed);
+        let total_latency_us = self.counters.total_latency_us.load(Ordering::Relaxed);
+        WriterMetrics {
+            queue_depth: self.records.len(),
+            written,
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            write_errors,
+            sink_errors: self.counters.sink_errors.iter().map(|(name, count)| (name.clone(), count.load(Ordering::Relaxed))).collect(),
+            avg_latency_us: total_latency_us.checked_div(written + write_errors).unwrap_or(0),
+            max_latency_us: self.counters.max_latency_us.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Worker {
+    /// 通知写入线程退出, 在阻塞线程池中等待剩余记录写入和文件关闭
+    async fn join(mut self) -> Result<()> {
+        self.flushes.take();
+        let Some(thread) = self.thread.take() else { return Ok(()) };
+        tokio::task::spawn_blocking(move || thread.join())
+            .await
+            .context("Failed to join writer thread")?
+            .map_err(|_| anyhow::anyhow!("Writer thread panicked"))
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // 关闭 flush 通道通知写入线程退出, 不等待: 写入线程写完队列中的记录并关闭文件后自行结束
+        self.flushes.take();
+    }
+}
+
+/// 公开的写入器结构体
+///
+/// 写入在后台线程中完成, `write` 只把记录放入有界队列, 队列满时按 [`OverflowPolicy`] 处理;
+/// 最后一个 Writer 释放时写入线程在后台写完剩余记录, 进程退出前应调用 [`Writer::close`] 等待写完
 #[derive(Clone)]
-pub struct Writer(Arc<TokioMutex<WriterInner>>);
+pub struct Writer(Arc<Worker>);
 
 impl Writer {
-    fn new(inner: WriterInner) -> Self {
-        Self(Arc::new(TokioMutex::new(inner)))
+    /// 写入一条记录, 文件写入器按记录的事件时间选择轮转文件
+    ///
+    /// 后台写入的错误只记录日志并计入 [`WriterMetrics::write_errors`] 和 [`WriterMetrics::sink_errors`]
+    pub async fn write<T: Record + Clone + 'static>(&self, data: &T) -> Result<()> {
+        self.0.push((Box::new(data.clone()), Instant::now())).await
     }
 
-    pub async fn write<T: Serialize + Send + Sync>(&self, data: &T) -> Result<()> {
-        let mut inner = self.0.lock().await;
-        inner.write(data).await
+    /// 等待之前入队的记录写入后, 立即写出所有缓冲
+    pub async fn flush(&self) -> Result<()> {
+        self.0.flush().await
     }
 
-    pub async fn flush(&self) -> Result<()> {
-        let mut inner = self.0.lock().await;
-        inner.flush().await
+    pub fn metrics(&self) -> WriterMetrics {
+        self.0.metrics()
+    }
+
+    /// 写出所有缓冲, 是最后一个 Writer 时等待写入线程关闭所有文件后退出
+    pub async fn close(self) -> Result<()> {
+        let result = self.flush().await;
+        if let Ok(worker) = Arc::try_unwrap(self.0) {
+            worker.join().await?;
+        }
+        result
     }
 }
 
@@ -258,13 +881,78 @@ impl Writer {
 #[derive(Clone)]
 pub enum WriterType {
     File(FileWriterConfig),
+    /// 与 File 相同的轮转和分区, 使用二进制列式格式
+    BinaryFile(FileWriterConfig),
     Shmem(ShmemWriterConfig),
+    /// 同时写入多个写入器, 某个写入器出错不影响其它写入器, 错误以 [`SinkErrors`] 返回
+    ///
+    /// 创建失败的写入器（如共享内存名称已存在）在后台定时重试, 全部创建失败时才返回错误
+    Multi(Vec<WriterType>),
 }
 
-pub fn create_writer(writer_type: WriterType) -> Result<Writer> {
+impl WriterType {
+    /// 用于日志和错误信息的名称
+    pub fn name(&self) -> String {
+        match self {
+            WriterType::File(config) => format!("file:{}", config.base_path.display()),
+            WriterType::BinaryFile(config) => format!("binary:{}", config.base_path.display()),
+            WriterType::Shmem(config) => format!("shmem:{}", config.shmem_name),
+            WriterType::Multi(sinks) => format!("multi[{}]", sinks.iter().map(WriterType::name).collect::<Vec<_>>().join(",")),
+        }
+    }
+
+    /// 检查各写入器的配置, 配置错误不会随重试恢复, 在创建前返回
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            WriterType::File(config) | WriterType::BinaryFile(config) => {
+                config.validate().with_context(|| self.name())
+            }
+            WriterType::Shmem(_) => Ok(()),
+            WriterType::Multi(sinks) => sinks.iter().try_for_each(WriterType::validate),
+        }
+    }
+}
+
+fn create_inner(writer_type: WriterType) -> Result<WriterInner> {
     let inner = match writer_type {
         WriterType::File(config) => WriterInner::File(FileWriter::new(config)),
+        WriterType::BinaryFile(config) => WriterInner::File(FileWriter::with_format(config, FileFormat::Binary)),
         WriterType::Shmem(config) => WriterInner::Shmem(ShmemWriter::new(config)?),
+        WriterType::Multi(writer_types) => {
+            let mut errors = Vec::new();
+            let sinks = writer_types
+                .into_iter()
+                .map(|writer_type| {
+                    let name = writer_type.name();
+                    let inner = create_inner(writer_type.clone())
+                        .map_err(|e| {
+                            error!("创建写入器 {} 失败, 后台定时重试: {:#}", name, e);
+                            errors.push((name.clone(), e));
+                        })
+                        .ok();
+                    Sink { name, writer_type, inner }
+                })
+                .collect::<Vec<_>>();
+            if !sinks.is_empty() && errors.len() == sinks.len() {
+                return Err(SinkErrors(errors).into());
+            }
+            WriterInner::Multi(sinks)
+        }
+    };
+    Ok(inner)
+}
+
+/// 使用默认队列配置创建写入器
+pub fn create_writer(writer_type: WriterType) -> Result<Writer> {
+    create_writer_with_queue(writer_type, QueueConfig::default())
+}
+
+pub fn create_writer_with_queue(writer_type: WriterType, queue: QueueConfig) -> Result<Writer> {
+    writer_type.validate()?;
+    queue.validate()?;
+    let sinks = match &writer_type {
+        WriterType::Multi(sinks) => sinks.iter().map(WriterType::name).collect(),
+        writer_type => vec![writer_type.name()],
     };
-    Ok(Writer::new(inner))
+    Ok(Writer(Arc::new(Worker::spawn(create_inner(writer_type)?, sinks, queue)?)))
 } 
\ No newline at end of file
@@ -0,0 +1,167 @@
+use cex_core::aggregate::KlineAggregator;
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+
+// 2025-06-02 00:00 UTC, 周一
+const T0: u64 = 1748822400000;
+const MINUTE: u64 = 60_000;
+const HOUR: u64 = 60 * MINUTE;
+
+fn minute(i: u64) -> SimpleKLine {
+    let open_time = T0 + i * MINUTE;
+    let mut kline = SimpleKLine::new(
+        "binance",
+        "BTCUSDT",
+        open_time,
+        open_time + MINUTE - 1,
+        KlineInterval::OneMinute,
+        Decimal::from(100 + i),
+        Decimal::from(101 + i),
+        Decimal::from(99 + i),
+        Decimal::from(100 + i) + Decimal::new(5, 1),
+        Decimal::from(2),
+        10,
+    );
+    kline.quote_volume = Some(Decimal::from(200 + i));
+    kline.first_trade_id = Some(i as i64 * 10);
+    kline.last_trade_id = Some(i as i64 * 10 + 9);
+    kline
+}
+
+#[test]
+fn aggregates_aligned_bars() {
+    let mut aggregator = KlineAggregator::new(&[KlineInterval::FourHours, KlineInterval::OneHour]);
+    let mut output = Vec::new();
+    for i in 0..4 * 60 {
+        output.extend(aggregator.push(&minute(i)));
+    }
+
+    // 每个周期最后一根K线到达时输出, 同时完成时按周期从短到长
+    let intervals = output.iter().map(|k| k.interval).collect::<Vec<_>>();
+    use KlineInterval::*;
+    assert_eq!(intervals, [OneHour, OneHour, OneHour, OneHour, FourHours]);
+
+    let hour = &output[1];
+    assert_eq!(hour.open_time_ms, T0 + HOUR);
+    assert_eq!(hour.close_time_ms, T0 + 2 * HOUR - 1);
+    assert_eq!(hour.open, Decimal::from(160));
+    assert_eq!(hour.high, Decimal::from(220));
+    assert_eq!(hour.low, Decimal::from(159));
+    assert_eq!(hour.close, Decimal::new(2195, 1));
+    assert_eq!(hour.volume, Decimal::from(120));
+    assert_eq!(hour.trades_count, 600);
+    assert_eq!(hour.quote_volume, Some((60..120).map(|i| Decimal::from(200 + i)).sum()));
+    assert_eq!(hour.first_trade_id, Some(600));
+    assert_eq!(hour.last_trade_id, Some(1199));
+
+    let four_hours = &output[4];
+    assert_eq!((four_hours.open_time_ms, four_hours.close_time_ms), (T0, T0 + 4 * HOUR - 1));
+    assert_eq!(four_hours.volume, Decimal::from(480));
+}
+
+#[test]
+fn missing_bars_keep_alignment() {
+    let mut aggregator = KlineAggregator::new(&[KlineInterval::OneHour]);
+    // 第一个小时缺少开头和最后一根, 第二个小时完全没有数据
+    let mut output = Vec::new();
+    for i in (5..59).chain(130..180) {
+        output.extend(aggregator.push(&minute(i)));
+    }
+    // 重复和更早的K线被忽略
+    output.extend(aggregator.push(&minute(170)));
+    output.extend(aggregator.push(&minute(10)));
+
+    assert_eq!(output.len(), 2);
+    assert_eq!(output[0].open_time_ms, T0);
+    assert_eq!(output[0].close_time_ms, T0 + HOUR - 1);
+    assert_eq!(output[0].open, Decimal::from(105));
+    assert_eq!(output[0].volume, Decimal::from(108));
+    assert_eq!(output[1].open_time_ms, T0 + 2 * HOUR);
+    assert_eq!(output[1].open, Decimal::from(230));
+    assert_eq!(output[1].trades_count, 500);
+}
+
+#[test]
+fn day_alignment() {
+    let utc = KlineAggregator::new(&[KlineInterval::OneDay]);
+    let utc8 = KlineAggregator::new(&[KlineInterval::OneDay]).with_utc_offset(8);
+    let time = T0 + 20 * HOUR;
+    assert_eq!(utc.period(KlineInterval::OneDay, time), Some((T0, T0 + 24 * HOUR - 1)));
+    // UTC+8 的 06-03 00:00 为 UTC 06-02 16:00
+    assert_eq!(utc8.period(KlineInterval::OneDay, time), Some((T0 + 16 * HOUR, T0 + 40 * HOUR - 1)));
+    // 周线从周一开始, 月线按对齐时区的自然月
+    assert_eq!(utc.period(KlineInterval::OneWeek, time), Some((T0, T0 + 7 * 24 * HOUR - 1)));
+    let june = T0 - 24 * HOUR;
+    assert_eq!(utc.period(KlineInterval::OneMonth, time), Some((june, june + 30 * 24 * HOUR - 1)));
+    assert_eq!(utc8.period(KlineInterval::OneMonth, time), Some((june - 8 * HOUR, june + 30 * 24 * HOUR - 8 * HOUR - 1)));
+
+    // 4h 的边界与 UTC+8 日线对齐, 6h 不对齐
+    assert!(utc8.can_aggregate(KlineInterval::FourHours, KlineInterval::OneDay));
+    assert!(!utc8.can_aggregate(KlineInterval::SixHours, KlineInterval::OneDay));
+    assert!(!utc.can_aggregate(KlineInterval::OneHour, KlineInterval::OneHour));
+
+    // 回放: 两天的 1h K线合成为 UTC+8 日线, 开头不完整的日线在下一天数据到达时输出, 最后未收盘的不输出
+    let hours = (0..48).map(|i| {
+        let mut kline = minute(0);
+        kline.interval = KlineInterval::OneHour;
+        kline.open_time_ms = T0 + i * HOUR;
+        kline.close_time_ms = T0 + (i + 1) * HOUR - 1;
+        kline
+    });
+    let days = utc8.resample(hours).collect::<Vec<_>>();
+    assert_eq!(days.iter().map(|k| k.open_time_ms).collect::<Vec<_>>(), [T0 - 8 * HOUR, T0 + 16 * HOUR]);
+    assert_eq!(days[0].trades_count, 16 * 10);
+    assert_eq!(days[1].trades_count, 24 * 10);
+}
+
+/// 由 minute(first..first + 15) 组成的 15m K线
+fn quarter(first: u64) -> SimpleKLine {
+    let minutes = (first..first + 15).map(minute).collect::<Vec<_>>();
+    SimpleKLine::new(
+        "binance",
+        "BTCUSDT",
+        minutes[0].open_time_ms,
+        minutes[14].close_time_ms,
+        KlineInterval::FifteenMinutes,
+        minutes[0].open,
+        minutes.iter().map(|k| k.high).max().unwrap(),
+        minutes.iter().map(|k| k.low).min().unwrap(),
+        minutes[14].close,
+        minutes.iter().map(|k| k.volume).sum(),
+        minutes.iter().map(|k| k.trades_count).sum(),
+    )
+}
+
+fn ohlcv(kline: &SimpleKLine) -> (u64, u64, Decimal, Decimal, Decimal, Decimal, Decimal, u64) {
+    (kline.open_time_ms, kline.close_time_ms, kline.open, kline.high, kline.low, kline.close, kline.volume, kline.trades_count)
+}
+
+#[test]
+fn multiple_bases_emit_each_bar_once() {
+    let expected = KlineAggregator::new(&[KlineInterval::OneHour]).resample((0..120).map(minute)).collect::<Vec<_>>();
+    assert_eq!(expected.len(), 2);
+
+    // 同一交易对同时订阅 1m 和 15m
+    let mut aggregator = KlineAggregator::new(&[KlineInterval::OneHour]);
+    let mut output = Vec::new();
+    // 第一个小时 15m K线在对应的 1m K线之后到达
+    for i in 0..60 {
+        output.extend(aggregator.push(&minute(i)));
+        if i % 15 == 14 {
+            output.extend(aggregator.push(&quarter(i - 14)));
+        }
+    }
+    // 第二个小时 15m K线先到, 之后的 1m K线已被覆盖; 最后 15 分钟的 15m K线在 1m K线之后到达
+    for first in (60..120).step_by(15) {
+        if first < 105 {
+            output.extend(aggregator.push(&quarter(first)));
+        }
+        for i in first..first + 15 {
+            output.extend(aggregator.push(&minute(i)));
+        }
+        if first == 105 {
+            output.extend(aggregator.push(&quarter(first)));
+        }
+    }
+
+    assert_eq!(output.iter().map(ohlcv).collect::<Vec<_>>(), expected.iter().map(ohlcv).collect::<Vec<_>>());
+}
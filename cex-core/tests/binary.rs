@@ -0,0 +1,85 @@
+use std::fs;
+
+use cex_core::binary::{decode_block, encode_block, read_header, write_header, HEADER_LEN, MAGIC, VERSION};
+use cex_core::reader::{FileReader, KlineFilter};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+
+// 2025-06-01 08:00 (UTC+8)
+const T0: u64 = 1748736000000;
+const MINUTE: u64 = 60_000;
+
+fn kline(symbol: &str, interval: KlineInterval, open_time: u64, close: Decimal) -> SimpleKLine {
+    let open = Decimal::new(10434906, 2);
+    SimpleKLine::new("binance", symbol, open_time, open_time + interval.duration_ms() - 1, interval, open, close.max(open), close.min(open), close, Decimal::new(1032405, 5), 588)
+}
+
+/// 两个交易对, 第二根K线带上全部可选字段
+fn klines() -> Vec<SimpleKLine> {
+    let mut full = kline("ETHUSDT", KlineInterval::FiveMinutes, T0 + 5 * MINUTE, Decimal::new(250175, 2));
+    full.quote_volume = Some(Decimal::new(1234567, 3));
+    full.taker_buy_base_volume = Some(Decimal::new(51, 1));
+    full.taker_buy_quote_volume = Some(Decimal::ZERO);
+    full.first_trade_id = Some(-1);
+    full.last_trade_id = Some(42);
+    full.event_time_ms = Some(T0 + 10 * MINUTE);
+    vec![
+        kline("BTCUSDT", KlineInterval::OneMinute, T0, Decimal::new(10438096, 2)),
+        full,
+        kline("BTCUSDT", KlineInterval::OneMinute, T0 + MINUTE, Decimal::new(1, 1)),
+    ]
+}
+
+fn json(klines: &[SimpleKLine]) -> serde_json::Value {
+    serde_json::to_value(klines).unwrap()
+}
+
+fn header(version: u16) -> Vec<u8> {
+    let mut header = MAGIC.to_vec();
+    header.extend_from_slice(&version.to_le_bytes());
+    header.extend_from_slice(&[0, 0]);
+    header
+}
+
+#[test]
+fn round_trips_block() {
+    let klines = klines();
+    let decoded = decode_block(&encode_block(&klines).unwrap()).unwrap();
+    assert_eq!(json(&decoded), json(&klines));
+    assert!(decode_block(&encode_block(&[]).unwrap()).unwrap().is_empty());
+}
+
+#[test]
+fn rejects_truncated_block_and_bad_header() {
+    let block = encode_block(&klines()).unwrap();
+    assert!(decode_block(&block[..block.len() - 1]).is_err());
+
+    let mut buf = Vec::new();
+    write_header(&mut buf).unwrap();
+    assert_eq!(buf.len(), HEADER_LEN);
+    assert_eq!(buf, header(VERSION));
+    read_header(&mut buf.as_slice()).unwrap();
+    assert!(read_header(&mut header(0).as_slice()).is_err());
+    assert!(read_header(&mut header(VERSION + 1).as_slice()).is_err());
+    assert!(read_header(&mut b"CEXJ\x01\x00\x00\x00".as_slice()).is_err());
+    assert!(read_header(&mut &buf[..HEADER_LEN - 1]).is_err());
+}
+
+#[test]
+fn reads_multi_frame_file() {
+    let dir = std::env::temp_dir().join(format!("cex-core-binary-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let klines = klines();
+    // 两个帧
+    let mut data = header(VERSION);
+    for _ in 0..2 {
+        data.extend_from_slice(&zstd::bulk::compress(&encode_block(&klines).unwrap(), 3).unwrap());
+    }
+    fs::write(dir.join("kline_20250601-0800.bin"), data).unwrap();
+
+    let read = FileReader::new(&dir).read(&KlineFilter::default()).unwrap().collect::<Vec<_>>();
+    let expected = klines.iter().chain(klines.iter()).cloned().collect::<Vec<_>>();
+    assert_eq!(json(&read), json(&expected));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::io::BufRead;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use cex_core::writer::{create_writer, recover_file, FileWriterConfig, WriterType};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+
+const CHILD_DIR_ENV: &str = "CRASH_RECOVERY_CHILD_DIR";
+// 2025-06-01 08:00 (UTC+8), 所有K线落在同一个8小时周期内
+const T0: u64 = 1748736000000;
+// 重启后写入的K线偏移, 仍在同一周期内且不会与子进程写入的重叠
+const RESTART: u64 = 20_000_000;
+// 子进程可写入的文件大小（ulimit -f 的块数）
+const FILE_BLOCKS: u64 = 256;
+const SIGXFSZ: i32 = 25;
+
+fn kline(i: u64) -> SimpleKLine {
+    SimpleKLine {
+        exchange: "binance".to_string(),
+        symbol: "BTCUSDT".to_string(),
+        open_time_ms: T0 + i,
+        close_time_ms: T0 + i + 59_999,
+        open_time_h: String::new(),
+        interval: KlineInterval::OneMinute,
+        open: Decimal::new(10434906, 2),
+        high: Decimal::new(10438096, 2),
+        low: Decimal::new(10434906, 2),
+        close: Decimal::new(10438096, 2),
+        volume: Decimal::new(1032405, 5),
+        trades_count: 588,
+        ..Default::default()
+    }
+}
+
+fn config(dir: &Path) -> FileWriterConfig {
+    FileWriterConfig {
+        base_path: dir.to_path_buf(),
+        flush_bytes: 2048,
+        ..Default::default()
+    }
+}
+
+fn archive(dir: &Path) -> PathBuf {
+    dir.join("kline_20250601-0800.zst")
+}
+
+/// 子进程: 一直写入直到被杀掉
+#[tokio::test]
+async fn crash_writer_child() {
+    let Ok(dir) = std::env::var(CHILD_DIR_ENV) else { return };
+    let writer = create_writer(WriterType::File(config(Path::new(&dir)))).unwrap();
+    for i in 0.. {
+        writer.write(&kline(i)).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn recovers_after_partial_frame_write() {
+    let dir = std::env::temp_dir().join(format!("cex-core-crash-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = archive(&dir);
+
+    // 限制子进程的文件大小: 写帧时只写入一部分, 随后被 SIGXFSZ 杀掉, 在文件末尾留下真实的半个帧
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("ulimit -f {} && exec \"$0\" \"$@\"", FILE_BLOCKS))
+        .arg(std::env::current_exe().unwrap())
+        .args(["crash_writer_child", "--exact", "--nocapture"])
+        .env(CHILD_DIR_ENV, &dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.signal(), Some(SIGXFSZ), "{:?}", status);
+
+    let len = fs::metadata(&path).unwrap().len();
+    assert!(len > 0 && len.is_multiple_of(512), "{}", len);
+    assert!(zstd::stream::decode_all(fs::File::open(&path).unwrap()).is_err());
+
+    // 重新打开写入器时截断不完整的帧并继续追加
+    let writer = create_writer(WriterType::File(config(&dir))).unwrap();
+    for i in 0..10 {
+        writer.write(&kline(RESTART + i)).await.unwrap();
+    }
+    writer.close().await.unwrap();
+
+    let data = zstd::stream::decode_all(fs::File::open(&path).unwrap()).unwrap();
+    let klines = data
+        .lines()
+        .map(|line| serde_json::from_str::<SimpleKLine>(&line.unwrap()).unwrap())
+        .collect::<Vec<_>>();
+
+    // 子进程写出的是从 0 开始的连续前缀, 之后是重启后写入的记录
+    let crashed = klines.len() - 10;
+    assert!(crashed > 0);
+    for (i, kline) in klines.iter().enumerate() {
+        let expected = if i < crashed { i as u64 } else { RESTART + (i - crashed) as u64 };
+        assert_eq!(kline.open_time_ms, T0 + expected);
+    }
+
+    // 恢复后的文件没有需要截断的部分
+    assert_eq!(recover_file(&path).unwrap(), 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,47 @@
+use cex_core::{KlineInterval, SimpleKLine};
+
+#[test]
+fn parse_and_format_round_trip() {
+    for interval in KlineInterval::ALL {
+        assert_eq!(interval.as_str().parse::<KlineInterval>().unwrap(), *interval);
+        assert_eq!(interval.to_string(), interval.as_str());
+    }
+    // 区分大小写: 1m 为分钟, 1M 为月
+    assert_eq!("1m".parse::<KlineInterval>().unwrap(), KlineInterval::OneMinute);
+    assert_eq!("1M".parse::<KlineInterval>().unwrap(), KlineInterval::OneMonth);
+    for unknown in ["60m", "240m", "1H", "2d", ""] {
+        assert!(unknown.parse::<KlineInterval>().is_err(), "{}", unknown);
+    }
+}
+
+#[test]
+fn serde_uses_interval_name() {
+    let kline = SimpleKLine { interval: KlineInterval::FourHours, ..Default::default() };
+    let json = serde_json::to_value(&kline).unwrap();
+    assert_eq!(json["interval"], "4h");
+    let back: SimpleKLine = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(back.interval, KlineInterval::FourHours);
+
+    let mut bad = json;
+    bad["interval"] = "60m".into();
+    assert!(serde_json::from_value::<SimpleKLine>(bad).is_err());
+}
+
+#[test]
+fn close_time() {
+    // 2025-06-02 00:00 UTC
+    let open = 1748822400000;
+    assert_eq!(KlineInterval::OneMinute.duration_ms(), 60_000);
+    assert_eq!(KlineInterval::OneHour.close_time_ms(open), Some(open + 3_600_000 - 1));
+    assert_eq!(KlineInterval::OneWeek.close_time_ms(open), Some(open + 7 * 86_400_000 - 1));
+    // 1M 按自然月: 2025-06-01 到 2025-07-01 共 30 天, 2025-07-01 到 2025-08-01 共 31 天
+    let june = 1748736000000;
+    let july = june + 30 * 86_400_000;
+    assert_eq!(KlineInterval::OneMonth.close_time_ms(june), Some(july - 1));
+    assert_eq!(KlineInterval::OneMonth.close_time_ms(july), Some(july + 31 * 86_400_000 - 1));
+
+    // 无效的开盘时间返回 None, 不会溢出
+    assert_eq!(KlineInterval::OneMinute.close_time_ms(u64::MAX - 1), None);
+    assert_eq!(KlineInterval::OneMonth.close_time_ms(u64::MAX), None);
+    assert_eq!(KlineInterval::OneMonth.close_time_ms(i64::MAX as u64), None);
+}
@@ -0,0 +1,164 @@
+use std::fs;
+
+use cex_core::export::{export_parquet, PARQUET_FILE_NAME};
+use cex_core::reader::{FileReader, KlineFilter};
+use cex_core::writer::{create_writer, FileWriterConfig, WriterType};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+use parquet::file::reader::{FileReader as _, SerializedFileReader};
+use parquet::record::RowAccessor;
+
+// 2025-06-01 22:00 (UTC+8), 跨越 06-01 和 06-02 两天
+const T0: u64 = 1748786400000;
+
+fn kline(symbol: &str, interval: KlineInterval, i: u64) -> SimpleKLine {
+    let step = interval.duration_ms();
+    SimpleKLine {
+        exchange: "binance".to_string(),
+        symbol: symbol.to_string(),
+        open_time_ms: T0 + i * step,
+        close_time_ms: T0 + (i + 1) * step - 1,
+        open_time_h: String::new(),
+        interval,
+        open: Decimal::new(10000 + i as i64 * 100, 2),
+        high: Decimal::new(10150 + i as i64 * 100, 2),
+        low: Decimal::new(9925 + i as i64 * 100, 2),
+        close: Decimal::new(10075 + i as i64 * 100, 2),
+        volume: Decimal::new(i as i64, 1),
+        // 只有部分K线带可选字段
+        quote_volume: i.is_multiple_of(2).then(|| Decimal::new(i as i64 * 1005, 2)),
+        taker_buy_base_volume: i.is_multiple_of(2).then(|| Decimal::new(i as i64, 2)),
+        taker_buy_quote_volume: i.is_multiple_of(2).then(|| Decimal::new(i as i64 * 503, 2)),
+        trades_count: i * 3,
+        first_trade_id: i.is_multiple_of(3).then_some(i as i64 * 100),
+        last_trade_id: i.is_multiple_of(3).then_some(i as i64 * 100 + 99),
+        event_time_ms: i.is_multiple_of(3).then_some(T0 + (i + 1) * step),
+    }
+}
+
+#[tokio::test]
+async fn parquet_rows_match_archive() {
+    let dir = std::env::temp_dir().join(format!("cex-core-parquet-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let (archive, output) = (dir.join("archive"), dir.join("parquet"));
+
+    let mut source = Vec::new();
+    for i in 0..180 {
+        source.push(kline("BTCUSDT", KlineInterval::OneMinute, i));
+        source.push(kline("ETHUSDT", KlineInterval::OneMinute, i));
+    }
+    for i in 0..5 {
+        source.push(kline("BTCUSDT", KlineInterval::OneHour, i));
+    }
+    let writer = create_writer(WriterType::File(FileWriterConfig {
+        base_path: archive.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    for kline in &source {
+        writer.write(kline).await.unwrap();
+    }
+    // 重复写入的K线只导出一次
+    writer.write(&source[0]).await.unwrap();
+    writer.close().await.unwrap();
+
+    // 先导出 1m, 再导出 1h 到同一目录, 不同周期写入不同的分区, 不会互相覆盖
+    let export = |intervals| {
+        let filter = KlineFilter { intervals, ..Default::default() };
+        export_parquet(&FileReader::new(&archive), &filter, &output).unwrap()
+    };
+    let mut files = export(vec![KlineInterval::OneMinute]);
+    assert_eq!(files.len(), 4);
+    files.extend(export(vec![KlineInterval::OneHour]));
+    files.sort();
+    let mut expected_files = [("BTCUSDT", "1m"), ("ETHUSDT", "1m"), ("BTCUSDT", "1h")]
+        .iter()
+        .flat_map(|(symbol, interval)| {
+            ["2025-06-01", "2025-06-02"].map(|date| format!("exchange=binance/symbol={}/interval={}/date={}", symbol, interval, date))
+        })
+        .map(|dir| output.join(dir).join(PARQUET_FILE_NAME))
+        .collect::<Vec<_>>();
+    expected_files.sort();
+    assert_eq!(files, expected_files);
+
+    let mut exported = Vec::new();
+    for path in &files {
+        // 分区目录中的取值
+        let value = |level: usize, key: &str| {
+            let dir = path.ancestors().nth(level).unwrap().file_name().unwrap().to_str().unwrap();
+            dir.strip_prefix(key).unwrap().to_string()
+        };
+        let reader = SerializedFileReader::new(fs::File::open(path).unwrap()).unwrap();
+        let schema = reader.metadata().file_metadata().schema_descr();
+        assert_eq!(schema.num_columns(), 14);
+        assert_eq!(schema.column(0).logical_type(), Some(parquet::basic::LogicalType::Timestamp {
+            is_adjusted_to_u_t_c: true,
+            unit: parquet::basic::TimeUnit::MILLIS(Default::default()),
+        }));
+        for row in reader.get_row_iter(None).unwrap() {
+            let row = row.unwrap();
+            exported.push((
+                value(3, "symbol="),
+                value(2, "interval="),
+                row.get_timestamp_millis(0).unwrap() as u64,
+                value(4, "exchange="),
+                row.get_timestamp_millis(1).unwrap() as u64,
+                [2, 3, 4, 5, 6].map(|i| row.get_double(i).unwrap()),
+                row.get_long(7).unwrap() as u64,
+                [8, 9, 10].map(|i| row.get_double(i).ok()),
+                [11, 12].map(|i| row.get_long(i).ok()),
+                row.get_timestamp_millis(13).ok().map(|t| t as u64),
+            ));
+        }
+    }
+    exported.sort_by(|a, b| (&a.0, &a.1, a.2).cmp(&(&b.0, &b.1, b.2)));
+
+    // 归档中的价格精确还原, 重复写入的记录去重后与源数据一致
+    let key = |k: &SimpleKLine| (k.symbol.clone(), k.interval.as_str(), k.open_time_ms);
+    let mut archived = FileReader::new(&archive).read(&KlineFilter::default()).unwrap().collect::<Vec<_>>();
+    archived.sort_by_key(key);
+    archived.dedup_by_key(|k| key(k));
+    source.sort_by_key(key);
+    assert_eq!(archived.len(), source.len());
+    for (archived, source) in archived.iter().zip(&source) {
+        assert_eq!(
+            serde_json::to_value(archived).unwrap(),
+            serde_json::to_value(source).unwrap(),
+        );
+    }
+
+    // parquet 中的 DOUBLE 与归档的 f64 值一致
+    assert_eq!(exported.len(), source.len());
+    for (exported, archived) in exported.iter().zip(&archived) {
+        let expected = (
+            archived.symbol.clone(),
+            archived.interval.to_string(),
+            archived.open_time_ms,
+            archived.exchange.clone(),
+            archived.close_time_ms,
+            [archived.open_f64(), archived.high_f64(), archived.low_f64(), archived.close_f64(), archived.volume_f64()],
+            archived.trades_count,
+            [archived.quote_volume, archived.taker_buy_base_volume, archived.taker_buy_quote_volume]
+                .map(|v| v.map(|v| v.to_string().parse::<f64>().unwrap())),
+            [archived.first_trade_id, archived.last_trade_id],
+            archived.event_time_ms,
+        );
+        assert_eq!(*exported, expected);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn escapes_partition_values() {
+    let dir = std::env::temp_dir().join(format!("cex-core-parquet-escape-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let (archive, output) = (dir.join("archive"), dir.join("parquet"));
+
+    let writer = create_writer(WriterType::File(FileWriterConfig { base_path: archive.clone(), ..Default::default() })).unwrap();
+    writer.write(&kline("../BTC/USDT", KlineInterval::OneMinute, 0)).await.unwrap();
+    writer.close().await.unwrap();
+
+    // 交易对中的 / 不会生成额外的目录或写到输出目录之外
+    let files = export_parquet(&FileReader::new(&archive), &KlineFilter::default(), &output).unwrap();
+    assert_eq!(files, [output.join("exchange=binance/symbol=..%2FBTC%2FUSDT/interval=1m/date=2025-06-01").join(PARQUET_FILE_NAME)]);
+    fs::remove_dir_all(&dir).unwrap();
+}
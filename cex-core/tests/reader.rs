@@ -0,0 +1,215 @@
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use cex_core::reader::{FileReader, KlineFilter};
+use cex_core::writer::{create_writer, FileWriterConfig, WriterType};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+
+// 2025-06-01 08:00 (UTC+8), 8小时轮转周期的开始
+const T0: u64 = 1748736000000;
+const MINUTE: u64 = 60_000;
+const PERIOD: u64 = 8 * 3600 * 1000;
+
+fn kline(exchange: &str, symbol: &str, interval: KlineInterval, open_time: u64) -> SimpleKLine {
+    let price = Decimal::new(10434906, 2);
+    SimpleKLine::new(exchange, symbol, open_time, open_time + interval.duration_ms() - 1, interval, price, price, price, price, Decimal::ONE, 1)
+}
+
+fn btc(i: u64) -> SimpleKLine {
+    kline("binance", "BTCUSDT", KlineInterval::OneMinute, T0 + i * MINUTE)
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cex-core-reader-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn open_times(klines: impl Iterator<Item = SimpleKLine>) -> Vec<u64> {
+    klines.map(|k| k.open_time_ms).collect()
+}
+
+fn file_names(reader: &FileReader, filter: &KlineFilter) -> Vec<String> {
+    reader
+        .files(filter)
+        .unwrap()
+        .iter()
+        .map(|p| p.strip_prefix(std::env::temp_dir()).unwrap().iter().skip(1).map(|p| p.to_str().unwrap()).collect::<Vec<_>>().join("/"))
+        .collect()
+}
+
+/// 每次 flush 写出一帧, 返回写入的文件
+async fn write_frames(writer_type: fn(FileWriterConfig) -> WriterType, dir: &Path, frames: &[&[u64]]) -> PathBuf {
+    let writer = create_writer(writer_type(FileWriterConfig { base_path: dir.to_path_buf(), ..Default::default() })).unwrap();
+    for frame in frames {
+        for i in *frame {
+            writer.write(&btc(*i)).await.unwrap();
+        }
+        writer.flush().await.unwrap();
+    }
+    let files = FileReader::new(dir).files(&KlineFilter::default()).unwrap();
+    assert_eq!(files.len(), 1);
+    files[0].clone()
+}
+
+#[tokio::test]
+async fn reads_every_frame() {
+    for (name, writer_type) in [("json", WriterType::File as fn(_) -> _), ("binary", WriterType::BinaryFile)] {
+        let dir = temp_dir(&format!("frames-{}", name));
+        write_frames(writer_type, &dir, &[&[0, 1], &[2], &[3, 4, 5]]).await;
+
+        let reader = FileReader::new(&dir);
+        let klines = reader.read(&KlineFilter::default()).unwrap();
+        assert_eq!(open_times(klines), (0..6).map(|i| T0 + i * MINUTE).collect::<Vec<_>>(), "{}", name);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn skips_truncated_last_frame() {
+    for (name, writer_type) in [("json", WriterType::File as fn(_) -> _), ("binary", WriterType::BinaryFile)] {
+        let dir = temp_dir(&format!("truncated-{}", name));
+        let first = write_frames(writer_type, &dir, &[&[0, 1], &[2, 3]]).await;
+        // 截掉最后一帧的末尾, 模拟写入过程中崩溃
+        let len = fs::metadata(&first).unwrap().len();
+        OpenOptions::new().write(true).open(&first).unwrap().set_len(len - 5).unwrap();
+
+        // 后面的文件照常读取
+        let writer = create_writer(writer_type(FileWriterConfig { base_path: dir.clone(), ..Default::default() })).unwrap();
+        let next = kline("binance", "BTCUSDT", KlineInterval::OneMinute, T0 + PERIOD);
+        writer.write(&next).await.unwrap();
+        writer.flush().await.unwrap();
+
+        let reader = FileReader::new(&dir);
+        let klines = reader.read(&KlineFilter::default()).unwrap();
+        assert_eq!(open_times(klines), [T0, T0 + MINUTE, next.open_time_ms], "{}", name);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn selects_files_by_time_range() {
+    let dir = temp_dir("range");
+    for name in ["kline_20250601-0800.zst", "kline_20250601-1600.zst", "kline_20250602-0000.zst", "other.zst", "kline_bad.zst"] {
+        fs::write(dir.join(name), b"").unwrap();
+    }
+    let reader = FileReader::new(&dir);
+    let range = |start_ms: Option<u64>, end_ms: Option<u64>| file_names(&reader, &KlineFilter { start_ms, end_ms, ..Default::default() });
+    let all = ["kline_20250601-0800.zst", "kline_20250601-1600.zst", "kline_20250602-0000.zst"];
+
+    assert_eq!(range(None, None), all);
+    // 文件覆盖到下一个文件开始为止
+    assert_eq!(range(Some(T0 + PERIOD - 1), None), all);
+    assert_eq!(range(Some(T0 + PERIOD), None), all[1..]);
+    // 最后一个文件不限结束时间
+    assert_eq!(range(Some(T0 + 10 * PERIOD), None), all[2..]);
+    // 结束时间之后的第一个文件可能有收盘后写入的K线, 同样读取
+    assert_eq!(range(None, Some(T0 + PERIOD)), all[..2]);
+    assert_eq!(range(None, Some(T0 + PERIOD + 1)), all);
+    assert_eq!(range(Some(T0 + PERIOD), Some(T0 + 2 * PERIOD)), all[1..]);
+    assert_eq!(range(None, Some(T0)), all[..1]);
+    assert_eq!(range(Some(T0 + 10 * PERIOD), Some(T0 + 11 * PERIOD)), all[2..]);
+    // 按最长的K线周期放宽结束时间
+    let filter = KlineFilter { end_ms: Some(T0), intervals: vec![KlineInterval::OneMinute, KlineInterval::OneDay], ..Default::default() };
+    assert_eq!(file_names(&reader, &filter), all);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 按写入时间轮转的旧归档: K线收盘后写入, 周期结束时收盘的K线落在下一个文件中
+fn write_legacy(path: &Path, klines: &[SimpleKLine]) {
+    let lines = klines.iter().map(|k| serde_json::to_string(k).unwrap() + "\n").collect::<String>();
+    fs::write(path, zstd::stream::encode_all(lines.as_bytes(), 0).unwrap()).unwrap();
+}
+
+#[test]
+fn reads_klines_written_after_boundary() {
+    let dir = temp_dir("boundary");
+    let bar_4h = |open_time| kline("binance", "BTCUSDT", KlineInterval::FourHours, open_time);
+    let last_1m = btc(PERIOD / MINUTE - 1);
+    write_legacy(&dir.join("kline_20250601-0800.zst"), &[btc(0), bar_4h(T0)]);
+    write_legacy(&dir.join("kline_20250601-1600.zst"), &[bar_4h(T0 + PERIOD / 2), last_1m.clone(), btc(PERIOD / MINUTE)]);
+    write_legacy(&dir.join("kline_20250602-0000.zst"), &[bar_4h(T0 + PERIOD), bar_4h(T0 + PERIOD * 3 / 2)]);
+    // 1d K线在两个周期之后才写入
+    write_legacy(&dir.join("kline_20250602-0800.zst"), &[kline("binance", "BTCUSDT", KlineInterval::OneDay, T0)]);
+
+    let reader = FileReader::new(&dir);
+    let filter = KlineFilter { start_ms: Some(T0), end_ms: Some(T0 + PERIOD), ..Default::default() };
+    assert_eq!(open_times(reader.read(&filter).unwrap()), [T0, T0, T0 + PERIOD / 2, last_1m.open_time_ms]);
+
+    let filter = KlineFilter { intervals: vec![KlineInterval::FourHours], ..filter };
+    assert_eq!(open_times(reader.read(&filter).unwrap()), [T0, T0 + PERIOD / 2]);
+
+    let filter = KlineFilter { intervals: vec![KlineInterval::OneDay], ..filter };
+    assert_eq!(open_times(reader.read(&filter).unwrap()), [T0]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn selects_partitions_and_filters_klines() {
+    let dir = temp_dir("partition");
+    let partition = "{exchange}/{symbol}/{interval}".to_string();
+    let writer = create_writer(WriterType::File(FileWriterConfig {
+        base_path: dir.clone(),
+        partition: Some(partition.clone()),
+        ..Default::default()
+    }))
+    .unwrap();
+    let klines = [
+        kline("binance", "BTCUSDT", KlineInterval::OneMinute, T0),
+        kline("binance", "BTCUSDT", KlineInterval::OneMinute, T0 + MINUTE),
+        kline("binance", "ETHUSDT", KlineInterval::OneMinute, T0),
+        kline("binance", "BTCUSDT", KlineInterval::FiveMinutes, T0),
+        kline("okx", "BTCUSDT", KlineInterval::OneMinute, T0),
+    ];
+    for k in &klines {
+        writer.write(k).await.unwrap();
+    }
+    writer.flush().await.unwrap();
+    // 分区外的文件不属于任何分区, 有分区模板时不读取
+    fs::write(dir.join("kline_20250601-0800.zst"), b"").unwrap();
+
+    let reader = FileReader::new(&dir).with_partition(Some(partition));
+    let filter = KlineFilter {
+        exchange: Some("binance".to_string()),
+        symbols: vec!["btcusdt".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(
+        file_names(&reader, &filter),
+        ["binance/BTCUSDT/1m/kline_20250601-0800.zst", "binance/BTCUSDT/5m/kline_20250601-0800.zst"]
+    );
+    assert_eq!(open_times(reader.read(&filter).unwrap()), [T0, T0 + MINUTE, T0]);
+
+    let filter = KlineFilter { intervals: vec![KlineInterval::OneMinute], ..Default::default() };
+    assert_eq!(file_names(&reader, &filter).len(), 3);
+    assert_eq!(reader.read(&filter).unwrap().count(), 4);
+
+    // 记录本身也按条件过滤, 不依赖分区
+    let filter = KlineFilter {
+        symbols: vec!["BtcUsdt".to_string()],
+        intervals: vec![KlineInterval::OneMinute],
+        start_ms: Some(T0 + MINUTE),
+        end_ms: Some(T0 + 2 * MINUTE),
+        ..Default::default()
+    };
+    let flat = FileReader::new(&dir);
+    assert_eq!(open_times(flat.read(&filter).unwrap()), [T0 + MINUTE]);
+    assert_eq!(open_times(reader.read(&filter).unwrap()), [T0 + MINUTE]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn filter_matches() {
+    let k = btc(1);
+    assert!(KlineFilter::default().matches(&k));
+    assert!(KlineFilter { exchange: Some("binance".to_string()), ..Default::default() }.matches(&k));
+    assert!(!KlineFilter { exchange: Some("okx".to_string()), ..Default::default() }.matches(&k));
+    assert!(KlineFilter { symbols: vec!["ethusdt".to_string(), "btcusdt".to_string()], ..Default::default() }.matches(&k));
+    assert!(!KlineFilter { intervals: vec![KlineInterval::OneHour], ..Default::default() }.matches(&k));
+    // 按开盘时间 [start_ms, end_ms)
+    assert!(KlineFilter { start_ms: Some(k.open_time_ms), end_ms: Some(k.open_time_ms + 1), ..Default::default() }.matches(&k));
+    assert!(!KlineFilter { start_ms: Some(k.open_time_ms + 1), ..Default::default() }.matches(&k));
+    assert!(!KlineFilter { end_ms: Some(k.open_time_ms), ..Default::default() }.matches(&k));
+}
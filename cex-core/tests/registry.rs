@@ -0,0 +1,61 @@
+use cex_core::registry::{StreamKey, StreamRegistry};
+use cex_core::KlineInterval;
+
+fn pair(symbol: &str, interval: KlineInterval) -> (String, KlineInterval) {
+    (symbol.to_string(), interval)
+}
+
+#[test]
+fn indices_follow_subscription_order() {
+    let registry = StreamRegistry::new(&[
+        pair("btcusdt", KlineInterval::OneMinute),
+        pair("ethusdt", KlineInterval::OneMinute),
+        pair("btcusdt", KlineInterval::OneHour),
+    ]);
+    assert_eq!(registry.len(), 3);
+    assert_eq!(registry.get("btcusdt", KlineInterval::OneMinute), Some(0));
+    assert_eq!(registry.get("ethusdt", KlineInterval::OneMinute), Some(1));
+    assert_eq!(registry.get("btcusdt", KlineInterval::OneHour), Some(2));
+    assert_eq!(registry.get("btcusdt", KlineInterval::FourHours), None);
+    assert_eq!(registry.key(2), Some(StreamKey::new("btcusdt", KlineInterval::OneHour)));
+    assert_eq!(registry.key(3), None);
+}
+
+#[test]
+fn register_is_idempotent() {
+    // 订阅列表中重复的流只占一个编号
+    let registry = StreamRegistry::new(&[
+        pair("btcusdt", KlineInterval::OneMinute),
+        pair("BTCUSDT", KlineInterval::OneMinute),
+        pair("ethusdt", KlineInterval::OneMinute),
+    ]);
+    assert_eq!(registry.len(), 2);
+    assert_eq!(registry.get("ethusdt", KlineInterval::OneMinute), Some(1));
+
+    // 交易对不区分大小写
+    assert_eq!(registry.register("BtcUsdt", KlineInterval::OneMinute), 0);
+    assert_eq!(registry.get("BTCUSDT", KlineInterval::OneMinute), Some(0));
+    assert_eq!(registry.key(0).unwrap().symbol, "btcusdt");
+    assert_eq!(registry.len(), 2);
+}
+
+#[test]
+fn runtime_registration_appends() {
+    let registry = StreamRegistry::new(&[pair("btcusdt", KlineInterval::OneMinute)]);
+    // 克隆共享同一张表, 连接任务中注册的流在句柄中可见
+    let shared = registry.clone();
+    assert_eq!(shared.register("ethusdt", KlineInterval::FiveMinutes), 1);
+    assert_eq!(registry.get("ethusdt", KlineInterval::FiveMinutes), Some(1));
+    assert_eq!(registry.register("solusdt", KlineInterval::OneMinute), 2);
+    assert_eq!(shared.len(), 3);
+
+    // 设置 partial 不影响编号
+    registry.set_partial(1, true);
+    assert!(shared.is_partial(1));
+    assert!(!shared.is_partial(0));
+    registry.set_partial(1, false);
+    assert!(!shared.is_partial(1));
+    assert_eq!(registry.register("ethusdt", KlineInterval::FiveMinutes), 1);
+
+    assert!(StreamRegistry::default().is_empty());
+}
@@ -0,0 +1,164 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use cex_core::reader::ShmemReader;
+use cex_core::ring::{RingRead, RingReader, RingWriter, RING_HEADER_LEN};
+use cex_core::writer::{create_writer, ShmemWriterConfig, WriterType};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+
+const CAPACITY: usize = 128;
+// RingHeader 中 claim_seq 的偏移
+const CLAIM_SEQ_OFFSET: usize = 64;
+
+/// 按 8 字节对齐的内存
+fn memory(capacity: usize) -> Vec<u64> {
+    vec![0u64; (RING_HEADER_LEN + capacity) / 8]
+}
+
+fn ring(memory: &mut [u64]) -> (RingWriter, RingReader) {
+    let (ptr, len) = (memory.as_mut_ptr() as *mut u8, memory.len() * 8);
+    unsafe { (RingWriter::init(ptr, len).unwrap(), RingReader::attach(ptr, len).unwrap()) }
+}
+
+fn payload(i: usize, len: usize) -> Vec<u8> {
+    (0..len).map(|j| (i + j) as u8).collect()
+}
+
+fn record(read: Option<RingRead>) -> Vec<u8> {
+    match read {
+        Some(RingRead::Record(data)) => data,
+        other => panic!("expected record, got {:?}", other),
+    }
+}
+
+/// 数据区 offset 处的 u32
+fn data_u32(memory: &[u64], offset: usize) -> u32 {
+    let bytes = unsafe { std::slice::from_raw_parts(memory.as_ptr() as *const u8, memory.len() * 8) };
+    u32::from_le_bytes(bytes[RING_HEADER_LEN + offset..][..4].try_into().unwrap())
+}
+
+#[test]
+fn wraps_around_with_padding() {
+    let mut memory = memory(CAPACITY);
+    let (mut writer, mut reader) = ring(&mut memory);
+    assert_eq!(writer.capacity(), CAPACITY as u64);
+    assert!(reader.try_read().is_none());
+
+    // 每条记录 24 字节, 第 6 条在偏移 120 处放不下
+    for i in 0..5 {
+        writer.push(&payload(i, 20)).unwrap();
+        assert_eq!(record(reader.try_read()), payload(i, 20));
+    }
+    writer.push(&payload(5, 20)).unwrap();
+    assert_eq!(data_u32(&memory, 120), u32::MAX);
+    assert_eq!(data_u32(&memory, 0), 20);
+    assert_eq!(record(reader.try_read()), payload(5, 20));
+    assert_eq!(writer.reader_lag(), 0);
+
+    // 不同长度的记录多次绕回, 读取端跟上时不丢数据
+    for i in 0..200 {
+        let len = i % 50;
+        writer.push(&payload(i, len)).unwrap();
+        assert_eq!(record(reader.try_read()), payload(i, len));
+    }
+    assert!(reader.try_read().is_none());
+    assert_eq!(writer.overruns(), 0);
+
+    // 单条记录不能超过容量的一半
+    assert!(writer.push(&payload(0, CAPACITY / 2)).is_err());
+}
+
+#[test]
+fn detects_overrun_and_skips_to_latest() {
+    let mut memory = memory(CAPACITY);
+    let (mut writer, mut reader) = ring(&mut memory);
+    writer.push(&payload(0, 20)).unwrap();
+    assert_eq!(record(reader.try_read()), payload(0, 20));
+
+    // 读取端落后超过 capacity
+    for i in 1..=6 {
+        writer.push(&payload(i, 20)).unwrap();
+    }
+    assert_eq!(writer.reader_lag(), 6 * 24 + 8);
+    match reader.try_read() {
+        Some(RingRead::Overrun { lost_bytes }) => assert_eq!(lost_bytes, 6 * 24 + 8),
+        other => panic!("expected overrun, got {:?}", other),
+    }
+    assert_eq!(writer.overruns(), 1);
+    assert_eq!(writer.reader_lag(), 0);
+
+    // 跳到最新位置后继续读取新的记录
+    assert!(reader.try_read().is_none());
+    writer.push(&payload(7, 20)).unwrap();
+    assert_eq!(record(reader.try_read()), payload(7, 20));
+}
+
+#[test]
+fn discards_torn_read_and_reads_next_record() {
+    let mut memory = memory(CAPACITY);
+    let (mut writer, mut reader) = ring(&mut memory);
+    writer.push(&payload(0, 20)).unwrap();
+
+    // 读取端复制数据时写入端已声明覆盖这段区域（写入尚未完成）
+    let claim_seq = unsafe { &*((memory.as_ptr() as *const u8).add(CLAIM_SEQ_OFFSET) as *const AtomicU64) };
+    claim_seq.store(CAPACITY as u64 + 24, Ordering::Relaxed);
+    match reader.try_read() {
+        Some(RingRead::Overrun { lost_bytes }) => assert_eq!(lost_bytes, 24),
+        other => panic!("expected overrun, got {:?}", other),
+    }
+    assert_eq!(writer.overruns(), 1);
+
+    // 之后的记录正常读取
+    assert!(reader.try_read().is_none());
+    writer.push(&payload(1, 20)).unwrap();
+    assert_eq!(record(reader.try_read()), payload(1, 20));
+}
+
+#[test]
+fn rejects_uninitialized_memory() {
+    let memory = memory(CAPACITY);
+    assert!(unsafe { RingReader::attach(memory.as_ptr() as *const u8, memory.len() * 8) }.is_err());
+    let mut small = vec![0u64; RING_HEADER_LEN / 8 + 8];
+    assert!(unsafe { RingWriter::init(small.as_mut_ptr() as *mut u8, small.len() * 8) }.is_err());
+}
+
+fn kline(i: u64) -> SimpleKLine {
+    let price = Decimal::new(10434906 + i as i64, 2);
+    let open_time = 1748736000000 + i * 60_000;
+    SimpleKLine::new("binance", "BTCUSDT", open_time, open_time + 59_999, KlineInterval::OneMinute, price, price, price, price, Decimal::ONE, 1)
+}
+
+#[tokio::test]
+async fn shmem_reader_round_trip() {
+    let shmem_name = format!("cex-core-ring-{}", std::process::id());
+    let writer = create_writer(WriterType::Shmem(ShmemWriterConfig {
+        symbol: "BTCUSDT".to_string(),
+        shmem_size: 4096,
+        shmem_name: shmem_name.clone(),
+    }))
+    .unwrap();
+    // 写入器创建完成后再连接
+    writer.flush().await.unwrap();
+    let mut reader = ShmemReader::open(&shmem_name).unwrap();
+    assert!(reader.try_next::<SimpleKLine>().unwrap().is_none());
+
+    for i in 0..3 {
+        writer.write(&kline(i)).await.unwrap();
+    }
+    writer.flush().await.unwrap();
+    for i in 0..3 {
+        let read = reader.try_next::<SimpleKLine>().unwrap().unwrap();
+        assert_eq!(serde_json::to_value(&read).unwrap(), serde_json::to_value(kline(i)).unwrap());
+    }
+    assert!(reader.try_next::<SimpleKLine>().unwrap().is_none());
+
+    // 落后超过容量时报告溢出, 之后从最新位置继续
+    for i in 0..100 {
+        writer.write(&kline(i)).await.unwrap();
+    }
+    writer.flush().await.unwrap();
+    assert!(reader.try_next::<SimpleKLine>().is_err());
+    assert!(reader.try_next::<SimpleKLine>().unwrap().is_none());
+    writer.write(&kline(100)).await.unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(reader.try_next::<SimpleKLine>().unwrap().unwrap().open_time_ms, kline(100).open_time_ms);
+}
@@ -0,0 +1,390 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use cex_core::reader::{FileReader, KlineFilter, ShmemReader};
+use cex_core::writer::{
+    create_writer, create_writer_with_queue, FileWriterConfig, OverflowPolicy, QueueConfig, Record, ShmemWriterConfig, Writer, WriterType,
+};
+use crossbeam::channel::{bounded, Receiver, Sender};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+
+// 2025-06-01 08:00 (UTC+8), 8小时轮转周期的开始
+const T0: u64 = 1748736000000;
+const MINUTE: u64 = 60_000;
+const HOUR: u64 = 60 * MINUTE;
+
+fn kline(symbol: &str, interval: KlineInterval, open_time: u64) -> SimpleKLine {
+    let price = Decimal::new(10434906, 2);
+    SimpleKLine::new("binance", symbol, open_time, open_time + interval.duration_ms() - 1, interval, price, price, price, price, Decimal::ONE, 1)
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cex-core-writer-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 只在显式 flush 时写出
+fn config(dir: &Path) -> FileWriterConfig {
+    FileWriterConfig {
+        base_path: dir.to_path_buf(),
+        flush_bytes: 1 << 20,
+        flush_interval: 3600,
+        ..Default::default()
+    }
+}
+
+/// 文件中完整的 zstd 帧数
+fn frame_count(path: &Path) -> usize {
+    let data = fs::read(path).unwrap();
+    let (mut pos, mut count) = (0, 0);
+    while pos < data.len() {
+        pos += zstd::zstd_safe::find_frame_compressed_size(&data[pos..]).unwrap();
+        count += 1;
+    }
+    count
+}
+
+fn read_file(path: &Path) -> Vec<(KlineInterval, u64)> {
+    let reader = FileReader::new(path.parent().unwrap());
+    reader.read_file(path, &KlineFilter::default()).map(|k| (k.interval, k.open_time_ms)).collect()
+}
+
+#[tokio::test]
+async fn keeps_adjacent_periods_open() {
+    let dir = temp_dir("periods");
+    let writer = create_writer(WriterType::File(config(&dir))).unwrap();
+
+    // 周期边界后, 上一周期开盘的 4h K线与新周期的 1m K线交替到达
+    let bar_4h = kline("ETHUSDT", KlineInterval::FourHours, T0 - 4 * HOUR);
+    for i in 0..5 {
+        writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 + i * MINUTE)).await.unwrap();
+        writer.write(&bar_4h).await.unwrap();
+    }
+    // 更早周期的迟到数据单独追加, 不影响当前的两个文件
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 - 16 * HOUR)).await.unwrap();
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 + 5 * MINUTE)).await.unwrap();
+    writer.flush().await.unwrap();
+
+    let previous = dir.join("kline_20250601-0000.zst");
+    let current = dir.join("kline_20250601-0800.zst");
+    let late = dir.join("kline_20250531-1600.zst");
+    // 两个文件都只在 flush 时写出一帧, 没有反复关闭
+    assert_eq!(frame_count(&previous), 1);
+    assert_eq!(frame_count(&current), 1);
+    assert_eq!(frame_count(&late), 1);
+
+    assert_eq!(read_file(&previous), vec![(KlineInterval::FourHours, T0 - 4 * HOUR); 5]);
+    assert_eq!(read_file(&current), (0..6).map(|i| (KlineInterval::OneMinute, T0 + i * MINUTE)).collect::<Vec<_>>());
+    assert_eq!(read_file(&late), [(KlineInterval::OneMinute, T0 - 16 * HOUR)]);
+
+    // 进入下一个周期后最早的文件关闭, 再次 flush 不会写入
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 + 8 * HOUR)).await.unwrap();
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 + 6 * MINUTE)).await.unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(frame_count(&previous), 1);
+    assert_eq!(frame_count(&current), 2);
+    assert_eq!(frame_count(&dir.join("kline_20250601-1600.zst")), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 自定义记录, 按成交时间轮转
+#[derive(Clone, serde::Serialize)]
+struct Fill {
+    symbol: String,
+    time_ms: u64,
+}
+
+impl Record for Fill {
+    fn event_time_ms(&self) -> u64 {
+        self.time_ms
+    }
+
+    fn partition_value(&self, name: &str) -> Option<String> {
+        (name == "symbol").then(|| self.symbol.clone())
+    }
+}
+
+#[tokio::test]
+async fn routes_records_by_event_time() {
+    let k = kline("BTCUSDT", KlineInterval::FourHours, T0 - 4 * HOUR);
+    // K线按开盘时间归档, 不按收盘时间
+    assert_eq!(k.event_time_ms(), T0 - 4 * HOUR);
+    assert_eq!(k.partition_value("exchange").as_deref(), Some("binance"));
+    assert_eq!(k.partition_value("symbol").as_deref(), Some("BTCUSDT"));
+    assert_eq!(k.partition_value("interval").as_deref(), Some("4h"));
+    assert_eq!(k.partition_value("other"), None);
+    assert!(k.as_kline().is_some());
+
+    let dir = temp_dir("record");
+    let writer = create_writer(WriterType::File(FileWriterConfig {
+        partition: Some("{symbol}".to_string()),
+        ..config(&dir)
+    }))
+    .unwrap();
+    for time_ms in [T0 - 1, T0] {
+        writer.write(&Fill { symbol: "BTCUSDT".to_string(), time_ms }).await.unwrap();
+    }
+    writer.flush().await.unwrap();
+    assert!(dir.join("BTCUSDT/kline_20250601-0000.zst").exists());
+    assert!(dir.join("BTCUSDT/kline_20250601-0800.zst").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn expands_partition_template() {
+    let dir = temp_dir("template");
+    let writer = create_writer(WriterType::File(FileWriterConfig {
+        // 固定的目录名原样保留, 多余的 / 忽略
+        partition: Some("/{exchange}//spot/{symbol}/{interval}/".to_string()),
+        ..config(&dir)
+    }))
+    .unwrap();
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0)).await.unwrap();
+    writer.write(&kline("ETHUSDT", KlineInterval::FourHours, T0)).await.unwrap();
+    // 记录没有的分区字段写入失败
+    writer.write(&Fill { symbol: "BTCUSDT".to_string(), time_ms: T0 }).await.unwrap();
+    writer.flush().await.unwrap();
+
+    assert_eq!(read_file(&dir.join("binance/spot/BTCUSDT/1m/kline_20250601-0800.zst")), [(KlineInterval::OneMinute, T0)]);
+    assert_eq!(read_file(&dir.join("binance/spot/ETHUSDT/4h/kline_20250601-0800.zst")), [(KlineInterval::FourHours, T0)]);
+    let metrics = writer.metrics();
+    assert_eq!((metrics.written, metrics.write_errors), (2, 1));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn closes_idle_files_without_writes() {
+    let dir = temp_dir("idle");
+    let writer = create_writer(WriterType::File(FileWriterConfig { idle_timeout: 1, ..config(&dir) })).unwrap();
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0)).await.unwrap();
+
+    // 没有新的写入或 flush, 后台线程定时关闭空闲文件并写出缓冲
+    let path = dir.join("kline_20250601-0800.zst");
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while fs::metadata(&path).map(|m| m.len()).unwrap_or(0) == 0 {
+        assert!(Instant::now() < deadline, "idle file not closed");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(frame_count(&path), 1);
+    assert_eq!(read_file(&path), [(KlineInterval::OneMinute, T0)]);
+
+    // 关闭后再次写入同一周期时追加新的帧
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 + MINUTE)).await.unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(frame_count(&path), 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn flushes_quiet_stream_on_interval() {
+    let dir = temp_dir("interval");
+    let writer = create_writer(WriterType::File(FileWriterConfig { flush_interval: 1, ..config(&dir) })).unwrap();
+    writer.write(&kline("BTCUSDT", KlineInterval::FourHours, T0)).await.unwrap();
+
+    // 之后没有新的记录, 缓冲仍按 flush_interval 写出, 文件保持打开
+    let path = dir.join("kline_20250601-0800.zst");
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while fs::metadata(&path).map(|m| m.len()).unwrap_or(0) == 0 {
+        assert!(Instant::now() < deadline, "buffer not flushed");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(read_file(&path), [(KlineInterval::FourHours, T0)]);
+    assert_eq!(writer.metrics().written, 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+fn shmem(name: &str) -> WriterType {
+    WriterType::Shmem(ShmemWriterConfig { symbol: "BTCUSDT".to_string(), shmem_size: 64 * 1024, shmem_name: name.to_string() })
+}
+
+#[tokio::test]
+async fn multi_isolates_sink_errors() {
+    let dir = temp_dir("multi");
+    let writer = create_writer(WriterType::Multi(vec![
+        WriterType::File(config(&dir.join("json"))),
+        WriterType::BinaryFile(config(&dir.join("binary"))),
+    ]))
+    .unwrap();
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0)).await.unwrap();
+    // 二进制写入器不接受非K线记录, json 写入器照常写入
+    writer.write(&Fill { symbol: "BTCUSDT".to_string(), time_ms: T0 + MINUTE }).await.unwrap();
+    writer.flush().await.unwrap();
+
+    let json = FileReader::new(dir.join("json"));
+    assert_eq!(json.files(&KlineFilter::default()).unwrap().len(), 1);
+    let lines = zstd::stream::decode_all(fs::File::open(dir.join("json/kline_20250601-0800.zst")).unwrap()).unwrap();
+    assert_eq!(lines.split(|b| *b == b'\n').filter(|l| !l.is_empty()).count(), 2);
+    assert_eq!(read_file(&dir.join("binary/kline_20250601-0800.bin")), [(KlineInterval::OneMinute, T0)]);
+    let metrics = writer.metrics();
+    assert_eq!((metrics.written, metrics.write_errors), (1, 1));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn multi_retries_failed_sink() {
+    let dir = temp_dir("multi-retry");
+    let name = format!("cex-core-writer-multi-{}", std::process::id());
+    // 共享内存名称已被占用
+    let occupied = create_writer(shmem(&name)).unwrap();
+    assert!(create_writer(shmem(&name)).is_err());
+    // 全部创建失败时返回错误
+    assert!(create_writer(WriterType::Multi(vec![shmem(&name)])).is_err());
+
+    let writer = create_writer(WriterType::Multi(vec![WriterType::File(config(&dir)), shmem(&name)])).unwrap();
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0)).await.unwrap();
+    let err = writer.flush().await.unwrap_err();
+    assert!(err.to_string().contains(&format!("shmem:{}", name)), "{}", err);
+    assert_eq!(read_file(&dir.join("kline_20250601-0800.zst")), [(KlineInterval::OneMinute, T0)]);
+    assert_eq!(writer.metrics().write_errors, 1);
+
+    // 名称释放后后台重试创建
+    drop(occupied);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while writer.flush().await.is_err() {
+        assert!(Instant::now() < deadline, "sink not recreated");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let mut reader = ShmemReader::open(&name).unwrap();
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 + MINUTE)).await.unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(reader.try_next::<SimpleKLine>().unwrap().unwrap().open_time_ms, T0 + MINUTE);
+    assert_eq!(writer.metrics().write_errors, 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// 序列化时等待 gate 关闭, 让后台写入线程停在这条记录上
+#[derive(Clone)]
+struct Blocker(Receiver<()>);
+
+impl serde::Serialize for Blocker {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let _ = self.0.recv();
+        serializer.serialize_str("blocker")
+    }
+}
+
+impl Record for Blocker {
+    fn event_time_ms(&self) -> u64 {
+        T0
+    }
+}
+
+/// 后台线程停在第一条记录上, 队列（容量 2）已满, 关闭返回的 Sender 后继续写入
+async fn stalled_writer(dir: &Path, overflow: OverflowPolicy) -> (Writer, Sender<()>) {
+    let writer = create_writer_with_queue(WriterType::File(config(dir)), QueueConfig { capacity: 2, overflow }).unwrap();
+    let (release, gate) = bounded(0);
+    writer.write(&Blocker(gate)).await.unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while writer.metrics().queue_depth > 0 {
+        assert!(Instant::now() < deadline, "writer thread idle");
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    for i in 0..2 {
+        writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 + i * MINUTE)).await.unwrap();
+    }
+    assert_eq!(writer.metrics().queue_depth, 2);
+    (writer, release)
+}
+
+/// 文件中K线的开盘时间, 其它记录为 None
+fn json_open_times(path: &Path) -> Vec<Option<u64>> {
+    let data = zstd::stream::decode_all(fs::File::open(path).unwrap()).unwrap();
+    data.split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice::<serde_json::Value>(line).unwrap()["open_time_ms"].as_u64())
+        .collect()
+}
+
+#[tokio::test]
+async fn overflow_error_rejects_new_record() {
+    let dir = temp_dir("overflow-error");
+    let (writer, release) = stalled_writer(&dir, OverflowPolicy::Error).await;
+    assert!(writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 + 2 * MINUTE)).await.is_err());
+    assert_eq!(writer.metrics().dropped, 1);
+
+    drop(release);
+    writer.flush().await.unwrap();
+    assert_eq!(json_open_times(&dir.join("kline_20250601-0800.zst")), [None, Some(T0), Some(T0 + MINUTE)]);
+    let metrics = writer.metrics();
+    assert_eq!((metrics.written, metrics.dropped, metrics.queue_depth), (3, 1, 0));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn overflow_drop_oldest_keeps_new_record() {
+    let dir = temp_dir("overflow-drop");
+    let (writer, release) = stalled_writer(&dir, OverflowPolicy::DropOldest).await;
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 + 2 * MINUTE)).await.unwrap();
+    assert_eq!(writer.metrics().dropped, 1);
+
+    drop(release);
+    writer.flush().await.unwrap();
+    assert_eq!(json_open_times(&dir.join("kline_20250601-0800.zst")), [None, Some(T0 + MINUTE), Some(T0 + 2 * MINUTE)]);
+    let metrics = writer.metrics();
+    assert_eq!((metrics.written, metrics.dropped), (3, 1));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn overflow_block_waits_for_space() {
+    let dir = temp_dir("overflow-block");
+    let (writer, release) = stalled_writer(&dir, OverflowPolicy::Block).await;
+    let blocked = tokio::spawn({
+        let writer = writer.clone();
+        async move { writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0 + 2 * MINUTE)).await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!blocked.is_finished());
+
+    drop(release);
+    blocked.await.unwrap().unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(json_open_times(&dir.join("kline_20250601-0800.zst")), [None, Some(T0), Some(T0 + MINUTE), Some(T0 + 2 * MINUTE)]);
+    let metrics = writer.metrics();
+    assert_eq!((metrics.written, metrics.dropped), (4, 0));
+    // 第一条记录等待了至少 100ms
+    assert!(metrics.max_latency_us >= 100_000, "{:?}", metrics);
+    assert!(metrics.avg_latency_us > 0 && metrics.avg_latency_us <= metrics.max_latency_us, "{:?}", metrics);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn counts_errors_per_sink() {
+    let dir = temp_dir("sink-errors");
+    let (json, binary) = (WriterType::File(config(&dir.join("json"))), WriterType::BinaryFile(config(&dir.join("binary"))));
+    let writer = create_writer(WriterType::Multi(vec![json.clone(), binary.clone()])).unwrap();
+    for time_ms in [T0, T0 + MINUTE] {
+        writer.write(&Fill { symbol: "BTCUSDT".to_string(), time_ms }).await.unwrap();
+    }
+    writer.write(&kline("BTCUSDT", KlineInterval::OneMinute, T0)).await.unwrap();
+    writer.flush().await.unwrap();
+    let metrics = writer.metrics();
+    assert_eq!((metrics.written, metrics.write_errors), (1, 2));
+    assert_eq!(metrics.sink_errors, [(json.name(), 0), (binary.name(), 2)]);
+
+    // 单个写入器时只有一项
+    let writer = create_writer(binary.clone()).unwrap();
+    writer.write(&Fill { symbol: "BTCUSDT".to_string(), time_ms: T0 }).await.unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(writer.metrics().sink_errors, [(binary.name(), 1)]);
+    writer.close().await.unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rejects_zero_capacity_and_rotation() {
+    let dir = temp_dir("zero");
+    let queue = |capacity| QueueConfig { capacity, overflow: OverflowPolicy::DropOldest };
+    assert!(create_writer_with_queue(WriterType::File(config(&dir)), queue(0)).is_err());
+
+    let zero = FileWriterConfig { rotation_interval: 0, ..config(&dir) };
+    assert!(create_writer(WriterType::File(zero.clone())).is_err());
+    assert!(create_writer(WriterType::BinaryFile(FileWriterConfig { rotation_interval: -1, ..config(&dir) })).is_err());
+    // 多路写入时配置错误不会作为创建失败在后台重试
+    let err = create_writer(WriterType::Multi(vec![WriterType::File(config(&dir)), WriterType::BinaryFile(zero)])).err().unwrap();
+    assert!(format!("{:#}", err).contains("rotation_interval"), "{:#}", err);
+    fs::remove_dir_all(&dir).unwrap();
+}
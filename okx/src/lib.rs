@@ -0,0 +1,397 @@
+use std::time::Duration;
+
+use cex_core::registry::StreamRegistry;
+use cex_core::source::{MarketDataSource, ReconnectPolicy, Reconnector, SubscribeOptions, TaskSubscriptions};
+use cex_core::{CexError, ChannelMsg, Decimal, KlineEvent, KlineInterval, Ping, SimpleKLine};
+
+use crossbeam::channel::Sender;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message, WebSocketStream};
+use tracing::{debug, error, info, warn};
+
+/// OKX 公共K线频道所在的 business 地址
+pub const OKX_BUSINESS_WS_URL: &str = "wss://ws.okx.com:8443/ws/v5/business";
+
+/// OKX 在 30s 内没有数据就会断开连接，需要提前发送 "ping"
+const PING_INTERVAL: Duration = Duration::from_secs(25);
+
+/// 超过该时间没有收到任何数据（包括 "pong"）视为连接已失效
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/*
+{
+    "arg": {
+        "channel": "candle1m",
+        "instId": "BTC-USDT"
+    },
+    "data": [
+        [
+            "1748877600000",   // ts 开盘时间
+            "104349.1",        // o
+            "104380.9",        // h
+            "104349.1",        // l
+            "104380.9",        // c
+            "10.32405",        // vol 交易量（币）
+            "1077392.5436071", // volCcy
+            "1077392.5436071", // volCcyQuote
+            "1"                // confirm: 0 未完结, 1 已完结
+        ]
+    ]
+}
+*/
+#[derive(Debug, Deserialize, Clone)]
+struct OKXPushFrame {
+    arg: OKXArg,
+    data: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct OKXArg {
+    channel: String,
+    #[serde(rename = "instId")]
+    inst_id: String,
+}
+
+/// 订阅/错误事件: {"event":"subscribe","arg":{...}} / {"event":"error","code":"60012","msg":"..."}
+#[derive(Debug, Deserialize, Clone)]
+struct OKXEventFrame {
+    event: String,
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    msg: String,
+}
+
+/// OKX 连接配置
+#[derive(Debug, Clone)]
+pub struct OkxConfig {
+    /// business websocket 地址, 测试时可指向本地服务
+    pub ws_url: String,
+    /// 断线重连策略
+    pub reconnect: ReconnectPolicy,
+    /// 发送 "ping" 的间隔
+    pub ping_interval: Duration,
+    /// 超过该时间没有收到数据则断开重连
+    pub read_timeout: Duration,
+}
+
+impl Default for OkxConfig {
+    fn default() -> Self {
+        Self {
+            ws_url: OKX_BUSINESS_WS_URL.to_string(),
+            reconnect: ReconnectPolicy::default(),
+            ping_interval: PING_INTERVAL,
+            read_timeout: READ_TIMEOUT,
+        }
+    }
+}
+
+/// OKX 支持的K线周期, OKX 没有 8h
+pub const OKX_INTERVALS: &[KlineInterval] = &[
+    KlineInterval::OneSecond,
+    KlineInterval::OneMinute,
+    KlineInterval::ThreeMinutes,
+    KlineInterval::FiveMinutes,
+    KlineInterval::FifteenMinutes,
+    KlineInterval::ThirtyMinutes,
+    KlineInterval::OneHour,
+    KlineInterval::TwoHours,
+    KlineInterval::FourHours,
+    KlineInterval::SixHours,
+    KlineInterval::TwelveHours,
+    KlineInterval::OneDay,
+    KlineInterval::ThreeDays,
+    KlineInterval::OneWeek,
+    KlineInterval::OneMonth,
+];
+
+/// 周期对应的 OKX bar 名称
+///
+/// 6h 及以上使用按 UTC 对齐的 `*utc` bar, 与 binance 一致（OKX 默认的 6H/1D 等按 UTC+8 对齐）
+pub fn okx_bar(interval: KlineInterval) -> Option<&'static str> {
+    Some(match interval {
+        KlineInterval::OneSecond => "1s",
+        KlineInterval::OneMinute => "1m",
+        KlineInterval::ThreeMinutes => "3m",
+        KlineInterval::FiveMinutes => "5m",
+        KlineInterval::FifteenMinutes => "15m",
+        KlineInterval::ThirtyMinutes => "30m",
+        KlineInterval::OneHour => "1H",
+        KlineInterval::TwoHours => "2H",
+        KlineInterval::FourHours => "4H",
+        KlineInterval::SixHours => "6Hutc",
+        KlineInterval::TwelveHours => "12Hutc",
+        KlineInterval::OneDay => "1Dutc",
+        KlineInterval::ThreeDays => "3Dutc",
+        KlineInterval::OneWeek => "1Wutc",
+        KlineInterval::OneMonth => "1Mutc",
+        KlineInterval::EightHours => return None,
+    })
+}
+
+/// 由 OKX bar 名称得到周期, 只识别 [`okx_bar`] 返回的名称
+pub fn from_okx_bar(bar: &str) -> Option<KlineInterval> {
+    OKX_INTERVALS.iter().copied().find(|i| okx_bar(*i) == Some(bar))
+}
+
+/// OKX 行情数据源
+pub struct OkxSource {
+    subscriptions: TaskSubscriptions,
+}
+
+impl OkxSource {
+    pub fn new() -> Self {
+        Self {
+            subscriptions: TaskSubscriptions::new(|pair_list, options, tx| {
+                Box::pin(subscribe_okx_with_options(OkxConfig::default(), pair_list, options, tx))
+            }),
+        }
+    }
+}
+
+impl Default for OkxSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for OkxSource {
+    fn name(&self) -> &'static str {
+        "okx"
+    }
+
+    fn supported_intervals(&self) -> &'static [KlineInterval] {
+        OKX_INTERVALS
+    }
+
+    async fn subscribe_with(
+        &self,
+        pair_list: Vec<(String, KlineInterval)>,
+        options: SubscribeOptions,
+        tx: Sender<ChannelMsg>,
+    ) -> Result<()> {
+        self.check_intervals(&pair_list)?;
+        self.subscriptions.spawn(pair_list, options, tx);
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, pair_list: Vec<(String, KlineInterval)>) -> Result<()> {
+        self.subscriptions.remove(&pair_list);
+        Ok(())
+    }
+}
+
+/// (instId, bar), sender
+/// ("BTC-USDT", "1m")
+pub async fn subscribe_okx(pair_list: Vec<(String, KlineInterval)>, tx: Sender<ChannelMsg>) {
+    subscribe_okx_with_config(OkxConfig::default(), pair_list, tx).await
+}
+
+/// 与 [`subscribe_okx`] 相同，使用指定的连接配置
+pub async fn subscribe_okx_with_config(config: OkxConfig, pair_list: Vec<(String, KlineInterval)>, tx: Sender<ChannelMsg>) {
+    subscribe_okx_with_options(config, pair_list, SubscribeOptions::default(), tx).await
+}
+
+/// 与 [`subscribe_okx_with_config`] 相同，使用指定的订阅选项
+pub async fn subscribe_okx_with_options(
+    config: OkxConfig,
+    pair_list: Vec<(String, KlineInterval)>,
+    options: SubscribeOptions,
+    tx: Sender<ChannelMsg>,
+) {
+    info!("subscribe to okx: {:?} {:?}", pair_list, options);
+    let registry = StreamRegistry::new(&pair_list);
+    for index in 0..registry.len() {
+        registry.set_partial(index, options.partial);
+    }
+    let mut reconnector = Reconnector::new("okx", config.reconnect.clone(), tx.clone());
+    loop { // 出错或服务端断开后自动重连
+        let reason = match connect_okx(&config, &pair_list, &registry, &mut reconnector, tx.clone()).await {
+            Ok(()) => "connection closed".to_string(),
+            Err(e) => {
+                error!("Failed to connect to OKX: {}", e);
+                e.to_string()
+            }
+        };
+        if !reconnector.disconnected(reason).await {
+            break;
+        }
+    }
+}
+
+async fn connect_okx(
+    config: &OkxConfig,
+    pair_list: &[(String, KlineInterval)],
+    registry: &StreamRegistry,
+    reconnector: &mut Reconnector,
+    tx: Sender<ChannelMsg>,
+) -> Result<()> {
+    let (mut ws_stream, _) = connect_async(config.ws_url.as_str()).await?;
+    info!("Connected to OKX");
+
+    let subs = json!({
+        "op": "subscribe",
+        "args": pair_list.iter().filter_map(|(inst_id, interval)| okx_bar(*interval).map(|bar| json!({
+            "channel": format!("candle{}", bar),
+            "instId": inst_id,
+        }))).collect::<Vec<_>>(),
+    });
+
+    ws_stream.send(Message::Text(subs.to_string())).await?;
+    info!("Subscribed to OKX");
+    reconnector.connected();
+
+    handle_websocket_stream(ws_stream, config, registry, tx).await?;
+
+    Ok(())
+}
+
+async fn handle_websocket_stream<S>(
+    mut ws_stream: WebSocketStream<S>,
+    config: &OkxConfig,
+    registry: &StreamRegistry,
+    tx: Sender<ChannelMsg>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    let mut ping_timer = tokio::time::interval(config.ping_interval);
+    ping_timer.tick().await;
+    // 半开的连接不会报错也不会关闭, 只能靠读超时发现
+    let mut last_received = tokio::time::Instant::now();
+
+    loop {
+        let message = tokio::select! {
+            message = ws_stream.next() => match message {
+                Some(message) => message,
+                None => break,
+            },
+            _ = ping_timer.tick() => {
+                ws_stream.send(Message::Text("ping".to_string())).await?;
+                continue;
+            }
+            _ = tokio::time::sleep_until(last_received + config.read_timeout) => {
+                anyhow::bail!("no data from OKX for {:?}", config.read_timeout);
+            }
+        };
+        last_received = tokio::time::Instant::now();
+
+        match message {
+            Ok(Message::Text(text)) if text == "pong" => {
+                debug!("收到pong消息");
+                if let Err(e) = tx.try_send(ChannelMsg::Ping(Ping::new("okx".to_string(), Utc::now().timestamp_millis()))) {
+                    error!("Failed to send ping message: {}", e);
+                }
+            }
+            Ok(Message::Text(text)) => {
+                if let Ok(frame) = serde_json::from_str::<OKXPushFrame>(&text) {
+                    handle_push_frame(frame, registry, &tx);
+                } else if let Ok(event) = serde_json::from_str::<OKXEventFrame>(&text) {
+                    if event.event == "error" {
+                        error!("OKX返回错误: {} {}", event.code, event.msg);
+                        if let Err(e) = tx.try_send(ChannelMsg::Error(CexError::ApiError(format!("{} {}", event.code, event.msg)))) {
+                            error!("Failed to send error message: {}", e);
+                        }
+                    } else {
+                        info!("OKX事件: {}", text);
+                    }
+                } else {
+                    warn!("ignore msg: {}", text);
+                }
+            }
+            Ok(Message::Ping(ping)) => {
+                ws_stream.send(Message::Pong(ping)).await?;
+            }
+            Err(e) => {
+                error!("Error receiving message: {}", e);
+                break;
+            }
+            _ => {
+                info!("收到其他类型消息");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_push_frame(frame: OKXPushFrame, registry: &StreamRegistry, tx: &Sender<ChannelMsg>) {
+    let Some(bar) = frame.arg.channel.strip_prefix("candle") else {
+        warn!("ignore channel: {}", frame.arg.channel);
+        return;
+    };
+    let Some(interval) = from_okx_bar(bar) else {
+        warn!("ignore bar: {}", bar);
+        return;
+    };
+    let Some(index) = registry.get(&frame.arg.inst_id, interval) else {
+        warn!("未订阅的K线: {} {}", frame.arg.inst_id, bar);
+        return;
+    };
+
+    for candle in frame.data {
+        // 默认只有当K线周期结束时才发送数据, 开启 partial 的流同时发送未收盘的更新
+        let is_final = candle.get(8).map(String::as_str) == Some("1");
+        if !is_final && !registry.is_partial(index) {
+            continue;
+        }
+        match parse_candle(&frame.arg.inst_id, interval, &candle) {
+            Ok(kline) => {
+                if let Err(e) = tx.try_send(ChannelMsg::Kline(KlineEvent { index, kline, is_final })) {
+                    error!("Failed to handle kline data: {}", e);
+                }
+            }
+            Err(e) => {
+                error!("Failed to parse okx candle {:?}: {}", candle, e);
+                if let Err(e) = tx.try_send(ChannelMsg::Error(e)) {
+                    error!("Failed to send error message: {}", e);
+                }
+            }
+        }
+    }
+}
+
+fn parse_candle(inst_id: &str, interval: KlineInterval, candle: &[String]) -> Result<SimpleKLine, CexError> {
+    if candle.len() < 9 {
+        return Err(CexError::ParseError(format!("candle field count {}", candle.len())));
+    }
+    let num = |i: usize| -> Result<Decimal, CexError> {
+        candle[i].parse::<Decimal>().map_err(|e| CexError::ParseError(format!("{}: {}", candle[i], e)))
+    };
+    let open_time_ms = candle[0].parse::<u64>().map_err(|e| CexError::ParseError(format!("{}: {}", candle[0], e)))?;
+    let close_time_ms = interval
+        .close_time_ms(open_time_ms)
+        .ok_or_else(|| CexError::ParseError(format!("invalid ts: {}", open_time_ms)))?;
+    let open_time_h = DateTime::from_timestamp_millis(open_time_ms as i64)
+        .ok_or_else(|| CexError::ParseError(format!("invalid ts: {}", open_time_ms)))?
+        .with_timezone(&chrono::FixedOffset::east_opt(8 * 3600).unwrap())
+        .format("%Y%m%d-%H:%M")
+        .to_string();
+
+    Ok(SimpleKLine {
+        exchange: "okx".to_string(),
+        symbol: inst_id.to_string(),
+        open_time_ms,
+        close_time_ms,
+        open_time_h,
+        interval,
+        open: num(1)?,
+        high: num(2)?,
+        low: num(3)?,
+        close: num(4)?,
+        volume: num(5)?,
+        // volCcyQuote, 计价货币成交量
+        quote_volume: Some(num(7)?),
+        // OKX 的K线不提供成交笔数、主动买入量和成交ID
+        trades_count: 0,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,128 @@
+use std::time::{Duration, Instant};
+
+use cex_core::source::ReconnectPolicy;
+use cex_core::{ChannelMsg, ConnectionStatus, KlineInterval};
+use futures_util::StreamExt;
+use okx::{subscribe_okx_with_config, OkxConfig};
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::protocol::Message};
+
+fn policy(max_attempts: Option<u32>) -> ReconnectPolicy {
+    ReconnectPolicy {
+        initial_delay: Duration::from_millis(50),
+        max_delay: Duration::from_millis(200),
+        multiplier: 2.0,
+        jitter: 0.0,
+        max_attempts,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn waits_before_reconnecting() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    // 每个连接收到订阅后立即断开, 记录连接时间
+    let (accepted_tx, accepted_rx) = crossbeam::channel::unbounded();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            accepted_tx.send(Instant::now()).unwrap();
+            let mut ws = accept_async(stream).await.unwrap();
+            let Some(Ok(Message::Text(sub))) = ws.next().await else { panic!("expect subscribe") };
+            assert!(sub.contains("candle1m"));
+            ws.close(None).await.unwrap();
+        }
+    });
+
+    let (tx, rx) = crossbeam::channel::bounded(16);
+    let config = OkxConfig { ws_url: url, reconnect: policy(None), ..Default::default() };
+    let task = tokio::spawn(subscribe_okx_with_config(config, vec![("BTC-USDT".to_string(), KlineInterval::OneMinute)], tx));
+
+    let recv = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    for _ in 0..3 {
+        assert!(matches!(recv(), ChannelMsg::Status(ConnectionStatus::Connected { .. })));
+        assert!(matches!(recv(), ChannelMsg::Status(ConnectionStatus::Disconnected { .. })));
+        // 连接成功过, 每次都从初始间隔开始
+        let ChannelMsg::Status(ConnectionStatus::Reconnecting { attempt, delay_ms, .. }) = recv() else { panic!("expect reconnecting") };
+        assert_eq!((attempt, delay_ms), (1, 50));
+    }
+    task.abort();
+
+    // 两次连接之间至少间隔 initial_delay, 不会空转
+    let times = accepted_rx.try_iter().collect::<Vec<_>>();
+    assert!(times.len() >= 3);
+    for pair in times.windows(2) {
+        assert!(pair[1] - pair[0] >= Duration::from_millis(50), "{:?}", pair[1] - pair[0]);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn gives_up_after_max_attempts() {
+    // 绑定后立即释放端口, 之后的连接都会被拒绝
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let (tx, rx) = crossbeam::channel::bounded(16);
+    let config = OkxConfig { ws_url: url, reconnect: policy(Some(2)), ..Default::default() };
+    let started = Instant::now();
+    subscribe_okx_with_config(config, vec![("BTC-USDT".to_string(), KlineInterval::OneMinute)], tx).await;
+    // 两次重连分别等待 50ms 和 100ms
+    assert!(started.elapsed() >= Duration::from_millis(150));
+
+    let statuses = rx.try_iter().collect::<Vec<_>>();
+    let delays = statuses
+        .iter()
+        .filter_map(|msg| match msg {
+            ChannelMsg::Status(ConnectionStatus::Reconnecting { delay_ms, .. }) => Some(*delay_ms),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(delays, [50, 100]);
+    assert!(matches!(statuses.last(), Some(ChannelMsg::Status(ConnectionStatus::GaveUp { attempts: 2, .. }))));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reconnects_when_server_stops_replying() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    // 连接保持打开, 但不回复 "pong" 也不推送数据
+    let (pings_tx, pings_rx) = crossbeam::channel::unbounded();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let pings_tx = pings_tx.clone();
+            tokio::spawn(async move {
+                let mut ws = accept_async(stream).await.unwrap();
+                while let Some(Ok(message)) = ws.next().await {
+                    if message == Message::Text("ping".to_string()) {
+                        pings_tx.send(()).unwrap();
+                    }
+                }
+            });
+        }
+    });
+
+    let (tx, rx) = crossbeam::channel::bounded(16);
+    let config = OkxConfig {
+        ws_url: url,
+        reconnect: policy(None),
+        ping_interval: Duration::from_millis(50),
+        read_timeout: Duration::from_millis(300),
+    };
+    let task = tokio::spawn(subscribe_okx_with_config(config, vec![("BTC-USDT".to_string(), KlineInterval::OneMinute)], tx));
+
+    let recv = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    let started = Instant::now();
+    assert!(matches!(recv(), ChannelMsg::Status(ConnectionStatus::Connected { .. })));
+    let ChannelMsg::Status(ConnectionStatus::Disconnected { reason, .. }) = recv() else { panic!("expect disconnected") };
+    assert!(reason.contains("no data"), "{}", reason);
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert!(matches!(recv(), ChannelMsg::Status(ConnectionStatus::Reconnecting { attempt: 1, .. })));
+    assert!(matches!(recv(), ChannelMsg::Status(ConnectionStatus::Connected { .. })));
+    task.abort();
+    // 超时前一直在发送 "ping"
+    assert!(pings_rx.try_iter().count() >= 3);
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use cex_core::{CexError, ChannelMsg, ConnectionStatus, Decimal, KlineEvent, KlineInterval};
+use futures_util::{SinkExt, StreamExt};
+use okx::{subscribe_okx_with_config, OkxConfig};
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::protocol::Message};
+
+// 录制的 OKX 推送帧
+const FRAMES: &[&str] = &[
+    r#"{"event":"subscribe","arg":{"channel":"candle1m","instId":"BTC-USDT"},"connId":"a4d3ae55"}"#,
+    r#"{"event":"subscribe","arg":{"channel":"candle1H","instId":"ETH-USDT"},"connId":"a4d3ae55"}"#,
+    r#"{"arg":{"channel":"candle1m","instId":"BTC-USDT"},"data":[["1748877600000","104349.1","104380.9","104349.1","104380.9","10.32405","1077392.5436071","1077392.5436071","0"]]}"#,
+    r#"{"arg":{"channel":"candle1m","instId":"BTC-USDT"},"data":[["1748877600000","104349.1","104390.0","104340.2","104385.5","12.1","1262931.12","1262931.12","1"]]}"#,
+    r#"{"arg":{"channel":"candle1H","instId":"ETH-USDT"},"data":[["1748876400000","2601.5","2610.0","2598.3","2605.7","5321.2","13862016.1","13862016.1","1"]]}"#,
+    "pong",
+    r#"{"arg":{"channel":"candle1m","instId":"BTC-USDT"},"data":[["1748877660000","104385.5","abc","104380.0","104388.1","3.2","334041.6","334041.6","1"]]}"#,
+];
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replay_recorded_frames() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(stream).await.unwrap();
+        let Some(Ok(Message::Text(sub))) = ws.next().await else { panic!("expect subscribe") };
+        let sub: serde_json::Value = serde_json::from_str(&sub).unwrap();
+        assert_eq!(sub["op"], "subscribe");
+        assert_eq!(sub["args"][0]["channel"], "candle1m");
+        assert_eq!(sub["args"][1]["channel"], "candle1H");
+        assert_eq!(sub["args"][1]["instId"], "ETH-USDT");
+        for frame in FRAMES {
+            ws.send(Message::Text(frame.to_string())).await.unwrap();
+        }
+        // 保持连接直到测试结束
+        while ws.next().await.is_some() {}
+    });
+
+    let (tx, rx) = crossbeam::channel::bounded(16);
+    let pair_list = vec![
+        ("BTC-USDT".to_string(), KlineInterval::OneMinute),
+        ("ETH-USDT".to_string(), KlineInterval::OneHour),
+    ];
+    let config = OkxConfig { ws_url: url, ..Default::default() };
+    tokio::spawn(async move { subscribe_okx_with_config(config, pair_list, tx).await });
+
+    let recv = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    let ChannelMsg::Status(ConnectionStatus::Connected { source }) = recv() else { panic!("expect connected") };
+    assert_eq!(source, "okx");
+
+    // 未完结的K线不会被转发
+    let ChannelMsg::Kline(KlineEvent { index, kline: btc, is_final: true }) = recv() else { panic!("expect kline") };
+    assert_eq!(index, 0);
+    assert_eq!(btc.exchange, "okx");
+    assert_eq!(btc.symbol, "BTC-USDT");
+    assert_eq!(btc.open_time_ms, 1748877600000);
+    assert_eq!(btc.close_time_ms, 1748877659999);
+    assert_eq!(btc.close, Decimal::new(1043855, 1));
+    assert_eq!(btc.volume, Decimal::new(121, 1));
+
+    let ChannelMsg::Kline(KlineEvent { index, kline: eth, is_final: true }) = recv() else { panic!("expect kline") };
+    assert_eq!(index, 1);
+    assert_eq!(eth.interval, KlineInterval::OneHour);
+    assert_eq!(eth.close_time_ms, 1748876400000 + 3_600_000 - 1);
+
+    let ChannelMsg::Ping(ping) = recv() else { panic!("expect ping") };
+    assert_eq!(ping.source, "okx");
+
+    // 解析失败的K线以 ParseError 发送到下游
+    let ChannelMsg::Error(CexError::ParseError(e)) = recv() else { panic!("expect parse error") };
+    assert!(e.contains("abc"), "{}", e);
+}
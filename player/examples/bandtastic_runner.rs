@@ -1,30 +1,36 @@
 use cex_core::{
-    structure::{Direction, Position, Signal, Trade},
+    structure::Trade,
     writer::{create_writer, FileWriterConfig, WriterType},
-    CexError, ChannelMsg, Ping, SimpleKLine
+    CexError, ChannelMsg, ConnectionStatus, KlineEvent, KlineInterval, Ping, SimpleKLine
 };
-use binance::subscribe_binance;
+use player::{create_source, default_exchange};
 use chrono::Utc;
 use serde::Deserialize;
 use serde_json::json;
 use tracing::{error, info};
-use std::{path::PathBuf, fs};
+use std::{collections::HashMap, path::PathBuf, fs};
 use tracing_subscriber::fmt::format::FmtSpan;
-use tracing_appender;
-use strategies::{bandtastic::BandtasticStrategy, Strategy};
+use strategies::StrategyRegistry;
 
 // 配置
 #[derive(Debug, Deserialize)]
 struct Config {
+    #[serde(default = "default_exchange")]
+    exchange: String,
     output_dir: String,
     webhook_url: Vec<String>,
-    sub_list: Vec<(String, String)>,
+    sub_list: Vec<(String, KlineInterval)>,
 }
 
+/// 运行的策略, 推送消息中的策略名也取自这里
+const STRATEGY: &str = "bandtastic";
+
+#[allow(clippy::large_enum_variant)]
 enum BoardcastMsg {
     Ping(Ping),
     Trade(SimpleKLine, Trade),
     Error(CexError),
+    Status(ConnectionStatus),
 }
 
 
@@ -61,8 +67,16 @@ async fn main() -> anyhow::Result<()> {
         "sell_mfi_enabled": true,
         "sell_ema_enabled": true,
         "sell_trigger": "sell-bb_upper2",
+        "min_roi": [[0, 0.162], [69, 0.097], [229, 0.061], [566, 0.0]],
+        "stoploss": -0.345,
+        "trailing_stop": true,
+        "trailing_stop_positive": 0.01,
+        "trailing_stop_positive_offset": 0.058,
+        "trailing_only_offset_is_reached": false,
     });
-    let strategy: BandtasticStrategy = serde_json::from_value(params).unwrap();
+    let registry = StrategyRegistry::default();
+    // 先创建一次以校验参数, 之后按需创建的实例不会失败
+    registry.create(STRATEGY, params.clone())?;
 
     // 确保数据目录存在
     let data_dir = PathBuf::from(config.output_dir);
@@ -84,68 +98,57 @@ async fn main() -> anyhow::Result<()> {
     let pair_list = config.sub_list;
     let p_len: usize = pair_list.len();
     let (tx, rx) = crossbeam::channel::bounded(p_len);
-    tokio::spawn(async move { subscribe_binance(pair_list, tx).await });
+    let source = create_source(&config.exchange)?;
+    source.subscribe(pair_list, tx).await?;
+
+    // 配置文件写入器
+    let writer = create_writer(WriterType::File(FileWriterConfig {
+        base_path: data_dir,
+        rotation_interval: 8 * 3600, // 8小时轮转一次
+        ..Default::default()
+    }))?;
+
+    // 行情消息分发到策略线程和写入任务, 各自使用独立的通道
+    let (st_tx, st_rx) = crossbeam::channel::bounded(p_len);
+    let (wr_tx, mut wr_rx) = tokio::sync::mpsc::channel::<SimpleKLine>(p_len);
+    std::thread::spawn(move || {
+        while let Ok(msg) = rx.recv() {
+            if let ChannelMsg::Kline(KlineEvent { kline, .. }) = &msg
+                && wr_tx.blocking_send(kline.clone()).is_err()
+            {
+                error!("写入任务已退出");
+            }
+            let _ = st_tx.send(msg);
+        }
+    });
+
+    let write_task = tokio::spawn(async move {
+        info!("开始写入K线数据");
+        while let Some(kline) = wr_rx.recv().await {
+            if let Err(e) = writer.write(&kline).await {
+                error!("写入K线失败: {:#}", e);
+            }
+        }
+        writer.close().await
+    });
 
     let (bd_tx, bd_rx) = crossbeam::channel::bounded(p_len);
 
-    // index 0 不存在, 需要多创建一个
-    let mut strategies = (0..p_len + 1).map(|_| {strategy.clone()}).collect::<Vec<BandtasticStrategy>>();
-    let mut trades = (0..p_len + 1).map(|_| {Trade::default()}).collect::<Vec<Trade>>();
+    // index 为 StreamRegistry 中的编号, 每个 (symbol, interval) 一个策略实例, 运行中新增的订阅在收到K线时创建
+    let mut runners = HashMap::new();
 
 
-    let st_rx = rx.clone();
     std::thread::spawn(move || {
         info!("开始计算策略");
         while let Ok(msg) = st_rx.recv() {
             match msg {
-                ChannelMsg::Kline((index, kline)) => {
-                    // 如果产生信号，需要根据当前的trade情况来进行判断
-                    if let Some(signal) = strategies[index].next(kline.clone()) {
-                        match signal {
-                            Signal::Enter { direction, price } => {
-                                if trades[index].enter_position.is_some() {
-                                    error!("入场时已有持仓，该策略不支持重复入场");
-                                    continue;
-                                }
-                                trades[index].exchange = kline.exchange.clone();
-                                trades[index].symbol = kline.symbol.clone();
-                                trades[index].direction = direction;
-                                trades[index].enter_position = Some(Position {
-                                    price,
-                                    entry_bar_index: 0,
-                                    size: 1.0,
-                                });
-                                trades[index].enter_time = kline.close_time_ms as i64;
-                                bd_tx.send(BoardcastMsg::Trade(kline, trades[index].clone())).unwrap();
-                            }
-                            Signal::Exit { reason, price } => {
-                                if trades[index].enter_position.is_none() {
-                                    error!("暂未入场，不处理该信号");
-                                    continue;
-                                }
-                                // 更新交易方向
-                                match trades[index].direction {
-                                    Direction::Long => {
-                                        trades[index].direction = Direction::LongClose;
-                                    },
-                                    Direction::Short => {
-                                        trades[index].direction = Direction::ShortClose;
-                                    },
-                                    _ => {},
-                                }
-                                trades[index].exit_position = Some(Position {
-                                    price: price,
-                                    entry_bar_index: 0,
-                                    size: 1.0,
-                                });
-                                trades[index].exit_reason = reason;
-                                trades[index].exit_time = kline.close_time_ms as i64;
-                                trades[index].calculate();
-                                bd_tx.send(BoardcastMsg::Trade(kline, trades[index].clone())).unwrap();
-                                // 出场后重置交易信息
-                                trades[index] = Trade::default();
-                            }
-                        }
+                ChannelMsg::Kline(KlineEvent { index, kline, .. }) => {
+                    // 产生信号时由 runner 根据当前的trade情况来进行判断
+                    let runner = runners
+                        .entry(index)
+                        .or_insert_with(|| registry.create(STRATEGY, params.clone()).expect("params validated"));
+                    if let Some(trade) = runner.on_bar(&kline) {
+                        bd_tx.send(BoardcastMsg::Trade(kline, trade)).unwrap();
                     }
                 }
                 ChannelMsg::Ping(ping) => {
@@ -154,12 +157,15 @@ async fn main() -> anyhow::Result<()> {
                 ChannelMsg::Error(error) => {
                     bd_tx.send(BoardcastMsg::Error(error)).unwrap();
                 },
+                ChannelMsg::Status(status) => {
+                    bd_tx.send(BoardcastMsg::Status(status)).unwrap();
+                },
             }
         }
     });
 
     boardcast(json!({
-        "策略名": "Bandtastic Strategy",
+        "策略名": STRATEGY,
         "消息": "开始计算策略",
         "当前时间": Utc::now().timestamp_millis(),
     })).await?;
@@ -172,7 +178,7 @@ async fn main() -> anyhow::Result<()> {
         match msg {
             BoardcastMsg::Trade(kline, trade) => {
                 let msg = json!({
-                    "策略名": "Bandtastic Strategy",
+                    "策略名": STRATEGY,
                     "标的名": kline.symbol,
                     "交易信息":  format!("{:?}", trade),
                     "当前时间": dt.format("%Y%m%d-%H:%M.%S").to_string(),
@@ -184,7 +190,7 @@ async fn main() -> anyhow::Result<()> {
             },
             BoardcastMsg::Ping(ping) => {
                 let msg = json!({
-                    "策略名": "Bandtastic Strategy",
+                    "策略名": STRATEGY,
                     "ping": ping,
                     "当前时间": dt.format("%Y%m%d-%H:%M.%S").to_string(),
                 });
@@ -195,26 +201,20 @@ async fn main() -> anyhow::Result<()> {
             BoardcastMsg::Error(error) => {
                 error!("Error: {:?}", error);
             }
+            BoardcastMsg::Status(status) => {
+                // 行情断开/重连通知
+                let msg = json!({
+                    "策略名": STRATEGY,
+                    "行情状态": status,
+                    "当前时间": dt.format("%Y%m%d-%H:%M.%S").to_string(),
+                });
+                if let Err(e)  = boardcast(msg).await {
+                    error!("Failed to boardcast status: {:?}", e);
+                }
+            }
         };
     }
 
-    // 配置文件写入器
-    let writer_type = WriterType::File(FileWriterConfig {
-        base_path: data_dir,
-        rotation_interval: 8 * 3600, // 8小时轮转一次
-    });
-    
-    let writer = create_writer(writer_type)?;
-    info!("开始写入K线数据");
-    while let Ok(msg) = rx.recv() {
-        match msg {
-            ChannelMsg::Kline(kline) => {
-                writer.write(&kline).await?;
-                writer.flush().await?;
-            },
-            _ => {}
-        };
-    }
-
+    write_task.await??;
     Ok(())
 } 
\ No newline at end of file
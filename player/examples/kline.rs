@@ -0,0 +1,77 @@
+use cex_core::{writer::{create_writer, FileWriterConfig, ShmemWriterConfig, WriterType}, ChannelMsg, KlineEvent, KlineInterval};
+use player::{create_source, default_exchange};
+use serde::Deserialize;
+use tracing::{error, info};
+use std::{path::PathBuf, fs};
+use tracing_subscriber::fmt::format::FmtSpan;
+
+// 配置
+#[derive(Debug, Deserialize)]
+struct Config {
+    #[serde(default = "default_exchange")]
+    exchange: String,
+    output_dir: String,
+    sub_list: Vec<(String, KlineInterval)>,
+    /// 配置后同时把K线发布到该共享内存
+    #[serde(default)]
+    shmem_name: Option<String>,
+    #[serde(default = "default_shmem_size")]
+    shmem_size: usize,
+}
+
+fn default_shmem_size() -> usize {
+    4 * 1024 * 1024
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // 初始化日志
+    tracing_subscriber::fmt()
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+
+    let config = toml::from_str::<Config>(&fs::read_to_string("sub.toml")?)?;
+    
+    // 确保数据目录存在
+    let data_dir = PathBuf::from(config.output_dir);
+    fs::create_dir_all(&data_dir)?;
+
+
+    // 配置文件写入器
+    let mut writer_type = WriterType::File(FileWriterConfig {
+        base_path: data_dir,
+        rotation_interval: 8 * 3600, // 8小时轮转一次
+        ..Default::default()
+    });
+    // 同时写文件和共享内存
+    if let Some(shmem_name) = config.shmem_name {
+        writer_type = WriterType::Multi(vec![
+            writer_type,
+            WriterType::Shmem(ShmemWriterConfig {
+                symbol: String::new(),
+                shmem_size: config.shmem_size,
+                shmem_name,
+            }),
+        ]);
+    }
+
+    let pair_list = config.sub_list;
+    let (tx, rx) = crossbeam::channel::bounded(pair_list.len());
+    let source = create_source(&config.exchange)?;
+    source.subscribe(pair_list, tx).await?;
+
+    let writer = create_writer(writer_type)?;
+    info!("开始写入K线数据");
+    while let Ok(msg) = rx.recv() {
+        if let ChannelMsg::Kline(KlineEvent { kline, .. }) = msg {
+            // 只写入K线本身, 不含流编号（早期版本写入 [index, kline]）
+            if let Err(e) = writer.write(&kline).await {
+                error!("写入K线失败: {:#}", e);
+                continue;
+            }
+            info!("写入到文件: {:?}", kline);
+        }
+    }
+
+    writer.close().await
+} 
\ No newline at end of file
@@ -0,0 +1,19 @@
+use binance::BinanceSource;
+use cex_core::source::MarketDataSource;
+use okx::OkxSource;
+
+pub use strategies::{StrategyConfig, StrategyInstance};
+
+/// 默认交易所，配置中未填写 `exchange` 时使用
+pub fn default_exchange() -> String {
+    "binance".to_string()
+}
+
+/// 根据配置中的交易所名称创建行情数据源
+pub fn create_source(exchange: &str) -> anyhow::Result<Box<dyn MarketDataSource>> {
+    match exchange {
+        "binance" => Ok(Box::new(BinanceSource::new())),
+        "okx" => Ok(Box::new(OkxSource::new())),
+        _ => anyhow::bail!("不支持的交易所: {}", exchange),
+    }
+}
@@ -1,31 +1,71 @@
 use cex_core::{
-    structure::{Direction, Position, Signal, Trade},
-    writer::{create_writer, FileWriterConfig, WriterType},
-    CexError, ChannelMsg, Ping, SimpleKLine
+    aggregate::KlineAggregator,
+    structure::Trade,
+    writer::{create_writer_with_queue, FileWriterConfig, OverflowPolicy, QueueConfig, ShmemWriterConfig, WriterType},
+    source::SubscribeOptions,
+    CexError, ChannelMsg, ConnectionStatus, KlineEvent, KlineInterval, Ping, SimpleKLine
 };
-use binance::subscribe_binance;
+use player::{create_source, default_exchange, StrategyConfig, StrategyInstance};
 
 use chrono::Utc;
 use serde::Deserialize;
 use serde_json::json;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use std::{path::PathBuf, fs};
 use tracing_subscriber::fmt::format::FmtSpan;
-use tracing_appender;
-use strategies::{bandtastic::BandtasticStrategy, MultiTimeFrameMacdStrategy, Strategy};
+use strategies::StrategyRegistry;
 
 // 配置
 #[derive(Debug, Deserialize)]
 struct Config {
+    #[serde(default = "default_exchange")]
+    exchange: String,
     output_dir: String,
+    /// 按 {exchange}/{symbol}/{interval} 等模板分目录写入, 不配置时写入同一目录
+    #[serde(default)]
+    partition: Option<String>,
     webhook_url: Vec<String>,
-    sub_list: Vec<(String, String)>,
+    /// (symbol, interval), 不支持的周期在加载配置时报错
+    sub_list: Vec<(String, KlineInterval)>,
+    /// 同时推送未收盘K线, 供策略在K线内部响应; 不能与 resample 同时配置
+    #[serde(default)]
+    partial: bool,
+    /// 用订阅的K线合成这些周期交给策略, 如 ["1h", "4h"]; 配置后策略只收到合成的K线
+    #[serde(default)]
+    resample: Vec<KlineInterval>,
+    /// 合成时按 UTC+N 的日期对齐, 默认 0 与 binance 的日线一致
+    #[serde(default)]
+    resample_utc_offset: i32,
+    /// 配置后同时把K线发布到该共享内存
+    #[serde(default)]
+    shmem_name: Option<String>,
+    #[serde(default = "default_shmem_size")]
+    shmem_size: usize,
+    /// 写入队列容量, 队列满时按 queue_overflow 处理: block / drop_oldest / error
+    #[serde(default = "default_queue_capacity")]
+    queue_capacity: usize,
+    #[serde(default)]
+    queue_overflow: OverflowPolicy,
+    /// 运行的策略实例, 见 [`StrategyConfig`]
+    #[serde(default)]
+    strategy: Vec<StrategyConfig>,
 }
 
+fn default_queue_capacity() -> usize {
+    QueueConfig::default().capacity
+}
+
+fn default_shmem_size() -> usize {
+    4 * 1024 * 1024
+}
+
+#[allow(clippy::large_enum_variant)]
 enum BoardcastMsg {
     Ping(Ping),
-    Trade(SimpleKLine, Trade),
+    /// (策略名, K线, 交易)
+    Trade(String, SimpleKLine, Trade),
     Error(CexError),
+    Status(ConnectionStatus),
 }
 
 
@@ -44,19 +84,37 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let config = toml::from_str::<Config>(&fs::read_to_string("sub.toml")?)?;
+    // 合成的周期只在基础K线收盘时更新, 没有未收盘的K线可以推送
+    anyhow::ensure!(config.resample.is_empty() || !config.partial, "partial 不能与 resample 同时配置");
+    anyhow::ensure!(config.queue_capacity > 0, "queue_capacity 必须大于 0");
 
-    let params = json!({
-        "fast_length": 12,
-        "slow_length": 26,
-        "signal_length": 9,
-        "short_trend_time": "60m",
-        "long_trend_time": "240m",
-        "stop_loss_perc": 1.9,
-        "take_profit_perc": 5.4,
-        "breakeven_threshold": 1.0,
-        "trail_offset": 0.5,
-    });
-    let strategy: MultiTimeFrameMacdStrategy = serde_json::from_value(params).unwrap();
+    // 策略收到的K线: 配置 resample 时只有合成的周期
+    let mut inputs = Vec::new();
+    if config.resample.is_empty() {
+        inputs.clone_from(&config.sub_list);
+    } else {
+        let aggregator = KlineAggregator::new(&config.resample).with_utc_offset(config.resample_utc_offset);
+        for (symbol, base) in &config.sub_list {
+            for target in aggregator.targets() {
+                // 同一交易对的多个基础周期合成同一个周期时只算一次
+                let input = (symbol.clone(), *target);
+                if aggregator.can_aggregate(*base, *target) && !inputs.contains(&input) {
+                    inputs.push(input);
+                }
+            }
+        }
+    }
+    let registry = StrategyRegistry::default();
+    let mut instances = Vec::new();
+    for strategy in &config.strategy {
+        strategy.check_inputs(&inputs)?;
+        instances.push(StrategyInstance::new(&registry, strategy)?);
+    }
+    if instances.is_empty() {
+        warn!("未配置策略, 只写入K线数据");
+    }
+    // 行情状态等不属于单个策略的消息使用全部策略名
+    let strategy_names = instances.iter().map(|s| s.label()).collect::<Vec<_>>().join(", ");
 
     // 确保数据目录存在
     let data_dir = PathBuf::from(config.output_dir);
@@ -78,66 +136,87 @@ async fn main() -> anyhow::Result<()> {
     let pair_list = config.sub_list;
     let p_len: usize = pair_list.len();
     let (tx, rx) = crossbeam::channel::bounded(p_len);
-    tokio::spawn(async move { subscribe_binance(pair_list, tx).await });
+    let source = create_source(&config.exchange)?;
+    source.subscribe_with(pair_list, SubscribeOptions { partial: config.partial }, tx).await?;
+
+    // 配置文件写入器
+    let mut writer_type = WriterType::File(FileWriterConfig {
+        base_path: data_dir,
+        rotation_interval: 8 * 3600, // 8小时轮转一次
+        partition: config.partition.clone(),
+        ..Default::default()
+    });
+    // 同时写文件和共享内存
+    if let Some(shmem_name) = config.shmem_name.clone() {
+        writer_type = WriterType::Multi(vec![
+            writer_type,
+            WriterType::Shmem(ShmemWriterConfig {
+                symbol: String::new(),
+                shmem_size: config.shmem_size,
+                shmem_name,
+            }),
+        ]);
+    }
+    let writer = create_writer_with_queue(writer_type, QueueConfig {
+        capacity: config.queue_capacity,
+        overflow: config.queue_overflow,
+    })?;
+
+    // 行情消息分发到策略线程和写入任务, 各自使用独立的通道
+    let (st_tx, st_rx) = crossbeam::channel::bounded(p_len);
+    let (wr_tx, mut wr_rx) = tokio::sync::mpsc::channel::<SimpleKLine>(p_len);
+    std::thread::spawn(move || {
+        while let Ok(msg) = rx.recv() {
+            // 只归档已收盘的K线
+            if let ChannelMsg::Kline(KlineEvent { kline, is_final: true, .. }) = &msg
+                && wr_tx.blocking_send(kline.clone()).is_err()
+            {
+                error!("写入任务已退出");
+            }
+            // 策略线程退出后仍继续写入
+            let _ = st_tx.send(msg);
+        }
+    });
+
+    let write_task = tokio::spawn(async move {
+        info!("开始写入K线数据");
+        let mut count = 0u64;
+        while let Some(kline) = wr_rx.recv().await {
+            // 各写入器的错误在后台记录并计入 metrics, 这里只有队列满（queue_overflow = error）或写入线程退出时出错
+            if let Err(e) = writer.write(&kline).await {
+                error!("写入K线失败: {:#}", e);
+            }
+            count += 1;
+            if count.is_multiple_of(1000) {
+                info!("写入器状态: {:?}", writer.metrics());
+            }
+        }
+        writer.close().await
+    });
 
     let (bd_tx, bd_rx) = crossbeam::channel::bounded(p_len);
 
-    // index 0 不存在, 需要多创建一个
-    let mut strategies = (0..p_len + 1).map(|_| {strategy.clone()}).collect::<Vec<MultiTimeFrameMacdStrategy>>();
-    let mut trades = (0..p_len + 1).map(|_| {Trade::default()}).collect::<Vec<Trade>>();
+    let mut aggregator = (!config.resample.is_empty())
+        .then(|| KlineAggregator::new(&config.resample).with_utc_offset(config.resample_utc_offset));
 
 
-    let st_rx = rx.clone();
     std::thread::spawn(move || {
         info!("开始计算策略");
         while let Ok(msg) = st_rx.recv() {
             match msg {
-                ChannelMsg::Kline((index, kline)) => {
-                    // 如果产生信号，需要根据当前的trade情况来进行判断
-                    if let Some(signal) = strategies[index].next(kline.clone()) {
-                        match signal {
-                            Signal::Enter { direction, price } => {
-                                if trades[index].enter_position.is_some() {
-                                    error!("入场时已有持仓，该策略不支持重复入场");
-                                    continue;
-                                }
-                                trades[index].exchange = kline.exchange.clone();
-                                trades[index].symbol = kline.symbol.clone();
-                                trades[index].direction = direction;
-                                trades[index].enter_position = Some(Position {
-                                    price,
-                                    entry_bar_index: 0,
-                                    size: 1.0,
-                                });
-                                trades[index].enter_time = Utc::now().timestamp_millis();
-                                bd_tx.send(BoardcastMsg::Trade(kline, trades[index].clone())).unwrap();
-                            }
-                            Signal::Exit { reason, price } => {
-                                if trades[index].enter_position.is_none() {
-                                    error!("暂未入场，不处理该信号");
-                                    continue;
-                                }
-                                // 更新交易方向
-                                match trades[index].direction {
-                                    Direction::Long => {
-                                        trades[index].direction = Direction::LongClose;
-                                    },
-                                    Direction::Short => {
-                                        trades[index].direction = Direction::ShortClose;
-                                    },
-                                    _ => {},
-                                }
-                                trades[index].exit_position = Some(Position {
-                                    price: price,
-                                    entry_bar_index: 0,
-                                    size: 1.0,
-                                });
-                                trades[index].exit_reason = reason;
-                                trades[index].exit_time = Utc::now().timestamp_millis();
-                                trades[index].calculate();
-                                bd_tx.send(BoardcastMsg::Trade(kline, trades[index].clone())).unwrap();
-                                // 出场后重置交易信息
-                                trades[index] = Trade::default();
+                ChannelMsg::Kline(KlineEvent { kline, is_final, .. }) => {
+                    // 按交易对和周期交给对应的策略实例, 未收盘的更新不参与合成（配置 resample 时不订阅）
+                    let klines = match aggregator.as_mut() {
+                        Some(aggregator) if is_final => aggregator.push(&kline),
+                        Some(_) => continue,
+                        None => vec![kline],
+                    };
+                    for kline in klines {
+                        for instance in instances.iter_mut() {
+                            // 产生信号时由 runner 根据当前的trade情况来进行判断
+                            if let Some(trade) = instance.on_kline(&kline, is_final) {
+                                let label = instance.label().to_string();
+                                bd_tx.send(BoardcastMsg::Trade(label, kline.clone(), trade)).unwrap();
                             }
                         }
                     }
@@ -148,12 +227,15 @@ async fn main() -> anyhow::Result<()> {
                 ChannelMsg::Error(error) => {
                     bd_tx.send(BoardcastMsg::Error(error)).unwrap();
                 },
+                ChannelMsg::Status(status) => {
+                    bd_tx.send(BoardcastMsg::Status(status)).unwrap();
+                },
             }
         }
     });
 
     boardcast(json!({
-        "策略名": "Bandtastic Strategy",
+        "策略名": strategy_names,
         "消息": "开始计算策略",
         "当前时间": Utc::now().timestamp_millis(),
     })).await?;
@@ -164,9 +246,9 @@ async fn main() -> anyhow::Result<()> {
             .unwrap()
             .with_timezone(&chrono::FixedOffset::east_opt(8 * 3600).unwrap());
         match msg {
-            BoardcastMsg::Trade(kline, trade) => {
+            BoardcastMsg::Trade(label, kline, trade) => {
                 let msg = json!({
-                    "策略名": "Bandtastic Strategy",
+                    "策略名": label,
                     "标的名": kline.symbol,
                     "交易信息":  format!("{:?}", trade),
                     "当前时间": dt.format("%Y%m%d-%H:%M.%S").to_string(),
@@ -178,7 +260,7 @@ async fn main() -> anyhow::Result<()> {
             },
             BoardcastMsg::Ping(ping) => {
                 let msg = json!({
-                    "策略名": "Bandtastic Strategy",
+                    "策略名": strategy_names,
                     "ping": ping,
                     "当前时间": dt.format("%Y%m%d-%H:%M.%S").to_string(),
                 });
@@ -189,26 +271,20 @@ async fn main() -> anyhow::Result<()> {
             BoardcastMsg::Error(error) => {
                 error!("Error: {:?}", error);
             }
+            BoardcastMsg::Status(status) => {
+                // 行情断开/重连通知
+                let msg = json!({
+                    "策略名": strategy_names,
+                    "行情状态": status,
+                    "当前时间": dt.format("%Y%m%d-%H:%M.%S").to_string(),
+                });
+                if let Err(e)  = boardcast(msg).await {
+                    error!("Failed to boardcast status: {:?}", e);
+                }
+            }
         };
     }
 
-    // 配置文件写入器
-    let writer_type = WriterType::File(FileWriterConfig {
-        base_path: data_dir,
-        rotation_interval: 8 * 3600, // 8小时轮转一次
-    });
-    
-    let writer = create_writer(writer_type)?;
-    info!("开始写入K线数据");
-    while let Ok(msg) = rx.recv() {
-        match msg {
-            ChannelMsg::Kline(kline) => {
-                writer.write(&kline).await?;
-                writer.flush().await?;
-            },
-            _ => {}
-        };
-    }
-
+    write_task.await??;
     Ok(())
 } 
\ No newline at end of file
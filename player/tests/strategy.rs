@@ -0,0 +1,106 @@
+use std::fs;
+
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+use player::{StrategyConfig, StrategyInstance};
+use serde::Deserialize;
+use strategies::StrategyRegistry;
+
+#[derive(Deserialize)]
+struct Sub {
+    strategy: Vec<StrategyConfig>,
+}
+
+fn parse(toml: &str) -> Vec<StrategyConfig> {
+    toml::from_str::<Sub>(toml).unwrap().strategy
+}
+
+fn kline(symbol: &str, interval: KlineInterval, i: u64) -> SimpleKLine {
+    let ms = interval.duration_ms();
+    let price = Decimal::from(100 + i);
+    SimpleKLine::new("binance", symbol, i * ms, (i + 1) * ms - 1, interval, price, price, price, price, Decimal::ONE, 1)
+}
+
+#[test]
+fn sub_toml_strategies() {
+    let configs = parse(&fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/sub.toml")).unwrap());
+    let registry = StrategyRegistry::default();
+    for config in &configs {
+        let instance = StrategyInstance::new(&registry, config).unwrap();
+        assert_eq!(instance.label(), config.label());
+        assert!(instance.runner("BTCUSDT").is_some());
+    }
+    assert_eq!(configs[0].name, "multi_time_frame_macd");
+}
+
+#[test]
+fn routes_by_symbol_and_interval() {
+    let configs = parse(
+        r#"
+        [[strategy]]
+        name = "bandtastic"
+        symbols = ["btcusdt", "ethusdt"]
+        intervals = ["15m"]
+
+        [strategy.params]
+        buy_fast_ema_period = 2
+        buy_slow_ema_period = 3
+        buy_rsi_threshold = 50.0
+        buy_mfi_threshold = 30.0
+        buy_rsi_enabled = true
+        buy_mfi_enabled = true
+        buy_ema_enabled = true
+        buy_trigger = "bb_lower1"
+        sell_fast_ema_period = 2
+        sell_slow_ema_period = 3
+        sell_rsi_threshold = 57.0
+        sell_mfi_threshold = 46.0
+        sell_rsi_enabled = false
+        sell_mfi_enabled = true
+        sell_ema_enabled = true
+        sell_trigger = "sell-bb_upper2"
+        min_roi = [[0, 0.162], [69, 0.097], [229, 0.061], [566, 0.0]]
+        stoploss = -0.345
+        trailing_stop = true
+        trailing_stop_positive = 0.01
+        trailing_stop_positive_offset = 0.058
+        trailing_only_offset_is_reached = false
+        "#,
+    );
+    let config = &configs[0];
+    assert_eq!(config.label(), "bandtastic");
+
+    let inputs = [("BTCUSDT".to_string(), KlineInterval::FifteenMinutes)];
+    assert!(config.check_inputs(&inputs).is_err());
+    let inputs = [
+        ("BTCUSDT".to_string(), KlineInterval::FifteenMinutes),
+        ("ethusdt".to_string(), KlineInterval::FifteenMinutes),
+    ];
+    config.check_inputs(&inputs).unwrap();
+
+    let mut instance = StrategyInstance::new(&StrategyRegistry::default(), config).unwrap();
+    let warmup = instance.runner("btcusdt").unwrap().warmup_bars();
+    for i in 0..warmup as u64 {
+        instance.on_kline(&kline("BTCUSDT", KlineInterval::FifteenMinutes, i), true);
+        // 其它周期和交易对不影响 BTCUSDT 的实例
+        instance.on_kline(&kline("BTCUSDT", KlineInterval::OneHour, i), true);
+        instance.on_kline(&kline("SOLUSDT", KlineInterval::FifteenMinutes, i), true);
+    }
+    assert!(!instance.runner("BTCUSDT").unwrap().is_started());
+    instance.on_kline(&kline("BTCUSDT", KlineInterval::FifteenMinutes, warmup as u64), true);
+    assert!(instance.runner("BTCUSDT").unwrap().is_started());
+    assert!(!instance.runner("ETHUSDT").unwrap().is_started());
+    assert!(instance.runner("SOLUSDT").is_none());
+}
+
+#[test]
+fn rejects_bad_config() {
+    let registry = StrategyRegistry::default();
+    let unknown = parse("[[strategy]]\nname = \"nope\"\nsymbols = [\"btcusdt\"]\nintervals = [\"1h\"]\nparams = {}\n");
+    assert!(StrategyInstance::new(&registry, &unknown[0]).is_err());
+
+    let bad_params = parse("[[strategy]]\nname = \"bandtastic\"\nsymbols = [\"btcusdt\"]\nintervals = [\"1h\"]\nparams = { buy_trigger = \"bb_lower1\" }\n");
+    assert!(StrategyInstance::new(&registry, &bad_params[0]).is_err());
+
+    // 配置项拼写错误
+    assert!(toml::from_str::<Sub>("[[strategy]]\nname = \"bandtastic\"\nsymbol = [\"btcusdt\"]\nintervals = [\"1h\"]\nparams = {}\n").is_err());
+}
@@ -1,4 +1,5 @@
-use strategies::MultiTimeFrameMacdStrategy;
+use cex_core::KlineInterval;
+use strategies::{MultiTimeFrameMacdStrategy, Strategy};
 use serde_json::json;
 
 fn main() {
@@ -6,8 +7,8 @@ fn main() {
     let fast_length: usize = 12;
     let slow_length = 26;
     let signal_length = 9;
-    let short_trend_time = "60m".to_string();
-    let long_trend_time = "240m".to_string();
+    let short_trend_time = KlineInterval::OneHour;
+    let long_trend_time = KlineInterval::FourHours;
     let stop_loss_perc = 1.9;
     let take_profit_perc = 5.4;
     let breakeven_threshold = 1.0;
@@ -24,20 +25,6 @@ fn main() {
         "breakeven_threshold": breakeven_threshold,
         "trail_offset": trail_offset,
     });
-    let strategy1: MultiTimeFrameMacdStrategy = serde_json::from_value(params).unwrap();
-
-    // Create a new instance of the MultiTimeFrameMacdStrategy
-    let mut _strategy = MultiTimeFrameMacdStrategy::new(
-        fast_length,
-        slow_length,
-        signal_length,
-        short_trend_time,
-        long_trend_time,
-        stop_loss_perc,
-        take_profit_perc,
-        breakeven_threshold,
-        trail_offset,
-    );
-    println!("strategy1: {:?}", strategy1);
-    println!("strategy2: {:?}", _strategy);
-}
\ No newline at end of file
+    let strategy = MultiTimeFrameMacdStrategy::from_value(params).unwrap();
+    println!("strategy: {:?}", strategy);
+}
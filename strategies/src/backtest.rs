@@ -0,0 +1,104 @@
+use std::cmp::Reverse;
+
+use anyhow::Result;
+use cex_core::reader::{FileReader, KlineFilter};
+use cex_core::structure::Trade;
+use cex_core::SimpleKLine;
+
+use crate::StrategyInstance;
+
+/// 回测统计
+#[derive(Debug, Clone, Default)]
+pub struct BacktestStats {
+    /// 已平仓交易数
+    pub total_trades: usize,
+    /// 按交易回报率复利计算的总收益（百分比）
+    pub total_return: f64,
+    /// 胜率 (0~1)
+    pub win_rate: f64,
+    /// 按平仓后净值计算的最大回撤（百分比）
+    pub max_drawdown: f64,
+    /// 以单笔交易回报率计算的夏普比率（未年化, 无风险收益按 0）
+    pub sharpe: f64,
+    /// 平均持仓时间（毫秒）
+    pub avg_holding_ms: f64,
+}
+
+/// 回测结果
+#[derive(Debug, Clone, Default)]
+pub struct BacktestReport {
+    /// 已平仓的交易, 按平仓时间排序
+    pub trades: Vec<Trade>,
+    /// 回测结束时仍未平仓的交易
+    pub open_trades: Vec<Trade>,
+    pub stats: BacktestStats,
+}
+
+/// 读取 FileWriter 写入的归档, 按时间顺序合并多个交易对/周期
+pub fn load_klines(reader: &FileReader, filter: &KlineFilter) -> Result<Vec<SimpleKLine>> {
+    let mut klines = reader.read(filter)?.collect::<Vec<_>>();
+
+    // 按收盘时间合并, 同时收盘时周期短的在前; 排序键包含完整的K线标识, 重复的记录相邻
+    klines.sort_by(|a, b| {
+        let key = |k: &SimpleKLine| (k.close_time_ms, Reverse(k.open_time_ms), k.exchange.clone(), k.symbol.clone(), k.interval);
+        key(a).cmp(&key(b))
+    });
+    klines.dedup_by(|a, b| {
+        a.exchange == b.exchange && a.symbol == b.symbol && a.interval == b.interval && a.open_time_ms == b.open_time_ms
+    });
+    Ok(klines)
+}
+
+/// 按时间顺序把已收盘K线交给策略实例, 与实时运行端使用同一个 [`StrategyInstance`]:
+/// 按交易对和周期路由, 先收集 `warmup_bars` 根K线预热, 信号按 [`Trade::on_signal`] 处理
+pub fn run_backtest<I>(instance: &mut StrategyInstance, klines: I) -> BacktestReport
+where
+    I: IntoIterator<Item = SimpleKLine>,
+{
+    let mut trades = Vec::new();
+    for kline in klines {
+        if let Some(snapshot) = instance.on_kline(&kline, true)
+            && snapshot.exit_position.is_some()
+        {
+            trades.push(snapshot);
+        }
+    }
+
+    let open_trades = instance
+        .runners()
+        .map(|(_, runner)| runner.trade().clone())
+        .filter(|trade| trade.enter_position.is_some())
+        .collect();
+    let stats = calculate_stats(&trades);
+    BacktestReport { trades, open_trades, stats }
+}
+
+pub fn calculate_stats(trades: &[Trade]) -> BacktestStats {
+    if trades.is_empty() {
+        return BacktestStats::default();
+    }
+    // Trade::roi 为扣除手续费后的百分比
+    let returns = trades.iter().map(|t| t.roi.unwrap_or(0.0) / 100.0).collect::<Vec<_>>();
+    let n = returns.len() as f64;
+
+    let mut equity = 1.0f64;
+    let mut peak = 1.0f64;
+    let mut max_drawdown = 0.0f64;
+    for r in &returns {
+        equity *= 1.0 + r;
+        peak = peak.max(equity);
+        max_drawdown = max_drawdown.max((peak - equity) / peak);
+    }
+
+    let mean = returns.iter().sum::<f64>() / n;
+    let std = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n).sqrt();
+
+    BacktestStats {
+        total_trades: trades.len(),
+        total_return: (equity - 1.0) * 100.0,
+        win_rate: returns.iter().filter(|r| **r > 0.0).count() as f64 / n,
+        max_drawdown: max_drawdown * 100.0,
+        sharpe: if std > 0.0 { mean / std } else { 0.0 },
+        avg_holding_ms: trades.iter().map(|t| (t.exit_time - t.enter_time) as f64).sum::<f64>() / n,
+    }
+}
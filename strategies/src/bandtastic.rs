@@ -1,20 +1,83 @@
 use ta::{
     indicators::{BollingerBands, ExponentialMovingAverage, MoneyFlowIndex, RelativeStrengthIndex},
-    Next,
+    Next, Period,
 };
 use ta::DataItem; // The struct that already implements these traits
 use std::collections::VecDeque;
-use cex_core::SimpleKLine;
+use cex_core::{KlineInterval, SimpleKLine};
 use cex_core::structure::Signal;
-use cex_core::structure::Position;
 use cex_core::structure::Direction;
 use cex_core::structure::ExitReason;
+use anyhow::{ensure, Result};
 use tracing::error;
 use serde::{Deserialize, Serialize};
 
-use crate::Strategy;
+use crate::{Strategy, StrategyContext, StrategyParams};
 
+const BUY_TRIGGERS: &[&str] = &["bb_lower1", "bb_lower2", "bb_lower3", "bb_lower4"];
+const SELL_TRIGGERS: &[&str] = &["sell-bb_upper1", "sell-bb_upper2", "sell-bb_upper3", "sell-bb_upper4"];
+
+/// [`BandtasticStrategy`] 的参数, 全部必填, 未知字段报错
 #[derive(Clone, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BandtasticParams {
+    pub buy_fast_ema_period: usize,
+    pub buy_slow_ema_period: usize,
+    pub buy_rsi_threshold: f64,
+    pub buy_mfi_threshold: f64,
+    pub buy_rsi_enabled: bool,
+    pub buy_mfi_enabled: bool,
+    pub buy_ema_enabled: bool,
+    /// bb_lower1 ~ bb_lower4
+    pub buy_trigger: String,
+    pub sell_fast_ema_period: usize,
+    pub sell_slow_ema_period: usize,
+    pub sell_rsi_threshold: f64,
+    pub sell_mfi_threshold: f64,
+    pub sell_rsi_enabled: bool,
+    pub sell_mfi_enabled: bool,
+    pub sell_ema_enabled: bool,
+    /// sell-bb_upper1 ~ sell-bb_upper4
+    pub sell_trigger: String,
+    /// (minutes, percentage), 如 [[0, 0.162], [69, 0.097], [229, 0.061], [566, 0.0]]
+    pub min_roi: Vec<(usize, f64)>,
+    /// 止损比例, 为负数, 如 -0.345
+    pub stoploss: f64,
+    pub trailing_stop: bool,
+    pub trailing_stop_positive: f64,
+    pub trailing_stop_positive_offset: f64,
+    pub trailing_only_offset_is_reached: bool,
+}
+
+impl StrategyParams for BandtasticParams {
+    fn validate(&self) -> Result<()> {
+        for (name, period) in [
+            ("buy_fast_ema_period", self.buy_fast_ema_period),
+            ("buy_slow_ema_period", self.buy_slow_ema_period),
+            ("sell_fast_ema_period", self.sell_fast_ema_period),
+            ("sell_slow_ema_period", self.sell_slow_ema_period),
+        ] {
+            ensure!(period > 0, "{} must be positive", name);
+        }
+        for (name, threshold) in [
+            ("buy_rsi_threshold", self.buy_rsi_threshold),
+            ("buy_mfi_threshold", self.buy_mfi_threshold),
+            ("sell_rsi_threshold", self.sell_rsi_threshold),
+            ("sell_mfi_threshold", self.sell_mfi_threshold),
+        ] {
+            ensure!((0.0..=100.0).contains(&threshold), "{} must be in [0, 100]: {}", name, threshold);
+        }
+        ensure!(BUY_TRIGGERS.contains(&self.buy_trigger.as_str()), "unknown buy_trigger: {}", self.buy_trigger);
+        ensure!(SELL_TRIGGERS.contains(&self.sell_trigger.as_str()), "unknown sell_trigger: {}", self.sell_trigger);
+        ensure!(self.stoploss > -1.0 && self.stoploss <= 0.0, "stoploss must be in (-1, 0]: {}", self.stoploss);
+        ensure!(self.trailing_stop_positive >= 0.0, "trailing_stop_positive must not be negative");
+        ensure!(self.trailing_stop_positive_offset >= 0.0, "trailing_stop_positive_offset must not be negative");
+        Ok(())
+    }
+}
+
+/// 通过 [`BandtasticParams`] 创建（`Strategy::from_params` 或 `TryFrom`）
+#[derive(Clone, Debug)]
 pub struct BandtasticStrategy {
     // Buy parameters
     buy_rsi_threshold: f64,
@@ -41,118 +104,106 @@ pub struct BandtasticStrategy {
     trailing_only_offset_is_reached: bool,
     
     // Indicators
-    #[serde(skip)]
     rsi: RelativeStrengthIndex,
-    #[serde(skip)]
     mfi: MoneyFlowIndex,
-    #[serde(skip)]
     bb1: BollingerBands,
-    #[serde(skip)]
     bb2: BollingerBands,
-    #[serde(skip)]
     bb3: BollingerBands,
-    #[serde(skip)]
     bb4: BollingerBands,
-    #[serde(skip)]
     buy_fast_ema: ExponentialMovingAverage,
-    #[serde(skip)]
     buy_slow_ema: ExponentialMovingAverage,
-    #[serde(skip)]
     sell_fast_ema: ExponentialMovingAverage,
-    #[serde(skip)]
     sell_slow_ema: ExponentialMovingAverage,
     
-    // State
-    #[serde(skip)]
-    position: Option<Position>,
-    #[serde(skip)]
-    bars_since_entry: usize,
-    #[serde(skip)]
-    bar_index: usize,
-    #[serde(skip)]
+    // State, 持仓从 StrategyContext 读取
     price_history: VecDeque<f64>,
 }
 
 impl BandtasticStrategy {
-    pub fn new(
-        buy_fast_ema_period: usize,
-        buy_slow_ema_period: usize,
-        buy_rsi_threshold: f64,
-        buy_mfi_threshold: f64,
-        buy_rsi_enabled: bool,
-        buy_mfi_enabled: bool,
-        buy_ema_enabled: bool,
-        buy_trigger: String,
-        sell_fast_ema_period: usize,
-        sell_slow_ema_period: usize,
-        sell_rsi_threshold: f64,
-        sell_mfi_threshold: f64,
-        sell_rsi_enabled: bool,
-        sell_mfi_enabled: bool,
-        sell_ema_enabled: bool,
-        sell_trigger: String,
-    ) -> Self {
+    /// 参数须已通过 [`StrategyParams::validate`], 外部通过 `TryFrom` 或 `Strategy::from_params` 创建
+    fn new(params: BandtasticParams) -> Result<Self> {
         // Initialize indicators with default periods (can be adjusted)
         let rsi_period = 14;
         let mfi_period = 14;
         let bb_period = 20;
         
-        BandtasticStrategy {
-            buy_rsi_threshold,
-            buy_mfi_threshold,
-            buy_rsi_enabled,
-            buy_mfi_enabled,
-            buy_ema_enabled,
-            buy_trigger,
-            sell_rsi_threshold,
-            sell_mfi_threshold,
-            sell_rsi_enabled,
-            sell_mfi_enabled,
-            sell_ema_enabled,
-            sell_trigger,
+        Ok(BandtasticStrategy {
+            buy_rsi_threshold: params.buy_rsi_threshold,
+            buy_mfi_threshold: params.buy_mfi_threshold,
+            buy_rsi_enabled: params.buy_rsi_enabled,
+            buy_mfi_enabled: params.buy_mfi_enabled,
+            buy_ema_enabled: params.buy_ema_enabled,
+            buy_trigger: params.buy_trigger,
+            sell_rsi_threshold: params.sell_rsi_threshold,
+            sell_mfi_threshold: params.sell_mfi_threshold,
+            sell_rsi_enabled: params.sell_rsi_enabled,
+            sell_mfi_enabled: params.sell_mfi_enabled,
+            sell_ema_enabled: params.sell_ema_enabled,
+            sell_trigger: params.sell_trigger,
             
             // ROI table (minutes, percentage)
-            min_roi: vec![
-                (0, 0.162),
-                (69, 0.097),
-                (229, 0.061),
-                (566, 0.0),
-            ],
-            stoploss: -0.345,
-            trailing_stop: true,
-            trailing_stop_positive: 0.01,
-            trailing_stop_positive_offset: 0.058,
-            trailing_only_offset_is_reached: false,
+            min_roi: params.min_roi,
+            stoploss: params.stoploss,
+            trailing_stop: params.trailing_stop,
+            trailing_stop_positive: params.trailing_stop_positive,
+            trailing_stop_positive_offset: params.trailing_stop_positive_offset,
+            trailing_only_offset_is_reached: params.trailing_only_offset_is_reached,
             
             // Indicators
-            rsi: RelativeStrengthIndex::new(rsi_period).unwrap(),
-            mfi: MoneyFlowIndex::new(mfi_period).unwrap(),
-            bb1: BollingerBands::new(bb_period, 1.0).unwrap(),
-            bb2: BollingerBands::new(bb_period, 2.0).unwrap(),
-            bb3: BollingerBands::new(bb_period, 3.0).unwrap(),
-            bb4: BollingerBands::new(bb_period, 4.0).unwrap(),
-            buy_fast_ema: ExponentialMovingAverage::new(buy_fast_ema_period).unwrap(),
-            buy_slow_ema: ExponentialMovingAverage::new(buy_slow_ema_period).unwrap(),
-            sell_fast_ema: ExponentialMovingAverage::new(sell_fast_ema_period).unwrap(),
-            sell_slow_ema: ExponentialMovingAverage::new(sell_slow_ema_period).unwrap(),
+            rsi: RelativeStrengthIndex::new(rsi_period)?,
+            mfi: MoneyFlowIndex::new(mfi_period)?,
+            bb1: BollingerBands::new(bb_period, 1.0)?,
+            bb2: BollingerBands::new(bb_period, 2.0)?,
+            bb3: BollingerBands::new(bb_period, 3.0)?,
+            bb4: BollingerBands::new(bb_period, 4.0)?,
+            buy_fast_ema: ExponentialMovingAverage::new(params.buy_fast_ema_period)?,
+            buy_slow_ema: ExponentialMovingAverage::new(params.buy_slow_ema_period)?,
+            sell_fast_ema: ExponentialMovingAverage::new(params.sell_fast_ema_period)?,
+            sell_slow_ema: ExponentialMovingAverage::new(params.sell_slow_ema_period)?,
             
             // State
-            position: None,
-            bars_since_entry: 0,
-            bar_index: 0,
             price_history: VecDeque::new(),
-        }
+        })
+    }
+}
+
+impl TryFrom<BandtasticParams> for BandtasticStrategy {
+    type Error = anyhow::Error;
+
+    fn try_from(params: BandtasticParams) -> Result<Self> {
+        params.validate()?;
+        Self::new(params)
     }
 }
 
 impl Strategy for BandtasticStrategy {
-    fn next(&mut self, kline: SimpleKLine) -> Option<Signal> {
-        if kline.interval != "15m" {
+    type Params = BandtasticParams;
+
+    fn from_params(params: BandtasticParams) -> Result<Self> {
+        Self::try_from(params)
+    }
+
+    fn warmup_bars(&self) -> usize {
+        [
+            self.rsi.period(),
+            self.mfi.period(),
+            self.bb1.period(),
+            self.buy_fast_ema.period(),
+            self.buy_slow_ema.period(),
+            self.sell_fast_ema.period(),
+            self.sell_slow_ema.period(),
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or_default()
+    }
+
+    fn on_bar(&mut self, kline: &SimpleKLine, ctx: &StrategyContext) -> Option<Signal> {
+        if kline.interval != KlineInterval::FifteenMinutes {
             error!("非法的K线间隔,请检查行情输入");
             return None;
         }
-        let (open, high, low, close, volume) = (kline.open, kline.high, kline.low, kline.close, kline.volume);
-        self.bar_index += 1;
+        let (open, high, low, close, volume) = (kline.open_f64(), kline.high_f64(), kline.low_f64(), kline.close_f64(), kline.volume_f64());
         
          // Create a DataItem that implements all required traits
         let data_item = DataItem::builder()
@@ -185,12 +236,11 @@ impl Strategy for BandtasticStrategy {
             self.price_history.pop_front();
         }
         
-        // Update position tracking
-        if let Some(position) = &mut self.position {
-            self.bars_since_entry = self.bar_index - position.entry_bar_index;
-        } else {
-            self.bars_since_entry = 0;
-        }
+        // Update position tracking, 按开仓K线的收盘时间计算持仓的K线数
+        let position = ctx.position();
+        let bars_since_entry = position
+            .map_or(0, |_| kline.close_time_ms.saturating_sub(ctx.trade.enter_time as u64) / kline.interval.duration_ms())
+            as usize;
         
         // Generate signals
         let mut signal = None;
@@ -230,11 +280,11 @@ impl Strategy for BandtasticStrategy {
         let sell_signal = sell_condition1 && sell_condition2 && sell_condition3 && sell_condition4 && sell_condition5;
         
         // Check ROI exits
-        if let Some(position) = &self.position {
+        if let Some(position) = position {
             for (minutes, roi_percentage) in &self.min_roi {
                 // Assuming 15 minutes per bar (adjust according to your timeframe)
                 let bars_needed = minutes / 15;
-                if self.bars_since_entry >= bars_needed {
+                if bars_since_entry >= bars_needed {
                     let target_price = position.price * (1.0 + roi_percentage);
                     if close >= target_price {
                         signal = Some(Signal::Exit {
@@ -248,7 +298,7 @@ impl Strategy for BandtasticStrategy {
         }
         
         // Check stop loss
-        if let Some(position) = &self.position {
+        if let Some(position) = position {
             let stop_loss_price = position.price * (1.0 + self.stoploss);
             if close <= stop_loss_price {
                 signal = Some(Signal::Exit {
@@ -259,13 +309,13 @@ impl Strategy for BandtasticStrategy {
         }
         
         // Check trailing stop
-        if self.trailing_stop && self.position.is_some() {
-            let position = self.position.as_ref().unwrap();
+        if let Some(position) = position.filter(|_| self.trailing_stop) {
             let trail_offset = position.price * self.trailing_stop_positive_offset;
             let trail_activation = position.price * (1.0 + self.trailing_stop_positive);
             
             if !self.trailing_only_offset_is_reached || close > trail_activation {
-                if let Some(highest_price) = self.price_history.iter().max_by(|a, b| a.partial_cmp(b).unwrap()) {
+                let highest_price = self.price_history.iter().max_by(|a, b| a.partial_cmp(b).unwrap());
+                if let Some(highest_price) = highest_price {
                     let trail_price = highest_price - trail_offset;
                     if close <= trail_price {
                         signal = Some(Signal::Exit {
@@ -278,7 +328,7 @@ impl Strategy for BandtasticStrategy {
         }
         
         // Generate entry signals only if we don't have a position
-        if self.position.is_none() && buy_signal {
+        if position.is_none() && buy_signal {
             signal = Some(Signal::Enter {
                 direction: Direction::Long,
                 price: close,
@@ -286,29 +336,13 @@ impl Strategy for BandtasticStrategy {
         }
         
         // Generate exit signal if we have a position and sell conditions are met
-        if self.position.is_some() && sell_signal {
+        if position.is_some() && sell_signal {
             signal = Some(Signal::Exit {
                 reason: ExitReason::StopProfit,
                 price: close,
             });
         }
         
-        // Update position based on signal
-        if let Some(signal) = &signal {
-            match signal {
-                Signal::Enter { direction:_, price } => {
-                    self.position = Some(Position {
-                        price: *price,
-                        entry_bar_index: self.bar_index,
-                        size: 1.0, // Assuming full position size
-                    });
-                },
-                Signal::Exit { .. } => {
-                    self.position = None;
-                },
-            }
-        }
-        
         signal
     }
 }
\ No newline at end of file
@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{ensure, Result};
+use chrono::{DateTime, NaiveDate};
+use cex_core::aggregate::KlineAggregator;
+use cex_core::reader::{FileReader, KlineFilter};
+use cex_core::KlineInterval;
+use clap::Parser;
+use strategies::backtest::{load_klines, run_backtest, BacktestReport};
+use strategies::{StrategyConfig, StrategyInstance, StrategyRegistry};
+use tracing::info;
+
+/// 使用录制/下载的K线归档回测策略
+#[derive(Parser, Debug)]
+struct Args {
+    /// kline_*.zst 所在目录
+    #[arg(long, default_value = "data")]
+    data_dir: PathBuf,
+    /// 录制时使用的分区目录模板, 如 {exchange}/{symbol}/{interval}
+    #[arg(long)]
+    partition: Option<String>,
+    /// 注册表中的策略名, 如 bandtastic / multi_time_frame_macd
+    #[arg(long)]
+    strategy: String,
+    /// 策略参数 json 文件, 格式与 player 中的参数一致
+    #[arg(long)]
+    params: PathBuf,
+    /// 只回测指定交易所的数据, 与实时运行端一样一次只用一个交易所
+    #[arg(long, default_value = "binance")]
+    exchange: String,
+    /// 回测的交易对, 可重复, 默认为数据中的全部交易对
+    #[arg(long)]
+    symbol: Vec<String>,
+    /// 只加载指定周期, 可重复; 未配置 resample 时也是策略使用的周期, 默认为数据中的全部周期
+    #[arg(long)]
+    interval: Vec<KlineInterval>,
+    /// 用加载的K线合成指定周期后回测, 可重复, 与 player 的 resample 配置一致
+    #[arg(long)]
+    resample: Vec<KlineInterval>,
+    /// 合成时按 UTC+N 的日期对齐
+    #[arg(long, default_value_t = 0)]
+    resample_utc_offset: i32,
+    /// 开始日期（UTC）
+    #[arg(long)]
+    start: Option<NaiveDate>,
+    /// 结束日期（UTC, 不含）
+    #[arg(long)]
+    end: Option<NaiveDate>,
+}
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt().init();
+    let args = Args::parse();
+
+    let date_ms = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_millis() as u64;
+    let filter = KlineFilter {
+        exchange: Some(args.exchange),
+        symbols: args.symbol.clone(),
+        intervals: args.interval.clone(),
+        start_ms: args.start.map(date_ms),
+        end_ms: args.end.map(date_ms),
+    };
+
+    let klines = load_klines(&FileReader::new(&args.data_dir).with_partition(args.partition), &filter)?;
+    info!("加载K线 {} 条", klines.len());
+    let klines = if args.resample.is_empty() {
+        klines
+    } else {
+        let klines = KlineAggregator::new(&args.resample)
+            .with_utc_offset(args.resample_utc_offset)
+            .resample(klines)
+            .collect::<Vec<_>>();
+        info!("合成K线 {} 条", klines.len());
+        klines
+    };
+
+    // 与 player 的 [[strategy]] 配置相同, 未指定的交易对和周期取自数据
+    let symbols = if args.symbol.is_empty() {
+        distinct(klines.iter().map(|k| k.symbol.to_lowercase()).collect())
+    } else {
+        args.symbol
+    };
+    let intervals = if !args.resample.is_empty() {
+        args.resample
+    } else if !args.interval.is_empty() {
+        args.interval
+    } else {
+        distinct(klines.iter().map(|k| k.interval).collect())
+    };
+    ensure!(!klines.is_empty(), "没有符合条件的K线");
+    let config = StrategyConfig {
+        name: args.strategy,
+        label: None,
+        symbols,
+        intervals,
+        params: serde_json::from_str(&fs::read_to_string(&args.params)?)?,
+    };
+    info!("回测 {}: {:?} {:?}", config.name, config.symbols, config.intervals);
+    let mut instance = StrategyInstance::new(&StrategyRegistry::default(), &config)?;
+    let report = run_backtest(&mut instance, klines);
+    print_report(&report);
+    Ok(())
+}
+
+fn distinct<T: Ord>(mut values: Vec<T>) -> Vec<T> {
+    values.sort();
+    values.dedup();
+    values
+}
+
+fn print_report(report: &BacktestReport) {
+    let fmt_time = |ms: i64| {
+        DateTime::from_timestamp_millis(ms)
+            .map(|t| t.with_timezone(&chrono::FixedOffset::east_opt(8 * 3600).unwrap()).format("%Y%m%d-%H:%M").to_string())
+            .unwrap_or_default()
+    };
+
+    for trade in &report.trades {
+        println!(
+            "{} {} {:?} {} @ {:.4} -> {} @ {:.4} {:?} roi {:.3}%",
+            trade.exchange,
+            trade.symbol,
+            trade.direction,
+            fmt_time(trade.enter_time),
+            trade.enter_position.as_ref().map(|p| p.price).unwrap_or_default(),
+            fmt_time(trade.exit_time),
+            trade.exit_position.as_ref().map(|p| p.price).unwrap_or_default(),
+            trade.exit_reason,
+            trade.roi.unwrap_or_default(),
+        );
+    }
+    for trade in &report.open_trades {
+        println!("未平仓: {} {} {:?} {}", trade.exchange, trade.symbol, trade.direction, fmt_time(trade.enter_time));
+    }
+
+    let stats = &report.stats;
+    println!("交易数: {}", stats.total_trades);
+    println!("总收益: {:.2}%", stats.total_return);
+    println!("胜率: {:.2}%", stats.win_rate * 100.0);
+    println!("最大回撤: {:.2}%", stats.max_drawdown);
+    println!("夏普比率（单笔）: {:.3}", stats.sharpe);
+    println!("平均持仓: {:.1} 分钟", stats.avg_holding_ms / 60_000.0);
+}
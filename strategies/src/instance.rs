@@ -0,0 +1,92 @@
+//! 按配置创建的策略实例, 实时运行端和回测共用同一套K线路由
+
+use cex_core::structure::Trade;
+use cex_core::{KlineInterval, SimpleKLine};
+use serde::Deserialize;
+
+use crate::{DynStrategyRunner, StrategyRegistry};
+
+/// 一个策略实例的配置, 对应 player sub.toml 中的 `[[strategy]]`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyConfig {
+    /// 注册表中的策略名, 如 multi_time_frame_macd
+    pub name: String,
+    /// webhook 消息中的策略名, 不配置时使用 name
+    #[serde(default)]
+    pub label: Option<String>,
+    /// 使用的交易对, 每个交易对一个独立的实例
+    pub symbols: Vec<String>,
+    /// 使用的周期, 配置 resample 时为合成后的周期
+    pub intervals: Vec<KlineInterval>,
+    /// 策略参数, 格式与回测的参数文件一致
+    pub params: serde_json::Value,
+}
+
+impl StrategyConfig {
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    /// 检查需要的 (symbol, interval) 都在 inputs 中, inputs 为交给策略的K线流
+    pub fn check_inputs(&self, inputs: &[(String, KlineInterval)]) -> anyhow::Result<()> {
+        for symbol in &self.symbols {
+            for interval in &self.intervals {
+                let found = inputs.iter().any(|(s, i)| s.eq_ignore_ascii_case(symbol) && i == interval);
+                anyhow::ensure!(found, "策略 {} 需要的K线未订阅: {} {}", self.label(), symbol, interval);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 按 [`StrategyConfig`] 创建的策略, 把匹配的K线交给对应交易对的 runner
+pub struct StrategyInstance {
+    label: String,
+    intervals: Vec<KlineInterval>,
+    runners: Vec<(String, Box<dyn DynStrategyRunner>)>,
+}
+
+impl StrategyInstance {
+    pub fn new(registry: &StrategyRegistry, config: &StrategyConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(!config.symbols.is_empty(), "策略 {} 未配置 symbols", config.label());
+        anyhow::ensure!(!config.intervals.is_empty(), "策略 {} 未配置 intervals", config.label());
+        let runners = config
+            .symbols
+            .iter()
+            .map(|symbol| Ok((symbol.clone(), registry.create(&config.name, config.params.clone())?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            label: config.label().to_string(),
+            intervals: config.intervals.clone(),
+            runners,
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// 交易对对应的 runner, 交易对不区分大小写
+    pub fn runner(&self, symbol: &str) -> Option<&dyn DynStrategyRunner> {
+        self.runners.iter().find(|(s, _)| s.eq_ignore_ascii_case(symbol)).map(|(_, runner)| runner.as_ref())
+    }
+
+    /// 全部 (交易对, runner), 按配置中的顺序
+    pub fn runners(&self) -> impl Iterator<Item = (&str, &dyn DynStrategyRunner)> {
+        self.runners.iter().map(|(symbol, runner)| (symbol.as_str(), runner.as_ref()))
+    }
+
+    /// 处理一根K线, 交易对或周期不匹配时忽略; 产生开仓或平仓时返回当时的交易快照
+    pub fn on_kline(&mut self, kline: &SimpleKLine, is_final: bool) -> Option<Trade> {
+        if !self.intervals.contains(&kline.interval) {
+            return None;
+        }
+        let (_, runner) = self.runners.iter_mut().find(|(s, _)| s.eq_ignore_ascii_case(&kline.symbol))?;
+        if is_final {
+            runner.on_bar(kline)
+        } else {
+            runner.on_partial_bar(kline)
+        }
+    }
+}
@@ -1,13 +1,83 @@
+pub mod backtest;
+pub mod instance;
+pub mod registry;
+pub mod runner;
 pub mod bandtastic;
 // Add new strategies here
 pub mod multi_time_frame_macd;
 
-pub use bandtastic::BandtasticStrategy;
+pub use bandtastic::{BandtasticParams, BandtasticStrategy};
 // Re-export new strategy types
-pub use multi_time_frame_macd::MultiTimeFrameMacdStrategy;
+pub use multi_time_frame_macd::{MultiTimeFrameMacdParams, MultiTimeFrameMacdStrategy};
+pub use instance::{StrategyConfig, StrategyInstance};
+pub use registry::{DynStrategyRunner, StrategyRegistry};
+pub use runner::StrategyRunner;
 
-use cex_core::{structure::Signal, SimpleKLine};
+use anyhow::Result;
+use cex_core::structure::{Position, Signal, Trade};
+use cex_core::SimpleKLine;
+use serde::de::DeserializeOwned;
+
+/// 策略参数, 一般从 json/toml 反序列化得到
+pub trait StrategyParams: DeserializeOwned {
+    /// 检查参数取值, 无效时返回错误
+    fn validate(&self) -> Result<()>;
+}
+
+/// 运行端提供给策略的状态（见 [`StrategyRunner`]）
+#[derive(Debug, Clone, Copy)]
+pub struct StrategyContext<'a> {
+    /// 运行端当前的交易, 未开仓时 `enter_position` 为 None
+    pub trade: &'a Trade,
+    /// 已成交的开仓/平仓快照, 按时间顺序
+    pub fills: &'a [Trade],
+}
+
+impl StrategyContext<'_> {
+    /// 当前持仓
+    pub fn position(&self) -> Option<&Position> {
+        self.trade.enter_position.as_ref()
+    }
+}
 
 pub trait Strategy {
-    fn next(&mut self, kline: SimpleKLine) -> Option<Signal>;
-}
\ No newline at end of file
+    type Params: StrategyParams;
+
+    /// 按参数创建策略, 参数无效时返回错误
+    fn from_params(params: Self::Params) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// 从 json 参数创建策略
+    fn from_value(params: serde_json::Value) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::from_params(serde_json::from_value(params)?)
+    }
+
+    /// 指标稳定所需的K线数量, 运行端先收集这么多K线交给 [`Strategy::on_start`], 之后才处理信号
+    fn warmup_bars(&self) -> usize {
+        0
+    }
+
+    /// 开始运行前用历史K线预热, 默认依次交给 `on_bar` 并丢弃信号
+    fn on_start(&mut self, history: &[SimpleKLine]) {
+        let trade = Trade::default();
+        let ctx = StrategyContext { trade: &trade, fills: &[] };
+        for kline in history {
+            let _ = self.on_bar(kline, &ctx);
+        }
+    }
+
+    /// 已收盘的K线
+    fn on_bar(&mut self, kline: &SimpleKLine, ctx: &StrategyContext) -> Option<Signal>;
+
+    /// 未收盘K线的更新, 只在订阅开启 partial 时调用, 默认忽略
+    fn on_partial_bar(&mut self, _kline: &SimpleKLine, _ctx: &StrategyContext) -> Option<Signal> {
+        None
+    }
+
+    /// 运行端按信号开仓或平仓后回调, fill 为当时的交易快照（平仓时 `exit_position` 不为 None）
+    fn on_fill(&mut self, _fill: &Trade) {}
+}
@@ -1,56 +1,105 @@
 use ta::indicators::MovingAverageConvergenceDivergence;
 use ta::Next;
 use std::collections::VecDeque;
-use cex_core::SimpleKLine;
+use cex_core::{KlineInterval, SimpleKLine};
 use cex_core::structure::{Signal, Position, Direction, ExitReason};
+use anyhow::{ensure, Result};
 use tracing::error;
 use serde::{Deserialize, Serialize};
 
-use crate::Strategy;
+use crate::{Strategy, StrategyContext, StrategyParams};
 
-/// Multi-timeframe MACD strategy with breakeven stop loss optimization
+/// [`MultiTimeFrameMacdStrategy`] 的参数, 全部必填, 未知字段报错
 #[derive(Clone, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MultiTimeFrameMacdParams {
+    pub fast_length: usize,
+    pub slow_length: usize,
+    pub signal_length: usize,
+    /// 入场信号的周期, 如 1h
+    pub short_trend_time: KlineInterval,
+    /// 判断趋势的周期, 如 4h
+    pub long_trend_time: KlineInterval,
+    pub stop_loss_perc: f64,
+    pub take_profit_perc: f64,
+    pub breakeven_threshold: f64,
+    pub trail_offset: f64,
+}
+
+impl StrategyParams for MultiTimeFrameMacdParams {
+    fn validate(&self) -> Result<()> {
+        ensure!(self.fast_length > 0 && self.signal_length > 0, "MACD lengths must be positive");
+        ensure!(
+            self.fast_length < self.slow_length,
+            "fast_length {} must be less than slow_length {}",
+            self.fast_length,
+            self.slow_length
+        );
+        ensure!(
+            self.short_trend_time < self.long_trend_time,
+            "short_trend_time {} must be shorter than long_trend_time {}",
+            self.short_trend_time,
+            self.long_trend_time
+        );
+        for (name, perc) in [
+            ("stop_loss_perc", self.stop_loss_perc),
+            ("take_profit_perc", self.take_profit_perc),
+            ("breakeven_threshold", self.breakeven_threshold),
+            ("trail_offset", self.trail_offset),
+        ] {
+            ensure!(perc >= 0.0, "{} must not be negative: {}", name, perc);
+        }
+        Ok(())
+    }
+}
+
+/// Multi-timeframe MACD strategy with breakeven stop loss optimization
+///
+/// 通过 [`MultiTimeFrameMacdParams`] 创建（`Strategy::from_params` 或 `TryFrom`）
+#[derive(Clone, Debug)]
 pub struct MultiTimeFrameMacdStrategy {
-    short_trend_time: String,
-    long_trend_time: String, // Time frame for long-term trend analysis (e.g., "240" for 4-hour)
+    short_trend_time: KlineInterval,
+    long_trend_time: KlineInterval, // Time frame for long-term trend analysis (e.g., 4h)
 
     // Stop loss and take profit parameters
     stop_loss_perc: f64,      // Initial stop loss percentage
     // TODO: 此部分逻辑未实现
+    #[allow(dead_code)]
     take_profit_perc: f64,    // Initial take profit percentage
     breakeven_threshold: f64, // Percentage at which breakeven is triggered
     trail_offset: f64,        // Trail offset after breakeven
 
     // MACD indicators for different time frames
-    #[serde(skip)]
     macd_4h: MovingAverageConvergenceDivergence,
-    #[serde(skip)]
     macd_1h: MovingAverageConvergenceDivergence,
+    // 各周期最近一次的 (macd, signal, histogram), 另一个周期的K线到达时沿用
+    last_4h: (f64, f64, f64),
+    last_1h: (f64, f64, f64),
 
-    // State variables
-    position: Option<Position>,
-    entry_price: Option<f64>,
-    #[serde(skip)]
+    // State variables, 持仓从 StrategyContext 读取
     breakeven_activated: bool,
-    #[serde(skip)]
-    bar_index: usize,
-    #[serde(skip)]
     price_history: VecDeque<f64>,
+    warmup_bars: usize,
 }
 
 impl MultiTimeFrameMacdStrategy {
-    pub fn new(
+    /// 参数须已通过 [`StrategyParams::validate`], 外部通过 `TryFrom` 或 `Strategy::from_params` 创建
+    #[allow(clippy::too_many_arguments)]
+    fn new(
         fast_length: usize, // 12
         slow_length: usize, // 26
         signal_length: usize,   // 9
-        short_trend_time: String,   // "60m"
-        long_trend_time: String,    // "240m"
+        short_trend_time: KlineInterval,    // 1h
+        long_trend_time: KlineInterval,     // 4h
         stop_loss_perc: f64,        // 1.9
         take_profit_perc: f64,      // 5.4
         breakeven_threshold: f64,   // 1.0
         trail_offset: f64,          // 0.5
-    ) -> Self {
-        MultiTimeFrameMacdStrategy {
+    ) -> Result<Self> {
+        // 两个周期合计: 大周期需要 slow + signal 根, 同时收到的小周期K线按周期长度比例计算
+        let ratio = (long_trend_time.duration_ms() / short_trend_time.duration_ms().max(1)) as usize;
+        let warmup_bars = (slow_length + signal_length) * (ratio + 1);
+        Ok(MultiTimeFrameMacdStrategy {
             short_trend_time,
             long_trend_time,
             stop_loss_perc,
@@ -58,56 +107,70 @@ impl MultiTimeFrameMacdStrategy {
             breakeven_threshold,
             trail_offset,
             // Initialize MACD indicators for different time frames
-            macd_4h: MovingAverageConvergenceDivergence::new(fast_length, slow_length, signal_length).unwrap(),
-            macd_1h: MovingAverageConvergenceDivergence::new(fast_length, slow_length, signal_length).unwrap(),
+            macd_4h: MovingAverageConvergenceDivergence::new(fast_length, slow_length, signal_length)?,
+            macd_1h: MovingAverageConvergenceDivergence::new(fast_length, slow_length, signal_length)?,
+            last_4h: (f64::NAN, f64::NAN, f64::NAN),
+            last_1h: (f64::NAN, f64::NAN, f64::NAN),
             // Initialize state variables
-            position: None,
-            entry_price: None,
             breakeven_activated: false,
-            bar_index: 0,
             price_history: VecDeque::new(),
-        }
+            warmup_bars,
+        })
+    }
+}
+
+impl TryFrom<MultiTimeFrameMacdParams> for MultiTimeFrameMacdStrategy {
+    type Error = anyhow::Error;
+
+    fn try_from(params: MultiTimeFrameMacdParams) -> Result<Self> {
+        params.validate()?;
+        Self::new(
+            params.fast_length,
+            params.slow_length,
+            params.signal_length,
+            params.short_trend_time,
+            params.long_trend_time,
+            params.stop_loss_perc,
+            params.take_profit_perc,
+            params.breakeven_threshold,
+            params.trail_offset,
+        )
     }
 }
 
 impl Strategy for MultiTimeFrameMacdStrategy {
-    fn next(&mut self, kline: SimpleKLine) -> Option<Signal> {
+    type Params = MultiTimeFrameMacdParams;
+
+    fn from_params(params: MultiTimeFrameMacdParams) -> Result<Self> {
+        Self::try_from(params)
+    }
+
+    fn warmup_bars(&self) -> usize {
+        self.warmup_bars
+    }
+
+    fn on_bar(&mut self, kline: &SimpleKLine, ctx: &StrategyContext) -> Option<Signal> {
         // Skip if the kline interval is not supported
-        if kline.interval != "60m" && kline.interval != self.long_trend_time {
+        if kline.interval != self.short_trend_time && kline.interval != self.long_trend_time {
             error!("Unsupported kline interval: {}, need short term: {},need long term: {}", kline.interval, self.short_trend_time, self.long_trend_time);
             return None;
         }
-        let close = kline.close;
-
-        // 只有小周期才增加bar_index
-        if kline.interval == "60m" {
-            self.bar_index += 1;
-        }
+        let close = kline.close_f64();
 
-        let mut macd_4h = f64::NAN;
-        let mut signal_4h = f64::NAN;
-        let mut hist_4h  = f64::NAN;
-        let mut macd_1h = f64::NAN;
-        let mut signal_1h = f64::NAN;
-        let mut hist_1h  = f64::NAN;
         // Update indicators based on the time frame
-        match kline.interval.as_str() {
-            "240m" => {
+        match kline.interval {
+            interval if interval == self.long_trend_time => {
                 // Update 4-hour MACD indicators
-                let output = self.macd_4h.next(close);
-                macd_4h = output.macd;
-                signal_4h = output.signal;
-                hist_4h = output.histogram;
+                self.last_4h = self.macd_4h.next(close).into();
             },
-            "60m" => {
+            interval if interval == self.short_trend_time => {
                 // Update 1-hour MACD indicators
-                let output = self.macd_1h.next(close);
-                macd_1h = output.macd;
-                signal_1h = output.signal;
-                hist_1h = output.histogram;
+                self.last_1h = self.macd_1h.next(close).into();
             },
             _ => { /* Ignore other time frames */ }
         }
+        let (macd_4h, signal_4h, hist_4h) = self.last_4h;
+        let (macd_1h, signal_1h, hist_1h) = self.last_1h;
 
         // Store price for trailing stop calculation
         self.price_history.push_back(close);
@@ -127,36 +190,40 @@ impl Strategy for MultiTimeFrameMacdStrategy {
         let long_exit = !hist_4h.is_nan() && macd_4h < signal_4h;
         let short_exit = !hist_4h.is_nan() && macd_4h > signal_4h;
 
-        // Track entry price and manage breakeven activation
-        if let Some(position) = &self.position {
-            if self.entry_price.is_none() {
-                self.entry_price = Some(position.price);
-            }
+        // 运行端的持仓, 空头的 size 记为负数
+        let position = ctx.position().map(|position| Position {
+            size: match ctx.trade.direction {
+                Direction::Short => -position.size,
+                _ => position.size,
+            },
+            ..position.clone()
+        });
+        let entry_price = position.as_ref().map(|position| position.price);
 
-            // Check if we've reached the breakeven threshold
-            if let Some(entry_price) = self.entry_price {
+        // Manage breakeven activation
+        match &position {
+            Some(position) => {
                 let long_position = position.size > 0.0;
-                
-                if long_position && !self.breakeven_activated {
-                    if close >= entry_price * (1.0 + self.breakeven_threshold / 100.0) {
-                        // Activate breakeven
-                        self.breakeven_activated = true;
-                    }
-                } else if !long_position && !self.breakeven_activated {
-                    if close <= entry_price * (1.0 - self.breakeven_threshold / 100.0) {
-                        // Activate breakeven
-                        self.breakeven_activated = true;
-                    }
+                let threshold_reached = if long_position {
+                    close >= position.price * (1.0 + self.breakeven_threshold / 100.0)
+                } else {
+                    close <= position.price * (1.0 - self.breakeven_threshold / 100.0)
+                };
+
+                if !self.breakeven_activated && threshold_reached {
+                    // Activate breakeven
+                    self.breakeven_activated = true;
                 }
             }
+            // 平仓后重置, 下一次开仓重新判断
+            None => self.breakeven_activated = false,
         }
 
         // Generate exit signals based on dynamic conditions
         let mut signal = None;
 
         // Check for exit conditions first
-        if self.breakeven_activated && self.position.is_some() && self.entry_price.is_some() {
-            let entry_price = self.entry_price.unwrap();
+        if let Some(entry_price) = entry_price.filter(|_| self.breakeven_activated) {
             let trail_stop_price = if self.breakeven_activated {
                 if entry_price > 0.0 {
                     entry_price * (1.0 + self.trail_offset / 100.0)
@@ -169,38 +236,27 @@ impl Strategy for MultiTimeFrameMacdStrategy {
             
 
             // Regular stop loss
-            if let Some(position) = &self.position {
-                if position.size > 0.0 && close <= trail_stop_price {
-                    signal = Some(Signal::Exit {
-                        reason: ExitReason::TrailingStop,
-                        price: close,
-                    });
-                } else if position.size < 0.0 && close >= trail_stop_price {
-                    signal = Some(Signal::Exit {
-                        reason: ExitReason::TrailingStop,
-                        price: close,
-                    });
-                }
-            }
-        }
-
-        // Check for trend reversal exits
-        if let Some(position) = &self.position {
-            if position.size > 0.0 && long_exit {
+            if let Some(position) = &position
+                && ((position.size > 0.0 && close <= trail_stop_price)
+                    || (position.size < 0.0 && close >= trail_stop_price)) {
                 signal = Some(Signal::Exit {
-                    reason: ExitReason::StopProfit,
-                    price: close,
-                });
-            } else if position.size < 0.0 && short_exit {
-                signal = Some(Signal::Exit {
-                    reason: ExitReason::StopProfit,
+                    reason: ExitReason::TrailingStop,
                     price: close,
                 });
             }
         }
 
+        // Check for trend reversal exits
+        if let Some(position) = &position
+            && ((position.size > 0.0 && long_exit) || (position.size < 0.0 && short_exit)) {
+            signal = Some(Signal::Exit {
+                reason: ExitReason::StopProfit,
+                price: close,
+            });
+        }
+
         // Generate entry signals only if we don't have a position
-        if self.position.is_none() {
+        if position.is_none() {
             if long_entry {
                 signal = Some(Signal::Enter {
                     direction: Direction::Long,
@@ -214,29 +270,6 @@ impl Strategy for MultiTimeFrameMacdStrategy {
             }
         }
 
-        // Update position based on signal
-        if let Some(signal) = &signal {
-            match signal {
-                Signal::Enter { direction, price } => {
-                    let size = match *direction {
-                        Direction::Long => 1.0,
-                        Direction::Short => -1.0,
-                        _ => panic!("Invalid direction"),
-                    };
-                    self.position = Some(Position {
-                        price: *price,
-                        entry_bar_index: self.bar_index,
-                        size, // Assuming full position size
-                    });
-                    self.entry_price = Some(*price);
-                    self.breakeven_activated = false;
-                },
-                Signal::Exit { .. } => {
-                    self.position = None;
-                },
-            }
-        }
-
         signal
     }
 }
\ No newline at end of file
@@ -0,0 +1,108 @@
+//! 按名称创建策略, 供运行端根据配置动态加载
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use cex_core::structure::Trade;
+use cex_core::SimpleKLine;
+
+use crate::{BandtasticStrategy, MultiTimeFrameMacdStrategy, Strategy, StrategyRunner};
+
+/// 类型擦除后的 [`StrategyRunner`], 不同策略可以放在同一个列表中运行
+pub trait DynStrategyRunner: Send {
+    /// 处理已收盘K线, 见 [`StrategyRunner::on_bar`]
+    fn on_bar(&mut self, kline: &SimpleKLine) -> Option<Trade>;
+
+    /// 处理未收盘K线的更新, 见 [`StrategyRunner::on_partial_bar`]
+    fn on_partial_bar(&mut self, kline: &SimpleKLine) -> Option<Trade>;
+
+    fn warmup_bars(&self) -> usize;
+
+    fn trade(&self) -> &Trade;
+
+    fn fills(&self) -> &[Trade];
+
+    fn is_started(&self) -> bool;
+}
+
+impl<S: Strategy + Send> DynStrategyRunner for StrategyRunner<S> {
+    fn on_bar(&mut self, kline: &SimpleKLine) -> Option<Trade> {
+        StrategyRunner::on_bar(self, kline)
+    }
+
+    fn on_partial_bar(&mut self, kline: &SimpleKLine) -> Option<Trade> {
+        StrategyRunner::on_partial_bar(self, kline)
+    }
+
+    fn warmup_bars(&self) -> usize {
+        self.strategy().warmup_bars()
+    }
+
+    fn trade(&self) -> &Trade {
+        StrategyRunner::trade(self)
+    }
+
+    fn fills(&self) -> &[Trade] {
+        StrategyRunner::fills(self)
+    }
+
+    fn is_started(&self) -> bool {
+        StrategyRunner::is_started(self)
+    }
+}
+
+/// 用 json 参数创建策略实例
+pub type StrategyFactory = fn(serde_json::Value) -> Result<Box<dyn DynStrategyRunner>>;
+
+fn create<S: Strategy + Send + 'static>(params: serde_json::Value) -> Result<Box<dyn DynStrategyRunner>> {
+    Ok(Box::new(StrategyRunner::new(S::from_value(params)?)))
+}
+
+/// 策略名到构造函数的映射, `default()` 包含本 crate 中的全部策略
+pub struct StrategyRegistry {
+    factories: BTreeMap<String, StrategyFactory>,
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register::<BandtasticStrategy>("bandtastic");
+        registry.register::<MultiTimeFrameMacdStrategy>("multi_time_frame_macd");
+        // Register new strategies here
+        registry
+    }
+}
+
+impl StrategyRegistry {
+    /// 空的注册表
+    pub fn new() -> Self {
+        Self { factories: BTreeMap::new() }
+    }
+
+    /// 注册策略, 同名时覆盖
+    pub fn register<S: Strategy + Send + 'static>(&mut self, name: &str) -> &mut Self {
+        self.register_factory(name, create::<S>)
+    }
+
+    pub fn register_factory(&mut self, name: &str, factory: StrategyFactory) -> &mut Self {
+        self.factories.insert(name.to_string(), factory);
+        self
+    }
+
+    /// 已注册的策略名, 按字母顺序
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// 按名称和参数创建策略实例, 名称未注册或参数无效时返回错误
+    pub fn create(&self, name: &str, params: serde_json::Value) -> Result<Box<dyn DynStrategyRunner>> {
+        let factory = self.factories.get(name).ok_or_else(|| {
+            anyhow!("unknown strategy: {} (available: {})", name, self.names().collect::<Vec<_>>().join(", "))
+        })?;
+        factory(params).map_err(|e| e.context(format!("invalid params for strategy {}", name)))
+    }
+}
@@ -0,0 +1,82 @@
+use cex_core::structure::{Signal, Trade};
+use cex_core::SimpleKLine;
+
+use crate::{Strategy, StrategyContext};
+
+/// 运行一个策略实例: 预热, 把K线交给策略, 按 [`Trade::on_signal`] 处理信号并回调 `on_fill`
+///
+/// 实时运行端和回测共用, 保证两者处理信号的方式一致
+pub struct StrategyRunner<S> {
+    strategy: S,
+    trade: Trade,
+    fills: Vec<Trade>,
+    /// 预热阶段收集的K线, 开始运行后为 None
+    history: Option<Vec<SimpleKLine>>,
+}
+
+impl<S: Strategy> StrategyRunner<S> {
+    pub fn new(strategy: S) -> Self {
+        Self {
+            strategy,
+            trade: Trade::default(),
+            fills: Vec::new(),
+            history: Some(Vec::new()),
+        }
+    }
+
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    /// 当前交易, 未开仓时 `enter_position` 为 None
+    pub fn trade(&self) -> &Trade {
+        &self.trade
+    }
+
+    /// 已成交的开仓/平仓快照
+    pub fn fills(&self) -> &[Trade] {
+        &self.fills
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.history.is_none()
+    }
+
+    /// 用已有的历史K线预热并开始运行, 不再等待收集 `warmup_bars` 根K线
+    pub fn start(&mut self, history: &[SimpleKLine]) {
+        let mut collected = self.history.take().unwrap_or_default();
+        collected.extend_from_slice(history);
+        self.strategy.on_start(&collected);
+    }
+
+    /// 处理已收盘K线, 产生开仓或平仓时返回当时的交易快照
+    pub fn on_bar(&mut self, kline: &SimpleKLine) -> Option<Trade> {
+        if let Some(history) = &mut self.history {
+            if history.len() < self.strategy.warmup_bars() {
+                history.push(kline.clone());
+                return None;
+            }
+            self.start(&[]);
+        }
+        let ctx = StrategyContext { trade: &self.trade, fills: &self.fills };
+        let signal = self.strategy.on_bar(kline, &ctx)?;
+        self.fill(signal, kline)
+    }
+
+    /// 处理未收盘K线的更新, 预热完成前忽略
+    pub fn on_partial_bar(&mut self, kline: &SimpleKLine) -> Option<Trade> {
+        if !self.is_started() {
+            return None;
+        }
+        let ctx = StrategyContext { trade: &self.trade, fills: &self.fills };
+        let signal = self.strategy.on_partial_bar(kline, &ctx)?;
+        self.fill(signal, kline)
+    }
+
+    fn fill(&mut self, signal: Signal, kline: &SimpleKLine) -> Option<Trade> {
+        let fill = self.trade.on_signal(signal, kline)?;
+        self.strategy.on_fill(&fill);
+        self.fills.push(fill.clone());
+        Some(fill)
+    }
+}
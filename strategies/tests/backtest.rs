@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use cex_core::reader::{FileReader, KlineFilter};
+use cex_core::structure::{Direction, ExitReason, Signal, Trade};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+use serde::Deserialize;
+use serde_json::json;
+use strategies::backtest::{calculate_stats, load_klines, run_backtest};
+use strategies::{Strategy, StrategyConfig, StrategyContext, StrategyInstance, StrategyParams, StrategyRegistry};
+
+const T0: u64 = 1748877600000;
+
+#[derive(Deserialize)]
+struct Params {}
+
+impl StrategyParams for Params {
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// 空仓时开多, 持仓时平仓
+struct Flip;
+
+impl Strategy for Flip {
+    type Params = Params;
+
+    fn from_params(_params: Params) -> Result<Self> {
+        Ok(Self)
+    }
+
+    fn on_bar(&mut self, kline: &SimpleKLine, ctx: &StrategyContext) -> Option<Signal> {
+        let price = kline.close_f64();
+        Some(match ctx.position() {
+            None => Signal::Enter { direction: Direction::Long, price },
+            Some(_) => Signal::Exit { reason: ExitReason::StopProfit, price },
+        })
+    }
+}
+
+fn kline(exchange: &str, symbol: &str, interval: KlineInterval, i: u64, close: i64) -> SimpleKLine {
+    let ms = interval.duration_ms();
+    let price = Decimal::from(close);
+    SimpleKLine::new(exchange, symbol, T0 + i * ms, T0 + (i + 1) * ms - 1, interval, price, price, price, price, Decimal::ONE, 1)
+}
+
+fn trade(roi: f64, enter_time: i64, exit_time: i64) -> Trade {
+    Trade { roi: Some(roi), enter_time, exit_time, ..Default::default() }
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+}
+
+#[test]
+fn stats_from_trade_returns() {
+    let stats = calculate_stats(&[trade(10.0, 0, 60_000), trade(-5.0, 0, 180_000), trade(20.0, 0, 120_000)]);
+    assert_eq!(stats.total_trades, 3);
+    // 1.1 * 0.95 * 1.2
+    assert_close(stats.total_return, 25.4);
+    assert_close(stats.win_rate, 2.0 / 3.0);
+    // 净值从 1.1 回落到 1.045
+    assert_close(stats.max_drawdown, 5.0);
+    let (mean, std) = (0.25 / 3.0, ((0.1f64 - 0.25 / 3.0).powi(2) + (-0.05f64 - 0.25 / 3.0).powi(2) + (0.2f64 - 0.25 / 3.0).powi(2)) / 3.0);
+    assert_close(stats.sharpe, mean / std.sqrt());
+    assert_close(stats.avg_holding_ms, 120_000.0);
+
+    // 连续亏损的回撤从初始净值算起, 收益相同时标准差为 0
+    let stats = calculate_stats(&[trade(-10.0, 0, 0), trade(-10.0, 0, 0)]);
+    assert_close(stats.max_drawdown, 19.0);
+    assert_close(stats.win_rate, 0.0);
+    assert_close(stats.sharpe, 0.0);
+
+    let stats = calculate_stats(&[]);
+    assert_eq!(stats.total_trades, 0);
+    assert_close(stats.total_return, 0.0);
+}
+
+#[test]
+fn backtest_routes_like_live() {
+    let mut registry = StrategyRegistry::new();
+    registry.register::<Flip>("flip");
+    let config = StrategyConfig {
+        name: "flip".to_string(),
+        label: None,
+        symbols: vec!["btcusdt".to_string(), "ethusdt".to_string()],
+        intervals: vec![KlineInterval::OneMinute],
+        params: json!({}),
+    };
+    let mut instance = StrategyInstance::new(&registry, &config).unwrap();
+
+    let klines = vec![
+        kline("binance", "BTCUSDT", KlineInterval::OneMinute, 0, 100),
+        kline("binance", "ETHUSDT", KlineInterval::OneMinute, 0, 10),
+        // 未配置的周期和交易对不交给策略
+        kline("binance", "BTCUSDT", KlineInterval::FiveMinutes, 0, 1),
+        kline("binance", "SOLUSDT", KlineInterval::OneMinute, 0, 1),
+        kline("binance", "BTCUSDT", KlineInterval::OneMinute, 1, 110),
+        kline("binance", "ETHUSDT", KlineInterval::OneMinute, 1, 9),
+        kline("binance", "BTCUSDT", KlineInterval::OneMinute, 2, 120),
+    ];
+    let report = run_backtest(&mut instance, klines);
+
+    // 每个交易对独立持仓
+    assert_eq!(report.trades.len(), 2);
+    let btc = &report.trades[0];
+    assert_eq!(btc.symbol, "BTCUSDT");
+    assert_eq!(btc.enter_position.as_ref().unwrap().price, 100.0);
+    assert_eq!(btc.exit_position.as_ref().unwrap().price, 110.0);
+    assert_close(btc.roi.unwrap(), 10.0 - btc.fee * 100.0);
+    let eth = &report.trades[1];
+    assert_eq!(eth.symbol, "ETHUSDT");
+    assert_close(eth.roi.unwrap(), -10.0 - eth.fee * 100.0);
+
+    assert_eq!(report.open_trades.len(), 1);
+    assert_eq!(report.open_trades[0].symbol, "BTCUSDT");
+    assert_eq!(report.open_trades[0].enter_position.as_ref().unwrap().price, 120.0);
+    assert_eq!(report.stats.total_trades, 2);
+    assert_close(report.stats.win_rate, 0.5);
+}
+
+fn write_archive(dir: &Path, name: &str, klines: &[SimpleKLine]) {
+    let lines = klines.iter().map(|k| serde_json::to_string(k).unwrap() + "\n").collect::<String>();
+    fs::write(dir.join(name), zstd::stream::encode_all(lines.as_bytes(), 0).unwrap()).unwrap();
+}
+
+#[test]
+fn load_klines_merges_and_dedups() {
+    let dir = std::env::temp_dir().join(format!("strategies-backtest-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let btc = |i| kline("binance", "BTCUSDT", KlineInterval::OneMinute, i, 100);
+    let eth = |i| kline("binance", "ETHUSDT", KlineInterval::OneMinute, i, 10);
+    let btc_5m = kline("binance", "BTCUSDT", KlineInterval::FiveMinutes, 0, 100);
+    let okx = kline("okx", "BTCUSDT", KlineInterval::OneMinute, 0, 100);
+    // 重复的记录不相邻: 中间隔着其它交易对和交易所, 或在另一个文件中
+    write_archive(&dir, "kline_20250602-0800.zst", &[btc(0), eth(0), okx.clone(), btc(0), btc(4), btc_5m.clone(), eth(0)]);
+    write_archive(&dir, "kline_20250602-1600.zst", &[btc(4), btc(0), btc(5)]);
+
+    let klines = load_klines(&FileReader::new(&dir), &KlineFilter::default()).unwrap();
+    let key = |k: &SimpleKLine| (k.exchange.clone(), k.symbol.clone(), k.interval, k.open_time_ms);
+    // 按收盘时间排序, 同时收盘时周期短的在前
+    assert_eq!(
+        klines.iter().map(key).collect::<Vec<_>>(),
+        [&btc(0), &eth(0), &okx, &btc(4), &btc_5m, &btc(5)].map(key)
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
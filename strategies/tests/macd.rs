@@ -0,0 +1,84 @@
+use cex_core::structure::{Direction, Position, Signal, Trade};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+use strategies::{MultiTimeFrameMacdParams, MultiTimeFrameMacdStrategy, Strategy, StrategyContext, StrategyRunner};
+
+const HOUR: u64 = 3600 * 1000;
+
+fn params() -> MultiTimeFrameMacdParams {
+    MultiTimeFrameMacdParams {
+        fast_length: 3,
+        slow_length: 6,
+        signal_length: 2,
+        short_trend_time: KlineInterval::OneHour,
+        long_trend_time: KlineInterval::FourHours,
+        stop_loss_perc: 1.9,
+        take_profit_perc: 5.4,
+        breakeven_threshold: 50.0,
+        trail_offset: 0.5,
+    }
+}
+
+fn kline(interval: KlineInterval, open_time: u64, close: f64) -> SimpleKLine {
+    let price = Decimal::try_from(close).unwrap();
+    SimpleKLine::new("binance", "BTCUSDT", open_time, open_time + interval.duration_ms() - 1, interval, price, price, price, price, Decimal::ONE, 1)
+}
+
+/// 每小时按 rate 变化的价格: 1h K线每小时一根, 4h K线在同一时刻收盘的 1h K线之后到达
+fn klines(rate: f64, hours: u64) -> Vec<SimpleKLine> {
+    let price = |hour: u64| 100.0 * rate.powi(hour as i32);
+    let mut klines = Vec::new();
+    for i in 0..hours {
+        klines.push(kline(KlineInterval::OneHour, i * HOUR, price(i + 1)));
+        if (i + 1) % 4 == 0 {
+            klines.push(kline(KlineInterval::FourHours, (i - 3) * HOUR, price(i + 1)));
+        }
+    }
+    klines
+}
+
+/// 返回第一次开仓的方向和开仓的小时数
+fn first_entry(klines: &[SimpleKLine]) -> Option<(Direction, u64)> {
+    let mut runner = StrategyRunner::new(MultiTimeFrameMacdStrategy::from_params(params()).unwrap());
+    runner.start(&[]);
+    klines.iter().find_map(|kline| {
+        let fill = runner.on_bar(kline)?;
+        fill.enter_position.as_ref()?;
+        Some((fill.direction, (kline.close_time_ms + 1) / HOUR))
+    })
+}
+
+#[test]
+fn enters_when_both_timeframes_agree() {
+    // 两个周期的 MACD 分别在各自的K线上更新, 另一个周期的K线到达时沿用上一次的值
+    let (direction, hour) = first_entry(&klines(1.01, 24)).unwrap();
+    assert!(matches!(direction, Direction::Long));
+    // 第一根 4h K线的 MACD 为 0, 第二根之后才有趋势
+    assert_eq!(hour, 8);
+
+    let (direction, hour) = first_entry(&klines(0.99, 24)).unwrap();
+    assert!(matches!(direction, Direction::Short));
+    assert_eq!(hour, 8);
+}
+
+#[test]
+fn needs_long_timeframe_trend() {
+    // 只有 1h K线时没有 4h 的趋势, 不开仓
+    let hours = klines(1.01, 24).into_iter().filter(|k| k.interval == KlineInterval::OneHour).collect::<Vec<_>>();
+    assert!(first_entry(&hours).is_none());
+}
+
+#[test]
+fn reads_position_from_context() {
+    // 持仓只由运行端通过 StrategyContext 提供, 策略不需要 on_fill 回调
+    let trade = Trade {
+        direction: Direction::Long,
+        enter_position: Some(Position { price: 100.0, entry_bar_index: 0, size: 1.0 }),
+        ..Default::default()
+    };
+    let ctx = StrategyContext { trade: &trade, fills: &[] };
+    let mut strategy = MultiTimeFrameMacdStrategy::from_params(params()).unwrap();
+    let signals = klines(0.99, 24).iter().filter_map(|kline| strategy.on_bar(kline, &ctx)).collect::<Vec<_>>();
+    assert!(!signals.is_empty());
+    // 持有多头时下跌趋势只产生平仓信号
+    assert!(signals.iter().all(|signal| matches!(signal, Signal::Exit { .. })));
+}
@@ -0,0 +1,144 @@
+use cex_core::structure::{Position, Trade};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+use serde_json::{json, Value};
+use strategies::{BandtasticParams, BandtasticStrategy, MultiTimeFrameMacdStrategy, Strategy, StrategyContext};
+
+fn bandtastic() -> Value {
+    json!({
+        "buy_fast_ema_period": 20,
+        "buy_slow_ema_period": 40,
+        "buy_rsi_threshold": 50.0,
+        "buy_mfi_threshold": 30.0,
+        "buy_rsi_enabled": true,
+        "buy_mfi_enabled": true,
+        "buy_ema_enabled": true,
+        "buy_trigger": "bb_lower1",
+        "sell_fast_ema_period": 7,
+        "sell_slow_ema_period": 88,
+        "sell_rsi_threshold": 57.0,
+        "sell_mfi_threshold": 46.0,
+        "sell_rsi_enabled": false,
+        "sell_mfi_enabled": true,
+        "sell_ema_enabled": true,
+        "sell_trigger": "sell-bb_upper2",
+        "min_roi": [[0, 0.162], [69, 0.097], [229, 0.061], [566, 0.0]],
+        "stoploss": -0.345,
+        "trailing_stop": true,
+        "trailing_stop_positive": 0.01,
+        "trailing_stop_positive_offset": 0.058,
+        "trailing_only_offset_is_reached": false,
+    })
+}
+
+fn macd() -> Value {
+    json!({
+        "fast_length": 12,
+        "slow_length": 30,
+        "signal_length": 7,
+        "short_trend_time": "1h",
+        "long_trend_time": "4h",
+        "stop_loss_perc": 1.9,
+        "take_profit_perc": 5.4,
+        "breakeven_threshold": 1.0,
+        "trail_offset": 0.5,
+    })
+}
+
+fn with(mut value: Value, key: &str, field: Value) -> Value {
+    value[key] = field;
+    value
+}
+
+fn without(mut value: Value, key: &str) -> Value {
+    value.as_object_mut().unwrap().remove(key);
+    value
+}
+
+fn kline(interval: KlineInterval, open_time: u64, close: f64) -> SimpleKLine {
+    let price = Decimal::try_from(close).unwrap();
+    let (high, low) = (Decimal::try_from(close * 1.001).unwrap(), Decimal::try_from(close * 0.999).unwrap());
+    SimpleKLine::new("binance", "BTCUSDT", open_time, open_time + interval.duration_ms() - 1, interval, price, high, low, price, Decimal::ONE, 1)
+}
+
+/// 第 i 根K线的收盘价: 两个周期叠加的波动
+fn price(i: u64) -> f64 {
+    let i = i as f64;
+    100.0 + 5.0 * (i / 7.0).sin() + 3.0 * (i / 3.0).sin()
+}
+
+/// 依次输入K线, 返回每个信号对应的K线序号和内容
+fn signals<S: Strategy>(mut strategy: S, klines: &[SimpleKLine], trade: &Trade) -> Vec<String> {
+    let ctx = StrategyContext { trade, fills: &[] };
+    klines.iter().enumerate().filter_map(|(i, kline)| Some(format!("{} {:?}", i, strategy.on_bar(kline, &ctx)?))).collect()
+}
+
+#[test]
+fn indicators_use_params() {
+    // 只改一个周期, 同样的K线产生的信号不同
+    let klines = (0..300).map(|i| kline(KlineInterval::FifteenMinutes, i * 900_000, price(i))).collect::<Vec<_>>();
+    // 只由 EMA 和布林带决定信号
+    let mut base = bandtastic();
+    for key in ["buy_rsi_enabled", "buy_mfi_enabled", "sell_rsi_enabled", "sell_mfi_enabled"] {
+        base[key] = json!(false);
+    }
+    base["trailing_stop"] = json!(false);
+    base["stoploss"] = json!(-0.99);
+    let bandtastic_signals = |params: Value, trade: &Trade| signals(BandtasticStrategy::from_value(params).unwrap(), &klines, trade);
+
+    // 空仓时只看开仓条件
+    let flat = Trade::default();
+    let entries = bandtastic_signals(base.clone(), &flat);
+    assert!(!entries.is_empty());
+    assert_ne!(bandtastic_signals(with(base.clone(), "buy_fast_ema_period", json!(5)), &flat), entries);
+    assert_ne!(bandtastic_signals(with(base.clone(), "buy_slow_ema_period", json!(10)), &flat), entries);
+
+    // 持仓价格足够高, 不会触发 ROI, 只看平仓条件
+    let holding = Trade { enter_position: Some(Position { price: 1000.0, entry_bar_index: 0, size: 1.0 }), ..Default::default() };
+    let exits = bandtastic_signals(base.clone(), &holding);
+    assert!(!exits.is_empty());
+    assert_ne!(bandtastic_signals(with(base.clone(), "sell_fast_ema_period", json!(30)), &holding), exits);
+
+    // 1h K线每 4 根之后跟一根同时收盘的 4h K线
+    let mut klines = Vec::new();
+    for i in 0..400 {
+        klines.push(kline(KlineInterval::OneHour, i * 3_600_000, price(i)));
+        if (i + 1) % 4 == 0 {
+            klines.push(kline(KlineInterval::FourHours, (i - 3) * 3_600_000, price(i)));
+        }
+    }
+    let macd_signals = |params: Value| signals(MultiTimeFrameMacdStrategy::from_value(params).unwrap(), &klines, &flat);
+    let entries = macd_signals(macd());
+    assert!(!entries.is_empty());
+    assert_ne!(macd_signals(with(macd(), "fast_length", json!(5))), entries);
+}
+
+#[test]
+fn bandtastic_exit_params_are_required() {
+    for key in ["min_roi", "stoploss", "trailing_stop", "trailing_stop_positive", "trailing_stop_positive_offset", "trailing_only_offset_is_reached"] {
+        assert!(serde_json::from_value::<BandtasticParams>(without(bandtastic(), key)).is_err(), "{}", key);
+    }
+
+    let params = serde_json::from_value::<BandtasticParams>(with(bandtastic(), "stoploss", json!(-0.1))).unwrap();
+    assert_eq!(params.stoploss, -0.1);
+    assert_eq!(params.min_roi, [(0, 0.162), (69, 0.097), (229, 0.061), (566, 0.0)]);
+    assert_eq!(BandtasticStrategy::try_from(params).unwrap().warmup_bars(), 88);
+}
+
+#[test]
+fn rejects_bad_params() {
+    // 拼错的字段名
+    assert!(BandtasticStrategy::from_value(with(bandtastic(), "buy_rsi_threshhold", json!(50.0))).is_err());
+    assert!(MultiTimeFrameMacdStrategy::from_value(with(macd(), "take_profit", json!(5.4))).is_err());
+
+    // 缺少必填字段
+    assert!(BandtasticStrategy::from_value(without(bandtastic(), "buy_trigger")).is_err());
+    assert!(MultiTimeFrameMacdStrategy::from_value(without(macd(), "slow_length")).is_err());
+
+    // 取值无效
+    assert!(BandtasticStrategy::from_value(with(bandtastic(), "buy_trigger", json!("bb_lower9"))).is_err());
+    assert!(BandtasticStrategy::from_value(with(bandtastic(), "buy_fast_ema_period", json!(0))).is_err());
+    assert!(BandtasticStrategy::from_value(with(bandtastic(), "stoploss", json!(0.1))).is_err());
+    assert!(MultiTimeFrameMacdStrategy::from_value(with(macd(), "fast_length", json!(30))).is_err());
+    assert!(MultiTimeFrameMacdStrategy::from_value(with(macd(), "long_trend_time", json!("15m"))).is_err());
+    assert!(MultiTimeFrameMacdStrategy::from_value(with(macd(), "short_trend_time", json!("1x"))).is_err());
+}
@@ -0,0 +1,101 @@
+use anyhow::Result;
+use cex_core::structure::{Direction, Signal};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+use serde::Deserialize;
+use serde_json::json;
+use strategies::{Strategy, StrategyContext, StrategyParams, StrategyRegistry};
+
+#[derive(Deserialize)]
+struct Params {
+    warmup: usize,
+}
+
+impl StrategyParams for Params {
+    fn validate(&self) -> Result<()> {
+        anyhow::ensure!(self.warmup < 10, "warmup too large");
+        Ok(())
+    }
+}
+
+/// 预热后第一根K线开多
+struct Enter {
+    warmup: usize,
+}
+
+impl Strategy for Enter {
+    type Params = Params;
+
+    fn from_params(params: Params) -> Result<Self> {
+        params.validate()?;
+        Ok(Self { warmup: params.warmup })
+    }
+
+    fn warmup_bars(&self) -> usize {
+        self.warmup
+    }
+
+    fn on_bar(&mut self, kline: &SimpleKLine, _ctx: &StrategyContext) -> Option<Signal> {
+        Some(Signal::Enter { direction: Direction::Long, price: kline.close_f64() })
+    }
+}
+
+fn kline(i: u64) -> SimpleKLine {
+    SimpleKLine {
+        exchange: "binance".to_string(),
+        symbol: "BTCUSDT".to_string(),
+        interval: KlineInterval::OneMinute,
+        open_time_ms: i * 60_000,
+        close_time_ms: (i + 1) * 60_000 - 1,
+        close: Decimal::from(100 + i),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn builtin_strategies() {
+    let registry = StrategyRegistry::default();
+    assert_eq!(registry.names().collect::<Vec<_>>(), ["bandtastic", "multi_time_frame_macd"]);
+
+    let params = json!({
+        "fast_length": 12,
+        "slow_length": 26,
+        "signal_length": 9,
+        "short_trend_time": "1h",
+        "long_trend_time": "4h",
+        "stop_loss_perc": 1.9,
+        "take_profit_perc": 5.4,
+        "breakeven_threshold": 1.0,
+        "trail_offset": 0.5,
+    });
+    let runner = registry.create("multi_time_frame_macd", params).unwrap();
+    assert_eq!(runner.warmup_bars(), 35 * 5);
+    assert!(!runner.is_started());
+
+    // 名称未注册或参数无效
+    let error = registry.create("macd", json!({})).err().unwrap();
+    assert!(error.to_string().contains("multi_time_frame_macd"));
+    assert!(registry.create("bandtastic", json!({})).is_err());
+}
+
+#[test]
+fn custom_strategy() {
+    let mut registry = StrategyRegistry::new();
+    assert!(!registry.contains("enter"));
+    registry.register::<Enter>("enter");
+    assert!(registry.contains("enter"));
+
+    assert!(registry.create("enter", json!({ "warmup": 20 })).is_err());
+
+    // 同一组参数创建的实例互相独立
+    let mut first = registry.create("enter", json!({ "warmup": 2 })).unwrap();
+    let mut second = registry.create("enter", json!({ "warmup": 2 })).unwrap();
+    assert!(first.on_bar(&kline(0)).is_none());
+    assert!(first.on_bar(&kline(1)).is_none());
+    let trade = first.on_bar(&kline(2)).unwrap();
+    assert_eq!(trade.enter_position.as_ref().unwrap().price, 102.0);
+    assert!(first.trade().enter_position.is_some());
+    assert_eq!(first.fills().len(), 1);
+
+    assert!(second.on_bar(&kline(3)).is_none());
+    assert!(second.trade().enter_position.is_none());
+}
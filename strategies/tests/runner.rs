@@ -0,0 +1,94 @@
+use anyhow::Result;
+use cex_core::structure::{Direction, ExitReason, Signal, Trade};
+use cex_core::{Decimal, KlineInterval, SimpleKLine};
+use serde::Deserialize;
+use strategies::{Strategy, StrategyContext, StrategyParams, StrategyRunner};
+
+#[derive(Deserialize)]
+struct Params {
+    warmup: usize,
+}
+
+impl StrategyParams for Params {
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// 每根K线都发出信号: 无持仓时开多, 有持仓时平仓, 记录收到的回调
+#[derive(Default)]
+struct Flip {
+    warmup: usize,
+    history: usize,
+    bars: Vec<u64>,
+    fills: usize,
+}
+
+impl Strategy for Flip {
+    type Params = Params;
+
+    fn from_params(params: Params) -> Result<Self> {
+        Ok(Self { warmup: params.warmup, ..Default::default() })
+    }
+
+    fn warmup_bars(&self) -> usize {
+        self.warmup
+    }
+
+    fn on_start(&mut self, history: &[SimpleKLine]) {
+        self.history = history.len();
+    }
+
+    fn on_bar(&mut self, kline: &SimpleKLine, ctx: &StrategyContext) -> Option<Signal> {
+        self.bars.push(kline.open_time_ms);
+        assert_eq!(ctx.fills.len(), self.fills);
+        let price = kline.close_f64();
+        Some(match ctx.position() {
+            None => Signal::Enter { direction: Direction::Long, price },
+            Some(_) => Signal::Exit { reason: ExitReason::StopProfit, price },
+        })
+    }
+
+    fn on_fill(&mut self, _fill: &Trade) {
+        self.fills += 1;
+    }
+}
+
+fn kline(i: u64) -> SimpleKLine {
+    SimpleKLine {
+        exchange: "binance".to_string(),
+        symbol: "BTCUSDT".to_string(),
+        interval: KlineInterval::OneMinute,
+        open_time_ms: i * 60_000,
+        close_time_ms: (i + 1) * 60_000 - 1,
+        close: Decimal::from(100 + i),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn warmup_then_fills() {
+    let strategy = Flip::from_value(serde_json::json!({ "warmup": 3 })).unwrap();
+    let mut runner = StrategyRunner::new(strategy);
+
+    // 预热期间不处理信号, 未收盘更新也被忽略
+    for i in 0..3 {
+        assert!(runner.on_bar(&kline(i)).is_none());
+        assert!(runner.on_partial_bar(&kline(i)).is_none());
+    }
+    assert!(!runner.is_started());
+
+    let enter = runner.on_bar(&kline(3)).unwrap();
+    assert!(runner.is_started());
+    assert_eq!(runner.strategy().history, 3);
+    assert!(enter.exit_position.is_none());
+    assert_eq!(runner.trade().enter_position.as_ref().unwrap().price, 103.0);
+
+    let exit = runner.on_bar(&kline(4)).unwrap();
+    assert_eq!(exit.exit_position.as_ref().unwrap().price, 104.0);
+    assert!(runner.trade().enter_position.is_none());
+
+    assert_eq!(runner.strategy().bars, [3 * 60_000, 4 * 60_000]);
+    assert_eq!(runner.strategy().fills, 2);
+    assert_eq!(runner.fills().len(), 2);
+}